@@ -16,6 +16,8 @@
 
 //! Predefined chains.
 
+use std::path::{Path, PathBuf};
+
 use service;
 
 /// The chain specification (this should eventually be replaced by a more general JSON-based chain
@@ -30,6 +32,8 @@ pub enum ChainSpec {
 	Alexander,
 	/// Whatever the current runtime is with the "global testnet" defaults.
 	StagingTestnet,
+	/// A local testnet described in a JSON or TOML file, with genesis parachains.
+	TestnetFile(PathBuf, service::chain_spec::TestnetSpec),
 }
 
 impl Default for ChainSpec {
@@ -46,18 +50,26 @@ impl ChainSpec {
 			ChainSpec::Development => service::chain_spec::development_config(),
 			ChainSpec::LocalTestnet => service::chain_spec::local_testnet_config(),
 			ChainSpec::StagingTestnet => service::chain_spec::staging_testnet_config(),
+			ChainSpec::TestnetFile(path, spec) =>
+				spec.into_chain_spec(path.parent().unwrap_or_else(|| Path::new(".")))?,
 		})
 	}
 
-	pub(crate) fn from(s: &str) -> Option<Self> {
-		match s {
+	pub(crate) fn from(s: &str) -> Result<Option<Self>, String> {
+		use service::chain_spec::TestnetSpec;
+
+		Ok(match s {
 			"dev" => Some(ChainSpec::Development),
 			"local" => Some(ChainSpec::LocalTestnet),
 			"poc-3" | "alex" | "alexander" => Some(ChainSpec::Alexander),
 			"staging" => Some(ChainSpec::StagingTestnet),
 			"" => Some(ChainSpec::default()),
+			s if s.ends_with(".toml") => Some(ChainSpec::TestnetFile(s.into(), TestnetSpec::from_file(Path::new(s))?)),
+			// other JSON files are left to be loaded as full chain specs.
+			s if s.ends_with(".json") => TestnetSpec::from_file(Path::new(s)).ok()
+				.map(|spec| ChainSpec::TestnetFile(s.into(), spec)),
 			_ => None,
-		}
+		})
 	}
 }
 
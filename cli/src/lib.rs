@@ -25,11 +25,16 @@ extern crate tokio;
 extern crate substrate_cli as cli;
 extern crate polkadot_service as service;
 extern crate exit_future;
+extern crate serde_json;
+extern crate libp2p;
 
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate structopt;
 
 mod chain_spec;
+mod testnet;
 
 use std::ops::Deref;
 use chain_spec::ChainSpec;
@@ -40,14 +45,17 @@ use cli::NoCustom;
 
 pub use service::{
 	Components as ServiceComponents, PolkadotService, CustomConfiguration, ServiceFactory, Factory,
-	ProvideRuntimeApi, CoreApi, ParachainHost,
+	ProvideRuntimeApi, CoreApi, ParachainHost, NodeBuilder, Roles,
 };
 
 pub use cli::{VersionInfo, IntoExit};
 pub use cli::error;
 
+/// Name of the node implementation.
+const IMPL_NAME: &str = "parity-polkadot";
+
 fn load_spec(id: &str) -> Result<Option<service::ChainSpec>, String> {
-	Ok(match ChainSpec::from(id) {
+	Ok(match ChainSpec::from(id)? {
 		Some(spec) => Some(spec.load()?),
 		None => None,
 	})
@@ -62,15 +70,28 @@ pub trait Worker: IntoExit {
 	/// This will be run on a tokio runtime.
 	type Work: Future<Item=(),Error=()> + Send + 'static;
 
-	/// Return configuration for the polkadot node.
-	// TODO: make this the full configuration, so embedded nodes don't need
-	// string CLI args
+	/// Return configuration for the polkadot node when it is configured from
+	/// command line arguments. Embedded nodes are fully configured by a `NodeBuilder`.
 	fn configuration(&self) -> service::CustomConfiguration { Default::default() }
 
 	/// Do work and schedule exit.
 	fn work<S: PolkadotService>(self, service: &S) -> Self::Work;
 }
 
+/// Polkadot-specific subcommands.
+#[derive(Debug, Clone, StructOpt)]
+pub enum PolkadotSubCommands {
+	/// Launch a local testnet of validators and collators as child processes.
+	#[structopt(name = "testnet")]
+	Testnet(testnet::TestnetCmd),
+}
+
+impl cli::GetLogFilter for PolkadotSubCommands {
+	fn get_log_filter(&self) -> Option<String> {
+		None
+	}
+}
+
 /// Parse command line arguments into service configuration.
 ///
 /// IANA unassigned port ranges that we could use:
@@ -84,33 +105,63 @@ pub fn run<I, T, W>(args: I, worker: W, version: cli::VersionInfo) -> error::Res
 	T: Into<std::ffi::OsString> + Clone,
 	W: Worker,
 {
-	cli::parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
-		load_spec, &version, "parity-polkadot", args, worker,
+	let subcommand = cli::parse_and_execute::<service::Factory, PolkadotSubCommands, NoCustom, _, _, _, _, _>(
+		load_spec, &version, IMPL_NAME, args, worker,
 		|worker, _custom_args, mut config| {
-			info!("{}", version.name);
-			info!("  version {}", config.full_version());
-			info!("  by {}, 2017-2019", version.author);
-			info!("Chain specification: {}", config.chain_spec.name());
-			info!("Node name: {}", config.name);
-			info!("Roles: {:?}", config.roles);
 			config.custom = worker.configuration();
-			let runtime = Runtime::new().map_err(|e| format!("{:?}", e))?;
-			let executor = runtime.executor();
-			match config.roles {
-				service::Roles::LIGHT =>
-					run_until_exit(
-						runtime,
-						Factory::new_light(config, executor).map_err(|e| format!("{:?}", e))?,
-						worker
-					),
-				_ => run_until_exit(
-						runtime,
-						Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?,
-						worker
-					),
-			}.map_err(|e| format!("{:?}", e))
+			run_node(config, worker, &version).map_err(|e| format!("{:?}", e))
 		}
-	).map_err(Into::into).map(|_| ())
+	)?;
+
+	match subcommand {
+		Some(PolkadotSubCommands::Testnet(cmd)) => cmd.run().map_err(Into::into),
+		None => Ok(()),
+	}
+}
+
+/// Run a node configured by a `NodeBuilder` until the worker's work is done.
+///
+/// Unlike `run`, no command line arguments are parsed and the worker's
+/// `configuration` is not consulted: the builder holds the full configuration.
+/// Logging is left to the embedder.
+pub fn run_with_builder<W: Worker>(node: NodeBuilder, worker: W, version: VersionInfo) -> error::Result<()> {
+	let mut config = node.build()?;
+	config.impl_name = IMPL_NAME;
+	config.impl_version = version.version;
+	config.impl_commit = version.commit;
+
+	run_node(config, worker, &version)
+}
+
+fn run_node<W: Worker>(mut config: service::Configuration, worker: W, version: &VersionInfo) -> error::Result<()> {
+	info!("{}", version.name);
+	info!("  version {}", config.full_version());
+	info!("  by {}, 2017-2019", version.author);
+	info!("Chain specification: {}", config.chain_spec.name());
+	info!("Node name: {}", config.name);
+	info!("Roles: {:?}", config.roles);
+	// the Polkadot-specific RPC API is served from state which light nodes lack.
+	if config.roles == service::Roles::LIGHT {
+		config.custom.parachain_rpc_http = None;
+	} else if config.custom.parachain_rpc_http.is_none() {
+		config.custom.parachain_rpc_http = service::default_parachain_rpc_http(config.rpc_http);
+	}
+
+	let runtime = Runtime::new().map_err(|e| format!("{:?}", e))?;
+	let executor = runtime.executor();
+	match config.roles {
+		service::Roles::LIGHT =>
+			run_until_exit(
+				runtime,
+				Factory::new_light(config, executor).map_err(|e| format!("{:?}", e))?,
+				worker
+			),
+		_ => run_until_exit(
+				runtime,
+				Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?,
+				worker
+			),
+	}
 }
 
 fn run_until_exit<T, C, W>(
@@ -129,6 +180,19 @@ fn run_until_exit<T, C, W>(
 	let executor = runtime.executor();
 	cli::informant::start(&service, exit.clone(), executor.clone());
 
+	let _parachain_rpc = service.config.custom.parachain_rpc_http.as_ref().and_then(|addr| {
+		match service::start_polkadot_rpc(addr, &*service) {
+			Ok(server) => {
+				info!("Polkadot RPC HTTP server listening on {}", addr);
+				Some(server)
+			}
+			Err(e) => {
+				warn!("Unable to start Polkadot RPC HTTP server on {}: {}", addr, e);
+				None
+			}
+		}
+	});
+
 	let _ = runtime.block_on(worker.work(&*service));
 	exit_send.fire();
 
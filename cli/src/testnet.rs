@@ -0,0 +1,355 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The `testnet` subcommand: launch a local testnet of validators and collators
+//! as child processes.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use libp2p::secio::SecioKeyPair;
+use service::chain_spec::{TestnetSpec, GenesisParachain};
+use service::Bytes;
+
+/// Names of the well-known keys given to the first validators.
+const VALIDATOR_NAMES: &[&str] = &["Alice", "Bob", "Charlie", "Dave", "Eve", "Ferdie"];
+
+/// Number of consecutive ports used by each node: its p2p port, HTTP RPC port,
+/// Polkadot RPC port, which follows the HTTP RPC port, and WebSockets RPC port.
+const PORTS_PER_NODE: usize = 4;
+
+/// A parachain to register at genesis, along with the collator to run for it,
+/// given as `ID:COLLATOR:WASM:GENESIS_HEAD`.
+#[derive(Debug, Clone)]
+pub struct ParachainEntry {
+	id: u32,
+	collator: PathBuf,
+	wasm: PathBuf,
+	genesis_head: Vec<u8>,
+}
+
+impl FromStr for ParachainEntry {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, String> {
+		let parts: Vec<_> = s.splitn(4, ':').collect();
+		if parts.len() != 4 {
+			return Err(format!("Expected ID:COLLATOR:WASM:GENESIS_HEAD, got {}", s));
+		}
+
+		let id = parts[0].parse().map_err(|e| format!("Invalid parachain id {}: {}", parts[0], e))?;
+		let head = parts[3].trim_start_matches("0x");
+		if head.len() % 2 != 0 {
+			return Err(format!("Genesis head {} has an odd number of hex digits", parts[3]));
+		}
+		let genesis_head = (0..head.len()).step_by(2)
+			.map(|i| u8::from_str_radix(&head[i..i + 2], 16))
+			.collect::<Result<_, _>>()
+			.map_err(|e| format!("Invalid genesis head {}: {}", parts[3], e))?;
+
+		Ok(ParachainEntry {
+			id,
+			collator: parts[1].into(),
+			wasm: parts[2].into(),
+			genesis_head,
+		})
+	}
+}
+
+/// Launch a local testnet of validators and collators.
+#[derive(Debug, Clone, StructOpt)]
+pub struct TestnetCmd {
+	/// Number of validators to run.
+	#[structopt(long = "validators", default_value = "2")]
+	pub validators: usize,
+
+	/// A parachain to register at genesis and the collator binary to run for it,
+	/// as `ID:COLLATOR:WASM:GENESIS_HEAD` with the genesis head in hex.
+	#[structopt(long = "parachain", value_name = "ID:COLLATOR:WASM:GENESIS_HEAD")]
+	pub parachains: Vec<ParachainEntry>,
+
+	/// Directory holding the chain spec and the base path of each node.
+	#[structopt(long = "base-path", short = "d", value_name = "PATH", parse(from_os_str))]
+	pub base_path: Option<PathBuf>,
+
+	/// First port of the first node. Each node uses a block of four ports for
+	/// p2p, HTTP RPC, Polkadot RPC and WebSockets RPC, after the previous node's.
+	#[structopt(long = "port", value_name = "PORT", default_value = "30333")]
+	pub port: u16,
+}
+
+// The ports of a node of the testnet.
+#[derive(Debug, PartialEq)]
+struct NodePorts {
+	p2p: u16,
+	rpc: u16,
+	ws: u16,
+}
+
+impl NodePorts {
+	// The ports of the `index`th node, from the block of ports following the
+	// blocks of the nodes before it. `None` if the block does not fit.
+	fn of_node(first_port: u16, index: usize) -> Option<Self> {
+		let first = index.checked_mul(PORTS_PER_NODE)
+			.and_then(|offset| (first_port as usize).checked_add(offset))?;
+		let last = first.checked_add(PORTS_PER_NODE - 1)?;
+		if last > u16::max_value() as usize {
+			return None;
+		}
+
+		let first = first as u16;
+		Some(NodePorts {
+			p2p: first,
+			rpc: first + 1,
+			// the Polkadot RPC server takes the port after the HTTP RPC port.
+			ws: first + 3,
+		})
+	}
+}
+
+// A node of the testnet, before it is launched.
+struct NodeSpec {
+	name: String,
+	binary: PathBuf,
+	args: Vec<String>,
+}
+
+// A launched node, which is stopped when dropped.
+struct RunningNode {
+	name: String,
+	child: Child,
+}
+
+impl Drop for RunningNode {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+impl TestnetCmd {
+	/// Generate the chain spec and launch all nodes, streaming their logs until
+	/// any of them exits.
+	pub fn run(self) -> Result<(), String> {
+		if self.validators == 0 {
+			return Err("A testnet needs at least one validator".into());
+		}
+
+		let base_path = self.base_path.clone()
+			.unwrap_or_else(|| ::std::env::temp_dir().join("polkadot-testnet"));
+		fs::create_dir_all(&base_path)
+			.map_err(|e| format!("Error creating {}: {}", base_path.display(), e))?;
+
+		let names = validator_names(self.validators);
+		let chain = self.write_chain_spec(&base_path, &names)?;
+		let polkadot = ::std::env::current_exe()
+			.map_err(|e| format!("Unable to locate the polkadot binary: {}", e))?;
+
+		let mut nodes: Vec<_> = names.iter().map(|name| NodeSpec {
+			name: name.to_lowercase(),
+			binary: polkadot.clone(),
+			args: vec!["--validator".into(), "--key".into(), name.clone()],
+		}).collect();
+		nodes.extend(self.parachains.iter().map(|para| NodeSpec {
+			name: format!("collator-{}", para.id),
+			binary: para.collator.clone(),
+			args: Vec::new(),
+		}));
+
+		for (i, node) in nodes.iter_mut().enumerate() {
+			let ports = NodePorts::of_node(self.port, i)
+				.ok_or_else(|| format!("Ports of node {} exceed the port range, choose a lower --port", node.name))?;
+			let node_path = base_path.join(&node.name);
+			node.args.extend(vec![
+				"--chain".into(), chain.display().to_string(),
+				"--base-path".into(), node_path.display().to_string(),
+				"--name".into(), node.name.clone(),
+				"--node-key".into(), to_hex(&node_key(i)),
+				"--port".into(), ports.p2p.to_string(),
+				"--rpc-port".into(), ports.rpc.to_string(),
+				"--ws-port".into(), ports.ws.to_string(),
+			]);
+		}
+
+		// all nodes boot from the first validator, whose network identity is
+		// known from its node key.
+		let bootnode = format!("/ip4/127.0.0.1/tcp/{}/p2p/{}", self.port, peer_id(&node_key(0))?);
+		info!("Booting testnet nodes from {}", bootnode);
+
+		let (lines_tx, lines_rx) = mpsc::channel();
+		let mut running = Vec::with_capacity(nodes.len());
+		for (i, node) in nodes.into_iter().enumerate() {
+			let extra_args = if i == 0 { Vec::new() } else { vec!["--bootnodes".into(), bootnode.clone()] };
+			running.push(launch(node, extra_args, lines_tx.clone())?);
+		}
+		drop(lines_tx);
+
+		loop {
+			while let Ok((name, line)) = lines_rx.try_recv() {
+				println!("[{}] {}", name, line);
+			}
+
+			for node in running.iter_mut() {
+				if let Some(status) = node.child.try_wait().map_err(|e| e.to_string())? {
+					// dropping the running nodes stops the others.
+					return Err(format!("Node {} exited with {}", node.name, status));
+				}
+			}
+
+			thread::sleep(Duration::from_millis(100));
+		}
+	}
+
+	// Write the description of the testnet's chain to the base path, returning
+	// its path. All nodes build the same genesis from it.
+	fn write_chain_spec(&self, base_path: &Path, names: &[String]) -> Result<PathBuf, String> {
+		let parachains = self.parachains.iter().map(|para| {
+			let wasm = fs::canonicalize(&para.wasm)
+				.map_err(|e| format!("Error locating code of parachain {} at {}: {}", para.id, para.wasm.display(), e))?;
+			Ok(GenesisParachain {
+				id: para.id,
+				wasm,
+				genesis_head: Bytes(para.genesis_head.clone()),
+			})
+		}).collect::<Result<_, String>>()?;
+
+		let spec = TestnetSpec {
+			name: "Local Parachain Testnet".into(),
+			id: "local_parachain_testnet".into(),
+			authorities: names.to_vec(),
+			parachains,
+		};
+
+		let path = base_path.join("testnet.json");
+		let json = ::serde_json::to_string_pretty(&spec).map_err(|e| e.to_string())?;
+		fs::write(&path, json).map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+
+		Ok(path)
+	}
+}
+
+// The key names of the first `count` validators.
+fn validator_names(count: usize) -> Vec<String> {
+	(0..count).map(|i| match VALIDATOR_NAMES.get(i) {
+		Some(name) => name.to_string(),
+		None => format!("Validator{}", i + 1),
+	}).collect()
+}
+
+// The secret key of the `index`th node's network identity. It is fixed, so that
+// the peer id of the bootnode is known before it is launched.
+fn node_key(index: usize) -> [u8; 32] {
+	let mut key = [0u8; 32];
+	key[24..].copy_from_slice(&(index as u64 + 1).to_be_bytes());
+	key
+}
+
+// The peer id of the network identity with the given secret key.
+fn peer_id(node_key: &[u8; 32]) -> Result<String, String> {
+	let key_pair = SecioKeyPair::secp256k1_raw_key(&node_key[..])
+		.map_err(|e| format!("Invalid node key: {:?}", e))?;
+	Ok(key_pair.to_peer_id().to_base58())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Launch a node, forwarding the lines of its output to `lines`.
+fn launch(node: NodeSpec, extra_args: Vec<String>, lines: mpsc::Sender<(String, String)>) -> Result<RunningNode, String> {
+	let mut child = Command::new(&node.binary)
+		.args(&node.args)
+		.args(&extra_args)
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|e| format!("Error launching {} ({}): {}", node.name, node.binary.display(), e))?;
+
+	info!("Launched {} with pid {}", node.name, child.id());
+
+	let stdout = child.stdout.take().expect("stdout is piped; qed");
+	let stderr = child.stderr.take().expect("stderr is piped; qed");
+	forward_lines(node.name.clone(), stdout, lines.clone());
+	forward_lines(node.name.clone(), stderr, lines);
+
+	Ok(RunningNode { name: node.name, child })
+}
+
+fn forward_lines<R: Read + Send + 'static>(name: String, output: R, lines: mpsc::Sender<(String, String)>) {
+	thread::spawn(move || {
+		for line in BufReader::new(output).lines() {
+			match line {
+				Ok(line) => if lines.send((name.clone(), line)).is_err() { break },
+				Err(_) => break,
+			}
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_parachain_entries() {
+		let entry: ParachainEntry = "100:./adder-collator:./adder.wasm:0x0102ff".parse().unwrap();
+		assert_eq!(entry.id, 100);
+		assert_eq!(entry.collator, PathBuf::from("./adder-collator"));
+		assert_eq!(entry.wasm, PathBuf::from("./adder.wasm"));
+		assert_eq!(entry.genesis_head, vec![1, 2, 255]);
+
+		assert!("100:./adder-collator:./adder.wasm".parse::<ParachainEntry>().is_err());
+		assert!("x:./adder-collator:./adder.wasm:00".parse::<ParachainEntry>().is_err());
+		assert!("100:./adder-collator:./adder.wasm:0x012".parse::<ParachainEntry>().is_err());
+	}
+
+	#[test]
+	fn nodes_get_disjoint_port_blocks() {
+		let ports: Vec<_> = (0..8).map(|i| NodePorts::of_node(30333, i).unwrap()).collect();
+		assert_eq!(ports[0], NodePorts { p2p: 30333, rpc: 30334, ws: 30336 });
+		assert_eq!(ports[1], NodePorts { p2p: 30337, rpc: 30338, ws: 30340 });
+
+		// including the Polkadot RPC port after each HTTP RPC port.
+		let mut used: Vec<_> = ports.iter().flat_map(|p| vec![p.p2p, p.rpc, p.rpc + 1, p.ws]).collect();
+		used.sort();
+		used.dedup();
+		assert_eq!(used.len(), 8 * PORTS_PER_NODE);
+
+		assert!(NodePorts::of_node(65532, 0).is_some());
+		assert!(NodePorts::of_node(65533, 0).is_none());
+		assert!(NodePorts::of_node(30333, 10_000).is_none());
+		assert!(NodePorts::of_node(0, usize::max_value()).is_none());
+	}
+
+	#[test]
+	fn node_keys_differ() {
+		assert_ne!(node_key(0), node_key(1));
+		assert_eq!(to_hex(&node_key(0))[48..], *"0000000000000001");
+		assert_ne!(peer_id(&node_key(0)).unwrap(), peer_id(&node_key(1)).unwrap());
+	}
+
+	#[test]
+	fn validators_get_well_known_names() {
+		assert_eq!(validator_names(2), vec!["Alice".to_string(), "Bob".to_string()]);
+		assert_eq!(validator_names(7)[6], "Validator7");
+	}
+}
@@ -65,13 +65,13 @@ use std::time::Duration;
 use futures::{future, stream, Stream, Future, IntoFuture};
 use client::BlockchainEvents;
 use primitives::ed25519;
-use polkadot_primitives::{AccountId, BlockId, SessionKey};
+use polkadot_primitives::{BlockId, SessionKey, key_to_account_id};
 use polkadot_primitives::parachain::{self, BlockData, DutyRoster, HeadData, ConsolidatedIngress, Message, Id as ParaId};
 use polkadot_cli::{PolkadotService, CustomConfiguration, CoreApi, ParachainHost};
 use polkadot_cli::{Worker, IntoExit, ProvideRuntimeApi};
 use tokio::timer::Timeout;
 
-pub use polkadot_cli::VersionInfo;
+pub use polkadot_cli::{VersionInfo, NodeBuilder};
 
 const COLLATION_TIMEOUT: Duration = Duration::from_secs(30);
 
@@ -127,11 +127,6 @@ pub trait RelayChainContext {
 	fn unrouted_egress(&self, id: ParaId) -> Self::FutureEgress;
 }
 
-fn key_to_account_id(key: &ed25519::Pair) -> AccountId {
-	let pubkey_bytes: [u8; 32] = key.public().into();
-	pubkey_bytes.into()
-}
-
 /// Collate the necessary ingress queue using the given context.
 pub fn collate_ingress<'a, R>(relay_context: R)
 	-> impl Future<Item=ConsolidatedIngress, Error=R::Error> + 'a
@@ -227,13 +222,22 @@ impl RelayChainContext for ApiContext {
 	}
 }
 
-struct CollationNode<P, E> {
+/// A worker running the collation logic of a parachain on top of a polkadot node.
+pub struct CollationNode<P, E> {
 	parachain_context: P,
 	exit: E,
 	para_id: ParaId,
 	key: Arc<ed25519::Pair>,
 }
 
+impl<P, E> CollationNode<P, E> {
+	/// Create a collation worker for the given parachain, signing collations with
+	/// the given key until `exit` resolves.
+	pub fn new(parachain_context: P, para_id: ParaId, exit: E, key: Arc<ed25519::Pair>) -> Self {
+		CollationNode { parachain_context, exit, para_id, key }
+	}
+}
+
 impl<P, E> IntoExit for CollationNode<P, E> where
 	P: ParachainContext + Send + 'static,
 	E: Future<Item=(),Error=()> + Send + 'static
@@ -253,7 +257,7 @@ impl<P, E> Worker for CollationNode<P, E> where
 	fn configuration(&self) -> CustomConfiguration {
 		let mut config = CustomConfiguration::default();
 		config.collating_for = Some((
-			key_to_account_id(&*self.key),
+			self.key.clone(),
 			self.para_id.clone(),
 		));
 		config
@@ -345,12 +349,34 @@ fn compute_targets(para_id: ParaId, session_keys: &[SessionKey], roster: DutyRos
 		.collect()
 }
 
-/// Run a collator node with the given `RelayChainContext` and `ParachainContext` and
+/// Run a collator node with the given `ParachainContext`, on a polkadot node
+/// configured by the given builder.
+///
+/// Provide a future which resolves when the node should exit.
+/// This function blocks until done.
+pub fn run_collator<P, E>(
+	parachain_context: P,
+	para_id: ParaId,
+	exit: E,
+	key: Arc<ed25519::Pair>,
+	node: NodeBuilder,
+	version: VersionInfo,
+) -> polkadot_cli::error::Result<()> where
+	P: ParachainContext + Send + 'static,
+	E: IntoFuture<Item=(),Error=()>,
+	E::Future: Send + Clone + 'static,
+{
+	let node = node.collating_for(key.clone(), para_id);
+	let node_logic = CollationNode::new(parachain_context, para_id, exit.into_future(), key);
+	polkadot_cli::run_with_builder(node, node_logic, version)
+}
+
+/// Run a collator node with the given `ParachainContext` and command line
 /// arguments to the underlying polkadot node.
 ///
 /// Provide a future which resolves when the node should exit.
 /// This function blocks until done.
-pub fn run_collator<P, E, I, ArgT>(
+pub fn run_collator_with_args<P, E, I, ArgT>(
 	parachain_context: P,
 	para_id: ParaId,
 	exit: E,
@@ -364,7 +390,7 @@ pub fn run_collator<P, E, I, ArgT>(
 	I: IntoIterator<Item=ArgT>,
 	ArgT: Into<std::ffi::OsString> + Clone,
 {
-	let node_logic = CollationNode { parachain_context, exit: exit.into_future(), para_id, key };
+	let node_logic = CollationNode::new(parachain_context, para_id, exit.into_future(), key);
 	polkadot_cli::run(args, node_logic, version)
 }
 
@@ -15,17 +15,56 @@
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Bridge between the network and consensus service for getting collations to it.
+//!
+//! Up to `MAX_PRIMARIES` collators per parachain are primaries, the rest are backups.
+//! Collators are scored by how quickly their first collation on each relay parent
+//! arrives and by the validity of their collations, losing score for invalid ones.
+//! Only time makes up for invalid collations: scores are kept per account across
+//! reconnections and decay towards zero. Pending collations are handed out best-scoring first and primaries are
+//! rotated against better-scoring backups during maintenance.
 
 use polkadot_primitives::{AccountId, Hash};
 use polkadot_primitives::parachain::{Id as ParaId, Collation};
 
 use futures::sync::oneshot;
 
-use std::collections::hash_map::{HashMap, Entry};
+use std::collections::{HashSet, hash_map::{HashMap, Entry}};
 use std::time::{Duration, Instant};
 
 const COLLATION_LIFETIME: Duration = Duration::from_secs(60 * 5);
 
+/// Maximum number of primary collators per parachain.
+pub const MAX_PRIMARIES: usize = 3;
+
+type Score = i32;
+
+const MAX_SCORE: Score = 100;
+const MIN_SCORE: Score = -1000;
+// collators are disconnected once their score drops to this.
+const DISCONNECT_THRESHOLD: Score = -100;
+// score lost for a collation which was found invalid.
+const BAD_COLLATION: Score = -50;
+// score gained for a collation which was found valid.
+const GOOD_COLLATION: Score = 2;
+// a collation arriving right after its slot opened scores this, one less
+// for each further `LATENCY_STEP` down to `MIN_LATENCY_SCORE`.
+const MAX_LATENCY_SCORE: Score = 4;
+const MIN_LATENCY_SCORE: Score = -4;
+const LATENCY_STEP: Duration = Duration::from_secs(1);
+// a backup must score this much above a primary to replace it.
+const ROTATION_MARGIN: Score = 10;
+// scores move one point towards zero every interval.
+const SCORE_DECAY_INTERVAL: Duration = Duration::from_secs(30);
+
+fn latency_score(latency: Duration) -> Score {
+	let steps = latency.as_secs() / LATENCY_STEP.as_secs();
+	if steps >= (MAX_LATENCY_SCORE - MIN_LATENCY_SCORE) as u64 {
+		MIN_LATENCY_SCORE
+	} else {
+		MAX_LATENCY_SCORE - steps as Score
+	}
+}
+
 /// The role of the collator. Whether they're the primary or backup for this parachain.
 #[derive(PartialEq, Debug, Clone, Copy, Encode, Decode)]
 pub enum Role {
@@ -37,7 +76,6 @@ pub enum Role {
 
 /// A maintenance action for the collator set.
 #[derive(PartialEq, Debug)]
-#[allow(dead_code)]
 pub enum Action {
 	/// Disconnect the given collator.
 	Disconnect(AccountId),
@@ -48,6 +86,8 @@ pub enum Action {
 struct CollationSlot {
 	live_at: Instant,
 	entries: SlotEntries,
+	// collators whose collation on this slot was scored already.
+	scored: HashSet<AccountId>,
 }
 
 impl CollationSlot {
@@ -55,6 +95,7 @@ impl CollationSlot {
 		CollationSlot {
 			live_at: Instant::now(),
 			entries: SlotEntries::Blank,
+			scored: HashSet::new(),
 		}
 	}
 
@@ -63,16 +104,21 @@ impl CollationSlot {
 	}
 }
 
+struct ScoredCollation {
+	score: Score,
+	collation: Collation,
+}
+
 enum SlotEntries {
 	Blank,
 	// not queried yet
-	Pending(Vec<Collation>),
+	Pending(Vec<ScoredCollation>),
 	// waiting for next to arrive.
 	Awaiting(Vec<oneshot::Sender<Collation>>),
 }
 
 impl SlotEntries {
-	fn received_collation(&mut self, collation: Collation) {
+	fn received_collation(&mut self, collation: ScoredCollation) {
 		*self = match ::std::mem::replace(self, SlotEntries::Blank) {
 			SlotEntries::Blank => SlotEntries::Pending(vec![collation]),
 			SlotEntries::Pending(mut cs) => {
@@ -81,7 +127,7 @@ impl SlotEntries {
 			}
 			SlotEntries::Awaiting(senders) => {
 				for sender in senders {
-					let _ = sender.send(collation.clone());
+					let _ = sender.send(collation.collation.clone());
 				}
 
 				SlotEntries::Blank
@@ -97,8 +143,14 @@ impl SlotEntries {
 				SlotEntries::Awaiting(senders)
 			}
 			SlotEntries::Pending(mut cs) => {
-				let next_collation = cs.pop().expect("empty variant is always `Blank`; qed");
-				let _ = sender.send(next_collation);
+				// best score first, earliest among equals.
+				let best = cs.iter().enumerate()
+					.max_by_key(|&(i, c)| (c.score, ::std::cmp::Reverse(i)))
+					.map(|(i, _)| i)
+					.expect("empty variant is always `Blank`; qed");
+
+				let next_collation = cs.remove(best);
+				let _ = sender.send(next_collation.collation);
 
 				if cs.is_empty() {
 					SlotEntries::Blank
@@ -111,15 +163,29 @@ impl SlotEntries {
 }
 
 struct ParachainCollators {
-	primary: AccountId,
+	primary: Vec<AccountId>,
 	backup: Vec<AccountId>,
 }
 
+impl ParachainCollators {
+	// the best-scoring backup, removed from the backups.
+	fn take_best_backup(&mut self, scores: &HashMap<AccountId, Score>) -> Option<AccountId> {
+		let best = self.backup.iter().enumerate()
+			.max_by_key(|&(i, a)| (scores.get(a).cloned().unwrap_or(0), ::std::cmp::Reverse(i)))
+			.map(|(i, _)| i);
+
+		best.map(|i| self.backup.remove(i))
+	}
+}
+
 /// Manages connected collators and role assignments from the perspective of a validator.
 pub struct CollatorPool {
 	collators: HashMap<AccountId, ParaId>,
 	parachain_collators: HashMap<ParaId, ParachainCollators>,
 	collations: HashMap<(Hash, ParaId), CollationSlot>,
+	// scores of connected collators and of disconnected ones until they decay to zero.
+	scores: HashMap<AccountId, Score>,
+	last_decay: Instant,
 }
 
 impl CollatorPool {
@@ -129,68 +195,98 @@ impl CollatorPool {
 			collators: HashMap::new(),
 			parachain_collators: HashMap::new(),
 			collations: HashMap::new(),
+			scores: HashMap::new(),
+			last_decay: Instant::now(),
 		}
 	}
 
 	/// Call when a new collator is authenticated. Returns the role.
+	/// A reconnecting collator keeps the score it had when it disconnected.
 	pub fn on_new_collator(&mut self, account_id: AccountId, para_id: ParaId) -> Role {
 		self.collators.insert(account_id.clone(), para_id);
-		match self.parachain_collators.entry(para_id) {
-			Entry::Vacant(vacant) => {
-				vacant.insert(ParachainCollators {
-					primary: account_id,
-					backup: Vec::new(),
-				});
+		self.scores.entry(account_id.clone()).or_insert(0);
 
-				Role::Primary
-			},
-			Entry::Occupied(mut occupied) => {
-				occupied.get_mut().backup.push(account_id);
+		let collators = self.parachain_collators.entry(para_id).or_insert_with(|| ParachainCollators {
+			primary: Vec::new(),
+			backup: Vec::new(),
+		});
 
-				Role::Backup
-			}
+		if collators.primary.len() < MAX_PRIMARIES {
+			collators.primary.push(account_id);
+			Role::Primary
+		} else {
+			collators.backup.push(account_id);
+			Role::Backup
 		}
 	}
 
-	/// Called when a collator disconnects. If it was the primary, returns a new primary for that
-	/// parachain.
+	/// Called when a collator disconnects. If it was a primary, returns the backup
+	/// promoted to primary in its place.
 	pub fn on_disconnect(&mut self, account_id: AccountId) -> Option<AccountId> {
-		self.collators.remove(&account_id).and_then(|para_id| match self.parachain_collators.entry(para_id) {
+		let para_id = match self.collators.remove(&account_id) {
+			Some(para_id) => para_id,
+			None => return None,
+		};
+
+		match self.parachain_collators.entry(para_id) {
 			Entry::Vacant(_) => None,
 			Entry::Occupied(mut occ) => {
-				if occ.get().primary == account_id {
-					if occ.get().backup.is_empty() {
-						occ.remove();
-						None
-					} else {
-						let mut collators = occ.get_mut();
-						collators.primary = collators.backup.pop().expect("backup non-empty; qed");
-						Some(collators.primary)
-					}
+				let new_primary = if let Some(pos) = occ.get().primary.iter().position(|a| a == &account_id) {
+					let collators = occ.get_mut();
+					collators.primary.remove(pos);
+
+					let new_primary = collators.take_best_backup(&self.scores);
+					collators.primary.extend(new_primary.clone());
+					new_primary
 				} else {
 					let pos = occ.get().backup.iter().position(|a| a == &account_id)
 						.expect("registered collator always present in backup if not primary; qed");
 
 					occ.get_mut().backup.remove(pos);
 					None
+				};
+
+				if occ.get().primary.is_empty() {
+					occ.remove();
 				}
+
+				new_primary
 			}
-		})
+		}
 	}
 
 	/// Called when a collation is received.
 	/// The collator should be registered for the parachain of the collation as a precondition of this function.
 	/// The collation should have been checked for integrity of signature before passing to this function.
 	pub fn on_collation(&mut self, account_id: AccountId, relay_parent: Hash, collation: Collation) {
+		self.on_collation_at(account_id, relay_parent, collation, Instant::now())
+	}
+
+	fn on_collation_at(&mut self, account_id: AccountId, relay_parent: Hash, collation: Collation, now: Instant) {
 		if let Some(para_id) = self.collators.get(&account_id) {
 			debug_assert_eq!(para_id, &collation.receipt.parachain_index);
 
-			// TODO: punish if not primary?
+			let slot = self.collations.entry((relay_parent, para_id.clone()))
+				.or_insert_with(CollationSlot::blank_now);
+
+			let score = self.scores.entry(account_id).or_insert(0);
 
-			self.collations.entry((relay_parent, para_id.clone()))
-				.or_insert_with(CollationSlot::blank_now)
-				.entries
-				.received_collation(collation);
+			// only the first collation of a collator on a relay parent is scored, so
+			// flooding collations doesn't earn score. latency is measured from when the
+			// slot was opened, either by the first collation arriving or by the collation
+			// being awaited. timely collations don't make up for invalid ones.
+			if slot.scored.insert(account_id) {
+				let latency = if now > slot.live_at { now - slot.live_at } else { Duration::from_secs(0) };
+				let delta = latency_score(latency);
+				if delta < 0 || *score >= 0 {
+					*score = score.saturating_add(delta).max(MIN_SCORE).min(MAX_SCORE);
+				}
+			}
+
+			slot.entries.received_collation(ScoredCollation {
+				score: *score,
+				collation,
+			});
 		}
 	}
 
@@ -202,11 +298,103 @@ impl CollatorPool {
 			.await_with(sender);
 	}
 
+	/// Note that a collation from the given collator was found valid. Like timely
+	/// collations, valid ones don't make up for invalid ones.
+	pub fn note_good_collator(&mut self, account_id: AccountId) {
+		if !self.collators.contains_key(&account_id) { return }
+
+		let score = self.scores.entry(account_id).or_insert(0);
+		if *score >= 0 {
+			*score = score.saturating_add(GOOD_COLLATION).min(MAX_SCORE);
+		}
+	}
+
+	/// Note that a collation from the given collator was found invalid.
+	/// A primary is demoted in favour of the best-scoring backup, and collators
+	/// with a low enough score are disconnected.
+	pub fn note_bad_collator(&mut self, account_id: AccountId) -> Vec<Action> {
+		let para_id = match self.collators.get(&account_id) {
+			Some(para_id) => *para_id,
+			None => return Vec::new(),
+		};
+
+		let score = {
+			let score = self.scores.entry(account_id.clone()).or_insert(0);
+			*score = score.saturating_add(BAD_COLLATION).max(MIN_SCORE).min(MAX_SCORE);
+			*score
+		};
+
+		let mut actions = Vec::new();
+		if let Some(collators) = self.parachain_collators.get_mut(&para_id) {
+			if let Some(pos) = collators.primary.iter().position(|a| a == &account_id) {
+				if let Some(promoted) = collators.take_best_backup(&self.scores) {
+					collators.primary[pos] = promoted.clone();
+					collators.backup.push(account_id.clone());
+
+					actions.push(Action::NewRole(promoted, Role::Primary));
+					if score > DISCONNECT_THRESHOLD {
+						actions.push(Action::NewRole(account_id.clone(), Role::Backup));
+					}
+				}
+			}
+		}
+
+		if score <= DISCONNECT_THRESHOLD {
+			actions.push(Action::Disconnect(account_id));
+		}
+
+		actions
+	}
+
 	/// Call periodically to perform collator set maintenance.
 	/// Returns a set of actions to perform on the network level.
 	pub fn maintain_peers(&mut self) -> Vec<Action> {
-		// TODO: rearrange periodically to new primary, evaluate based on latency etc.
-		Vec::new()
+		self.decay_scores(Instant::now());
+
+		let scores = &self.scores;
+		let score_of = |a: &AccountId| scores.get(a).cloned().unwrap_or(0);
+		let mut actions = Vec::new();
+
+		for collators in self.parachain_collators.values_mut() {
+			loop {
+				let worst_primary = collators.primary.iter().enumerate()
+					.min_by_key(|&(_, a)| score_of(a))
+					.map(|(i, a)| (i, score_of(a)));
+
+				let best_backup = collators.backup.iter().enumerate()
+					.max_by_key(|&(i, a)| (score_of(a), ::std::cmp::Reverse(i)))
+					.map(|(i, a)| (i, score_of(a)));
+
+				match (worst_primary, best_backup) {
+					(Some((p, p_score)), Some((b, b_score))) if b_score >= p_score.saturating_add(ROTATION_MARGIN) => {
+						::std::mem::swap(&mut collators.primary[p], &mut collators.backup[b]);
+						actions.push(Action::NewRole(collators.primary[p].clone(), Role::Primary));
+						actions.push(Action::NewRole(collators.backup[b].clone(), Role::Backup));
+					}
+					_ => break,
+				}
+			}
+		}
+
+		actions
+	}
+
+	// move scores towards zero by a point for each decay interval passed, forgetting
+	// those of disconnected collators once they reach zero.
+	fn decay_scores(&mut self, now: Instant) {
+		if now <= self.last_decay { return }
+
+		let intervals = (now - self.last_decay).as_secs() / SCORE_DECAY_INTERVAL.as_secs();
+		if intervals == 0 { return }
+
+		self.last_decay += SCORE_DECAY_INTERVAL * intervals as u32;
+
+		let decay = ::std::cmp::min(intervals, (MAX_SCORE - MIN_SCORE) as u64) as Score;
+		let collators = &self.collators;
+		self.scores.retain(|account_id, score| {
+			*score = if *score > 0 { (*score - decay).max(0) } else { (*score + decay).min(0) };
+			*score != 0 || collators.contains_key(account_id)
+		});
 	}
 
 	/// called when a block with given hash has been imported.
@@ -223,32 +411,242 @@ mod tests {
 	use substrate_primitives::H512;
 	use futures::Future;
 
+	fn make_collation(para_id: ParaId, collator: AccountId, head: u8) -> Collation {
+		Collation {
+			receipt: CandidateReceipt {
+				parachain_index: para_id,
+				collator,
+				signature: H512::from([2; 64]).into(),
+				head_data: HeadData(vec![head]),
+				balance_uploads: vec![],
+				egress_queue_roots: vec![],
+				fees: 0,
+				block_data_hash: [3; 32].into(),
+			},
+			block_data: BlockData(vec![4, 5, 6]),
+		}
+	}
+
+	// registers `MAX_PRIMARIES` primaries for the parachain.
+	fn add_primaries(pool: &mut CollatorPool, para_id: ParaId) -> Vec<AccountId> {
+		(0..MAX_PRIMARIES).map(|i| {
+			let account_id: AccountId = [100 + i as u8; 32].into();
+			assert_eq!(pool.on_new_collator(account_id, para_id), Role::Primary);
+			account_id
+		}).collect()
+	}
+
+	#[test]
+	fn multiple_primaries_then_backups() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+
+		let primaries = add_primaries(&mut pool, para_id);
+		assert_eq!(pool.on_new_collator([1; 32].into(), para_id), Role::Backup);
+
+		// other parachains have their own primaries.
+		assert_eq!(pool.on_new_collator([2; 32].into(), 6.into()), Role::Primary);
+		assert_eq!(pool.parachain_collators.get(&para_id).unwrap().primary, primaries);
+	}
+
 	#[test]
 	fn disconnect_primary_gives_new_primary() {
 		let mut pool = CollatorPool::new();
 		let para_id: ParaId = 5.into();
-		let bad_primary = [0; 32].into();
+		let primaries = add_primaries(&mut pool, para_id);
 		let good_backup = [1; 32].into();
 
-		assert_eq!(pool.on_new_collator(bad_primary, para_id.clone()), Role::Primary);
 		assert_eq!(pool.on_new_collator(good_backup, para_id.clone()), Role::Backup);
-		assert_eq!(pool.on_disconnect(bad_primary), Some(good_backup));
+		assert_eq!(pool.on_disconnect(primaries[0]), Some(good_backup));
 		assert_eq!(pool.on_disconnect(good_backup), None);
+		assert_eq!(pool.on_disconnect(primaries[1]), None);
+		assert_eq!(pool.on_disconnect(primaries[2]), None);
+		assert!(pool.parachain_collators.is_empty());
 	}
 
 	#[test]
 	fn disconnect_backup_removes_from_pool() {
 		let mut pool = CollatorPool::new();
 		let para_id: ParaId = 5.into();
-		let primary = [0; 32].into();
+		add_primaries(&mut pool, para_id);
 		let backup = [1; 32].into();
 
-		assert_eq!(pool.on_new_collator(primary, para_id.clone()), Role::Primary);
 		assert_eq!(pool.on_new_collator(backup, para_id.clone()), Role::Backup);
 		assert_eq!(pool.on_disconnect(backup), None);
 		assert!(pool.parachain_collators.get(&para_id).unwrap().backup.is_empty());
 	}
 
+	#[test]
+	fn best_scoring_collation_handed_out_first() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+		let relay_parent = [1; 32].into();
+		let now = Instant::now();
+
+		// a collator with a history of invalid collations.
+		pool.note_bad_collator(primaries[0]);
+
+		pool.on_collation_at(primaries[0], relay_parent, make_collation(para_id, primaries[0], 0), now);
+		let slot_opened = pool.collations[&(relay_parent, para_id)].live_at;
+		pool.on_collation_at(primaries[1], relay_parent, make_collation(para_id, primaries[1], 1), slot_opened + Duration::from_secs(3));
+		pool.on_collation_at(primaries[2], relay_parent, make_collation(para_id, primaries[2], 2), slot_opened);
+
+		let mut next_head = || {
+			let (tx, rx) = oneshot::channel();
+			pool.await_collation(relay_parent, para_id, tx);
+			rx.wait().unwrap().receipt.head_data.0[0]
+		};
+
+		// quickest first, then slower, then the bad collator's.
+		assert_eq!(next_head(), 2);
+		assert_eq!(next_head(), 1);
+		assert_eq!(next_head(), 0);
+	}
+
+	#[test]
+	fn bad_primary_demoted_and_disconnected() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+		let backup = [1; 32].into();
+
+		// without backups a bad primary stays primary.
+		assert!(pool.note_bad_collator(primaries[0]).is_empty());
+
+		assert_eq!(pool.on_new_collator(backup, para_id), Role::Backup);
+		assert_eq!(pool.note_bad_collator(primaries[1]), vec![
+			Action::NewRole(backup, Role::Primary),
+			Action::NewRole(primaries[1], Role::Backup),
+		]);
+		assert_eq!(pool.parachain_collators[&para_id].backup, vec![primaries[1]]);
+
+		// a second bad collation drops the collator's score far enough to disconnect.
+		assert_eq!(pool.note_bad_collator(primaries[0]), vec![
+			Action::NewRole(primaries[1], Role::Primary),
+			Action::Disconnect(primaries[0]),
+		]);
+
+		// unknown collators are ignored.
+		assert!(pool.note_bad_collator([9; 32].into()).is_empty());
+	}
+
+	#[test]
+	fn only_first_collation_per_relay_parent_scored() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+		let relay_parent = [1; 32].into();
+		let now = Instant::now();
+
+		for i in 0..100 {
+			pool.on_collation_at(primaries[0], relay_parent, make_collation(para_id, primaries[0], i), now);
+		}
+		assert_eq!(pool.scores[&primaries[0]], MAX_LATENCY_SCORE);
+
+		pool.on_collation_at(primaries[0], [2; 32].into(), make_collation(para_id, primaries[0], 0), now);
+		assert_eq!(pool.scores[&primaries[0]], 2 * MAX_LATENCY_SCORE);
+	}
+
+	#[test]
+	fn flooding_does_not_make_up_for_bad_collations() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+		let now = Instant::now();
+
+		assert!(pool.note_bad_collator(primaries[0]).is_empty());
+
+		for i in 0..100u8 {
+			let relay_parent = [i; 32].into();
+			pool.on_collation_at(primaries[0], relay_parent, make_collation(para_id, primaries[0], i), now);
+		}
+		assert_eq!(pool.scores[&primaries[0]], BAD_COLLATION);
+
+		// a single further bad collation reaches the disconnect threshold.
+		assert_eq!(pool.note_bad_collator(primaries[0]), vec![Action::Disconnect(primaries[0])]);
+	}
+
+	#[test]
+	fn scores_kept_across_reconnects_and_decay() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+		let now = pool.last_decay;
+
+		pool.note_bad_collator(primaries[0]);
+		assert_eq!(pool.on_disconnect(primaries[0]), None);
+		assert_eq!(pool.on_new_collator(primaries[0], para_id), Role::Primary);
+		assert_eq!(pool.scores[&primaries[0]], BAD_COLLATION);
+
+		pool.decay_scores(now + SCORE_DECAY_INTERVAL / 2);
+		assert_eq!(pool.scores[&primaries[0]], BAD_COLLATION);
+
+		pool.decay_scores(now + SCORE_DECAY_INTERVAL * 10);
+		assert_eq!(pool.scores[&primaries[0]], BAD_COLLATION + 10);
+
+		// disconnected collators are forgotten once their score decayed to zero.
+		assert_eq!(pool.on_disconnect(primaries[0]), None);
+		pool.decay_scores(now + SCORE_DECAY_INTERVAL * (10 - BAD_COLLATION) as u32);
+		assert!(!pool.scores.contains_key(&primaries[0]));
+
+		// while connected ones are kept.
+		assert_eq!(pool.scores[&primaries[1]], 0);
+	}
+
+	#[test]
+	fn valid_collations_raise_score() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+
+		pool.note_good_collator(primaries[0]);
+		assert_eq!(pool.scores[&primaries[0]], GOOD_COLLATION);
+
+		for _ in 0..MAX_SCORE {
+			pool.note_good_collator(primaries[0]);
+		}
+		assert_eq!(pool.scores[&primaries[0]], MAX_SCORE);
+
+		// valid collations don't make up for invalid ones.
+		pool.note_bad_collator(primaries[1]);
+		pool.note_good_collator(primaries[1]);
+		assert_eq!(pool.scores[&primaries[1]], BAD_COLLATION);
+
+		// unknown collators aren't scored.
+		let unknown: AccountId = [9; 32].into();
+		pool.note_good_collator(unknown);
+		assert!(!pool.scores.contains_key(&unknown));
+	}
+
+	#[test]
+	fn maintenance_rotates_better_backups_in() {
+		let mut pool = CollatorPool::new();
+		let para_id: ParaId = 5.into();
+		let primaries = add_primaries(&mut pool, para_id);
+		let backup = [1; 32].into();
+		assert_eq!(pool.on_new_collator(backup, para_id), Role::Backup);
+
+		assert!(pool.maintain_peers().is_empty());
+
+		*pool.scores.get_mut(&primaries[2]).unwrap() = -ROTATION_MARGIN + 1;
+		assert!(pool.maintain_peers().is_empty());
+
+		*pool.scores.get_mut(&primaries[2]).unwrap() = -ROTATION_MARGIN;
+		assert_eq!(pool.maintain_peers(), vec![
+			Action::NewRole(backup, Role::Primary),
+			Action::NewRole(primaries[2], Role::Backup),
+		]);
+		assert!(pool.maintain_peers().is_empty());
+	}
+
+	#[test]
+	fn latency_lowers_score() {
+		assert_eq!(latency_score(Duration::from_millis(500)), MAX_LATENCY_SCORE);
+		assert_eq!(latency_score(Duration::from_secs(1)), MAX_LATENCY_SCORE - 1);
+		assert_eq!(latency_score(Duration::from_secs(60)), MIN_LATENCY_SCORE);
+	}
+
 	#[test]
 	fn await_before_collation() {
 		let mut pool = CollatorPool::new();
@@ -0,0 +1,100 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compression of large messages.
+//!
+//! Encoded messages of at least `COMPRESSION_THRESHOLD` bytes are sent zstd-compressed
+//! to peers which negotiated compression. The uncompressed size travels along with the
+//! compressed data and may not exceed `MAX_DECOMPRESSED_SIZE`. It only limits the
+//! output: memory grows with the data actually decompressed, so a small message
+//! claiming a large size doesn't allocate more than its contents need.
+
+use std::io::{self, Read};
+
+/// Encoded messages smaller than this are sent uncompressed.
+pub(crate) const COMPRESSION_THRESHOLD: usize = 64 * 1024;
+/// Largest size a compressed message may decompress to.
+pub(crate) const MAX_DECOMPRESSED_SIZE: usize = 32 * 1024 * 1024;
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Compress an encoded message. Returns `None` if it is too small to be worth compressing,
+/// too large to be decompressed by the receiver, or does not compress.
+pub(crate) fn compress(data: &[u8]) -> Option<Vec<u8>> {
+	if data.len() < COMPRESSION_THRESHOLD || data.len() > MAX_DECOMPRESSED_SIZE {
+		return None
+	}
+
+	match ::zstd::block::compress(data, COMPRESSION_LEVEL) {
+		Ok(compressed) => if compressed.len() < data.len() { Some(compressed) } else { None },
+		Err(e) => {
+			debug!(target: "p_net", "Failed to compress message: {:?}", e);
+			None
+		}
+	}
+}
+
+/// Decompress a message of the given uncompressed size.
+pub(crate) fn decompress(data: &[u8], size: u32) -> io::Result<Vec<u8>> {
+	let size = size as usize;
+	if size > MAX_DECOMPRESSED_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Decompressed size exceeds limit"))
+	}
+
+	// decompress incrementally rather than into a buffer of the claimed size,
+	// reading at most one byte more than claimed to detect larger output.
+	let mut decompressed = Vec::new();
+	::zstd::stream::read::Decoder::with_buffer(data)?
+		.take(size as u64 + 1)
+		.read_to_end(&mut decompressed)?;
+
+	if decompressed.len() != size {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Decompressed size does not match"))
+	}
+
+	Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compresses_only_large_data() {
+		assert!(compress(&vec![1; COMPRESSION_THRESHOLD - 1]).is_none());
+
+		let data = vec![1; COMPRESSION_THRESHOLD];
+		let compressed = compress(&data).unwrap();
+		assert!(compressed.len() < data.len());
+		assert_eq!(decompress(&compressed, data.len() as u32).unwrap(), data);
+	}
+
+	#[test]
+	fn decompression_is_bounded() {
+		let data = vec![0; MAX_DECOMPRESSED_SIZE + 1];
+		let bomb = ::zstd::block::compress(&data, COMPRESSION_LEVEL).unwrap();
+
+		// the real size is refused outright, and understating it doesn't help.
+		assert!(decompress(&bomb, data.len() as u32).is_err());
+		assert!(decompress(&bomb, 1024).is_err());
+
+		// neither does overstating the size of a small message.
+		let small = ::zstd::block::compress(&[1; 100], COMPRESSION_LEVEL).unwrap();
+		assert!(decompress(&small, 200).is_err());
+		assert!(decompress(&small, MAX_DECOMPRESSED_SIZE as u32).is_err());
+		assert_eq!(decompress(&small, 100).unwrap(), vec![1; 100]);
+	}
+}
@@ -30,8 +30,10 @@ extern crate polkadot_primitives;
 
 extern crate arrayvec;
 extern crate parking_lot;
+extern crate rand;
 extern crate tokio;
 extern crate slice_group_by;
+extern crate zstd;
 
 #[macro_use]
 extern crate futures;
@@ -47,23 +49,36 @@ extern crate substrate_client;
 extern crate substrate_keyring;
 
 mod collator_pool;
+mod compression;
 mod local_collations;
 mod router;
+pub mod reputation;
+mod statement_distribution;
 pub mod validation;
 
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, Input, Output};
 use futures::sync::oneshot;
-use polkadot_primitives::{AccountId, Block, SessionKey, Hash, Header};
+use polkadot_primitives::{AccountId, Block, SessionKey, Hash, Header, Signature, key_to_account_id};
 use polkadot_primitives::parachain::{Id as ParaId, BlockData, CandidateReceipt, Collation};
+use polkadot_validation::SignedStatement;
 use substrate_network::{NodeIndex, RequestId, Context, Severity};
 use substrate_network::{message, generic_message};
 use substrate_network::specialization::NetworkSpecialization as Specialization;
 use substrate_network::StatusMessage as GenericFullStatus;
+use substrate_primitives::ed25519;
+use sr_primitives::traits::{BlakeTwo256, Hash as HashT, Verify};
 use self::validation::{LiveValidationSessions, RecentSessionKeys, InsertedRecentKey};
 use self::collator_pool::{CollatorPool, Role, Action};
 use self::local_collations::LocalCollations;
+use self::reputation::{Identity, Reputation, Score};
+use self::statement_distribution::StatementDistribution;
 
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub use self::router::DeferredEvictions;
 
 
 #[cfg(test)]
@@ -77,18 +92,261 @@ type FullStatus = GenericFullStatus<Block>;
 /// Specialization of the network service for the polkadot protocol.
 pub type NetworkService = ::substrate_network::Service<Block, PolkadotProtocol>;
 
+/// Current version of the polkadot sub-protocol.
+pub const CURRENT_VERSION: u32 = 5;
+
+/// Oldest version of the polkadot sub-protocol we still talk to.
+///
+/// Peers older than `KEY_CHALLENGE_VERSION` announce their session keys unsigned.
+/// Those keys are accepted but unauthenticated until this is raised to
+/// `KEY_CHALLENGE_VERSION`, which is the cut-over to signed announcements only.
+pub const MIN_SUPPORTED_VERSION: u32 = 1;
+
+// version assumed for peers whose status predates version negotiation.
+const LEGACY_VERSION: u32 = 1;
+
+// version introducing per-connection challenges for signed session key announcements.
+const KEY_CHALLENGE_VERSION: u32 = 2;
+
+// version introducing statements sent directly to peers.
+const TARGETED_STATEMENTS_VERSION: u32 = 3;
+
+// version introducing proofs of collator account ownership.
+const COLLATOR_AUTH_VERSION: u32 = 4;
+
+// version introducing compressed messages.
+const COMPRESSION_VERSION: u32 = 5;
+
+/// Optional features of the polkadot sub-protocol, as a bitset.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+	/// No optional features.
+	pub const NONE: Capabilities = Capabilities(0);
+	/// Exchange of erasure-coded chunks of candidate data.
+	pub const CHUNKS: Capabilities = Capabilities(1 << 0);
+	/// Compression of large messages.
+	pub const COMPRESSION: Capabilities = Capabilities(1 << 1);
+
+	/// Whether all capabilities in `other` are contained in `self`.
+	pub fn contains(&self, other: Capabilities) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	/// The capabilities contained in both `self` and `other`.
+	pub fn intersection(&self, other: Capabilities) -> Capabilities {
+		Capabilities(self.0 & other.0)
+	}
+
+	/// The capabilities contained in either `self` or `other`.
+	pub fn union(&self, other: Capabilities) -> Capabilities {
+		Capabilities(self.0 | other.0)
+	}
+}
+
 /// Status of a Polkadot node.
-#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Status {
 	collating_for: Option<(AccountId, ParaId)>,
+	/// Version of the polkadot sub-protocol spoken by the node.
+	version: u32,
+	/// Optional protocol features supported by the node.
+	capabilities: Capabilities,
+}
+
+impl Encode for Status {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		self.collating_for.encode_to(dest);
+		self.version.encode_to(dest);
+		self.capabilities.encode_to(dest);
+	}
 }
 
+impl Decode for Status {
+	fn decode<I: Input>(input: &mut I) -> Option<Self> {
+		let collating_for = Decode::decode(input)?;
+
+		// negotiation fields are appended so that legacy nodes can still decode
+		// the status of newer ones. if they are missing, the peer is a legacy node.
+		let (version, capabilities) = match u32::decode(input) {
+			Some(version) => (version, Capabilities::decode(input)?),
+			None => (LEGACY_VERSION, Capabilities::NONE),
+		};
+
+		Some(Status { collating_for, version, capabilities })
+	}
+}
+
+// domain separator for session key announcements.
+const SESSION_KEY_CONTEXT: &[u8] = b"polkadot-session-key";
+const COLLATOR_PROOF_CONTEXT: &[u8] = b"polkadot-collator-proof";
+
+/// A session key announcement, signed by the announced key.
+///
+/// The signature covers the key and a random challenge the receiving node issued for
+/// this connection with `Message::KeyChallenge`, so an announcement is only valid on
+/// that connection and cannot be replayed by a peer claiming someone else's key, neither
+/// to other nodes nor to the same node later on.
+///
+/// This proves that the holder of the key answered the challenge of this connection,
+/// not that the holder is the peer at the other end of it. The transport identity of
+/// peers is not exposed to the specialization, so it can't be covered by the signature,
+/// and a peer connected to a validator may relay the challenge to it and the
+/// announcement back while both connections are open. Such a peer is then sent the
+/// collations and block data requests meant for the validator, which it may drop.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct SessionKeyAnnouncement {
+	/// The announced session key.
+	pub key: SessionKey,
+	/// Signature by `key` over the key and the recipient's challenge.
+	pub signature: Signature,
+}
+
+impl SessionKeyAnnouncement {
+	fn payload(key: &SessionKey, challenge: &Hash) -> Vec<u8> {
+		let mut payload = SESSION_KEY_CONTEXT.to_vec();
+		key.encode_to(&mut payload);
+		challenge.encode_to(&mut payload);
+		payload
+	}
+
+	/// Sign an announcement of the given key for a connection with the given challenge.
+	pub fn new(key: &ed25519::Pair, challenge: &Hash) -> Self {
+		let session_key: SessionKey = key.public().into();
+		let signature = key.sign(&Self::payload(&session_key, challenge)).into();
+
+		SessionKeyAnnouncement { key: session_key, signature }
+	}
+
+	/// Check the announcement against the challenge we issued for the connection.
+	pub fn verify(&self, challenge: &Hash) -> bool {
+		self.signature.verify(&Self::payload(&self.key, challenge)[..], &self.key.into())
+	}
+}
+
+/// Proof that a peer collating for a parachain owns the collator account it claims.
+///
+/// Like `SessionKeyAnnouncement`, the signature covers the challenge the receiving
+/// node issued for the connection, so it is only valid on that connection.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct CollatorProof {
+	/// Signature by the collator account over the account, parachain and the
+	/// recipient's challenge.
+	pub signature: Signature,
+}
+
+impl CollatorProof {
+	fn payload(account_id: &AccountId, para_id: &ParaId, challenge: &Hash) -> Vec<u8> {
+		let mut payload = COLLATOR_PROOF_CONTEXT.to_vec();
+		account_id.encode_to(&mut payload);
+		para_id.encode_to(&mut payload);
+		challenge.encode_to(&mut payload);
+		payload
+	}
+
+	/// Prove ownership of the collator account of `key` on a connection with the given challenge.
+	pub fn new(key: &ed25519::Pair, para_id: &ParaId, challenge: &Hash) -> Self {
+		let signature = key.sign(&Self::payload(&key_to_account_id(key), para_id, challenge)).into();
+		CollatorProof { signature }
+	}
+
+	/// Check the proof for the claimed collator against the challenge we issued for the connection.
+	pub fn verify(&self, account_id: &AccountId, para_id: &ParaId, challenge: &Hash) -> bool {
+		self.signature.verify(&Self::payload(account_id, para_id, challenge)[..], account_id)
+	}
+}
+
+// time a peer has to answer the first block data request for a candidate.
+// doubles with every further peer asked, up to `MAX_REQUEST_TIMEOUT`.
+const BASE_REQUEST_TIMEOUT: Duration = Duration::from_secs(4);
+const MAX_REQUEST_TIMEOUT: Duration = Duration::from_secs(64);
+
+// time a request with no holder left to ask waits for another holder to connect,
+// counted from the deadline of the last peer asked.
+const HOLDER_GRACE_PERIOD: Duration = Duration::from_secs(16);
+
+// time late answers to a request are still accepted from a peer after we stopped
+// waiting for it, counted from its deadline.
+const LATE_RESPONSE_PERIOD: Duration = Duration::from_secs(64);
+
+fn request_timeout(attempts: usize) -> Duration {
+	let timeout = BASE_REQUEST_TIMEOUT * 2u32.pow(::std::cmp::min(attempts, 16) as u32);
+	::std::cmp::min(timeout, MAX_REQUEST_TIMEOUT)
+}
+
+/// Error when fetching block data from the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockDataError {
+	/// All connected peers known to hold the data were asked, none served it, and no
+	/// other holder connected in time.
+	AllPeersFailed,
+	/// The validation session the data was requested for has ended.
+	SessionEnded,
+}
+
+impl fmt::Display for BlockDataError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			BlockDataError::AllPeersFailed => write!(f, "No peer known to hold the block data served it"),
+			BlockDataError::SessionEnded => write!(f, "Validation session ended before block data was fetched"),
+		}
+	}
+}
+
+impl ::std::error::Error for BlockDataError {}
+
+// block data requested from the network. data served by peers is only passed to
+// `sender` after passing `verify`.
 struct BlockDataRequest {
 	attempted_peers: HashSet<SessionKey>,
 	validation_session_parent: Hash,
 	candidate_hash: Hash,
 	block_data_hash: Hash,
-	sender: oneshot::Sender<BlockData>,
+	sender: oneshot::Sender<Result<BlockData, BlockDataError>>,
+	// when the peer the request is in flight to must have answered. while pending,
+	// the deadline of the last peer asked, or when the request was made.
+	deadline: Instant,
+}
+
+impl BlockDataRequest {
+	// check that data served by a peer is the data committed to in the candidate
+	// receipt. returns the hash of the served data on mismatch.
+	fn verify(&self, data: BlockData) -> Result<BlockData, Hash> {
+		verify_block_data(&self.block_data_hash, data)
+	}
+
+	// whether this requests the same data as a stale request.
+	fn matches(&self, stale: &StaleRequest) -> bool {
+		self.candidate_hash == stale.candidate_hash && self.block_data_hash == stale.block_data_hash
+	}
+}
+
+// a request we stopped waiting for at a peer, because it timed out or another peer
+// answered first. answers are verified and accepted until `until`.
+struct StaleRequest {
+	candidate_hash: Hash,
+	block_data_hash: Hash,
+	until: Instant,
+}
+
+impl StaleRequest {
+	fn new(req: &BlockDataRequest) -> Self {
+		StaleRequest {
+			candidate_hash: req.candidate_hash,
+			block_data_hash: req.block_data_hash,
+			until: req.deadline + LATE_RESPONSE_PERIOD,
+		}
+	}
+}
+
+fn verify_block_data(block_data_hash: &Hash, data: BlockData) -> Result<BlockData, Hash> {
+	let hash = data.hash();
+	if &hash == block_data_hash {
+		Ok(data)
+	} else {
+		Err(hash)
+	}
 }
 
 // ensures collator-protocol messages are sent in correct order.
@@ -100,8 +358,8 @@ enum CollatorState {
 }
 
 impl CollatorState {
-	fn send_key<F: FnMut(Message)>(&mut self, key: SessionKey, mut f: F) {
-		f(Message::SessionKey(key));
+	fn send_key<F: FnMut(Message)>(&mut self, announcement: Message, mut f: F) {
+		f(announcement);
 		if let CollatorState::RolePending(role) = *self {
 			f(Message::CollatorRole(role));
 			*self = CollatorState::Primed(Some(role));
@@ -131,19 +389,59 @@ struct PeerInfo {
 	validator_keys: RecentSessionKeys,
 	claimed_validator: bool,
 	collator_state: CollatorState,
+	// random challenge issued to the peer on this connection. its session key
+	// announcements and collator proof must be signed over it.
+	local_challenge: Hash,
+	// challenge the peer issued to us on this connection, once received. our
+	// announcements and collator proof are signed over it.
+	remote_challenge: Option<Hash>,
+	// protocol version negotiated with this peer.
+	version: u32,
+	// capabilities supported by both us and this peer.
+	capabilities: Capabilities,
+	// whether the peer proved ownership of the collator account it claims and
+	// was registered with the collator pool.
+	collator_authenticated: bool,
 }
 
 impl PeerInfo {
 	fn should_send_key(&self) -> bool {
 		self.claimed_validator || self.collating_for.is_some()
 	}
+
+	// announce a local session key to this peer. the announcement is signed once the
+	// peer issued its challenge, legacy peers get the bare key.
+	fn announce_key(&self, key: &ed25519::Pair) -> Option<Message> {
+		if self.version < KEY_CHALLENGE_VERSION {
+			return Some(Message::SessionKey(key.public().into()))
+		}
+
+		self.remote_challenge.as_ref()
+			.map(|challenge| Message::SignedSessionKey(SessionKeyAnnouncement::new(key, challenge)))
+	}
+
+	// whether the given message may be exchanged with this peer.
+	fn supports(&self, message: &Message) -> bool {
+		self.version >= message.min_version() && self.capabilities.contains(message.required_capabilities())
+	}
+
+	// whether the session keys of this peer were signed over our challenge. keys of
+	// legacy peers are unauthenticated, and give way to authenticated announcements.
+	fn authenticates_keys(&self) -> bool {
+		self.version >= KEY_CHALLENGE_VERSION
+	}
+
+	// whether large messages to this peer should be compressed.
+	fn compresses(&self) -> bool {
+		self.version >= COMPRESSION_VERSION && self.capabilities.contains(Capabilities::COMPRESSION)
+	}
 }
 
 /// Polkadot-specific messages.
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode)]
 pub enum Message {
-	/// As a validator, tell the peer your current session key.
-	// TODO: do this with a cryptographic proof of some kind
+	/// As a validator, tell the peer your current session key. Only exchanged with
+	/// legacy peers, which can't sign announcements.
 	SessionKey(SessionKey),
 	/// Requesting parachain block data by (relay_parent, candidate_hash).
 	RequestBlockData(RequestId, Hash, Hash),
@@ -153,6 +451,53 @@ pub enum Message {
 	CollatorRole(Role),
 	/// A collation provided by a peer. Relay parent and collation.
 	Collation(Hash, Collation),
+	/// A statement sent directly to a validator which needs it. Relay parent and statement.
+	Statement(Hash, SignedStatement),
+	/// As a collator, prove ownership of the collator account claimed in the status
+	/// over the challenge received for the connection.
+	CollatorProof(CollatorProof),
+	/// A zstd-compressed message. Uncompressed size and the compressed encoding of the message.
+	Compressed(u32, Vec<u8>),
+	/// Random challenge which session key announcements and collator proofs on this
+	/// connection must be signed over. Sent once by each side after connecting.
+	KeyChallenge(Hash),
+	/// As a validator, tell the peer your current session key, signed over its challenge.
+	SignedSessionKey(SessionKeyAnnouncement),
+	// New variants must be appended, and gated in `min_version` and
+	// `required_capabilities` so they are only sent to peers understanding them.
+}
+
+impl Message {
+	/// The lowest protocol version in which this message exists.
+	pub fn min_version(&self) -> u32 {
+		match *self {
+			Message::SessionKey(_)
+				| Message::RequestBlockData(..)
+				| Message::BlockData(..)
+				| Message::CollatorRole(_)
+				| Message::Collation(..) => LEGACY_VERSION,
+			Message::Statement(..) => TARGETED_STATEMENTS_VERSION,
+			Message::CollatorProof(_) => COLLATOR_AUTH_VERSION,
+			Message::Compressed(..) => COMPRESSION_VERSION,
+			Message::KeyChallenge(_) | Message::SignedSessionKey(_) => KEY_CHALLENGE_VERSION,
+		}
+	}
+
+	/// The capabilities both sides need to exchange this message.
+	pub fn required_capabilities(&self) -> Capabilities {
+		match *self {
+			Message::SessionKey(_)
+				| Message::RequestBlockData(..)
+				| Message::BlockData(..)
+				| Message::CollatorRole(_)
+				| Message::Collation(..)
+				| Message::Statement(..)
+				| Message::CollatorProof(_)
+				| Message::KeyChallenge(_)
+				| Message::SignedSessionKey(_) => Capabilities::NONE,
+			Message::Compressed(..) => Capabilities::COMPRESSION,
+		}
+	}
 }
 
 fn send_polkadot_message(ctx: &mut Context<Block>, to: NodeIndex, message: Message) {
@@ -161,39 +506,104 @@ fn send_polkadot_message(ctx: &mut Context<Block>, to: NodeIndex, message: Messa
 	ctx.send_message(to, generic_message::Message::ChainSpecific(encoded))
 }
 
+// send a potentially large message, compressing it if the peer supports compression.
+fn send_compressible_message(ctx: &mut Context<Block>, to: NodeIndex, compress: bool, message: Message) {
+	let encoded = message.encode();
+	let compressed = if compress { compression::compress(&encoded) } else { None };
+
+	match compressed {
+		Some(compressed) => {
+			trace!(target: "p_net", "Sending polkadot message to {}, compressed from {} to {} bytes: {:?}",
+				to, encoded.len(), compressed.len(), message);
+			let encoded = Message::Compressed(encoded.len() as u32, compressed).encode();
+			ctx.send_message(to, generic_message::Message::ChainSpecific(encoded))
+		}
+		None => {
+			trace!(target: "p_net", "Sending polkadot message to {}: {:?}", to, message);
+			ctx.send_message(to, generic_message::Message::ChainSpecific(encoded))
+		}
+	}
+}
+
+// announce the recent local session keys to a peer.
+fn send_session_keys(ctx: &mut Context<Block>, who: NodeIndex, info: &mut PeerInfo, sessions: &LiveValidationSessions) {
+	for local_session_key in sessions.recent_keys() {
+		let announcement = sessions.key_pair(local_session_key)
+			.and_then(|pair| info.announce_key(&pair));
+
+		if let Some(announcement) = announcement {
+			info.collator_state.send_key(announcement, |msg| send_polkadot_message(
+				ctx,
+				who,
+				msg,
+			));
+		}
+	}
+}
+
+// change the reputation of a peer, disconnecting it if that got it banned.
+fn note_reputation(reputation: &mut Reputation, ctx: &mut Context<Block>, who: NodeIndex, change: Score, reason: &str) {
+	trace!(target: "p_net", "Reputation change of {} for peer {}: {}", change, who, reason);
+	if reputation.note(who, change) {
+		debug!(target: "p_net", "Banning peer {}: {}", who, reason);
+		ctx.report_peer(who, Severity::Bad(format!("Reputation too low: {}", reason)));
+	}
+}
+
 /// Polkadot protocol attachment for substrate.
 pub struct PolkadotProtocol {
 	peers: HashMap<NodeIndex, PeerInfo>,
 	collating_for: Option<(AccountId, ParaId)>,
+	collator_key: Option<Arc<ed25519::Pair>>,
 	collators: CollatorPool,
+	// accounts allowed to collate on permissioned parachains.
+	collator_whitelists: HashMap<ParaId, Vec<AccountId>>,
 	validators: HashMap<SessionKey, NodeIndex>,
 	local_collations: LocalCollations<Collation>,
 	live_validation_sessions: LiveValidationSessions,
 	in_flight: HashMap<(RequestId, NodeIndex), BlockDataRequest>,
+	stale_requests: HashMap<(RequestId, NodeIndex), StaleRequest>,
 	pending: Vec<BlockDataRequest>,
 	extrinsic_store: Option<::av_store::Store>,
 	next_req_id: u64,
+	capabilities: Capabilities,
+	reputation: Reputation,
+	statement_distribution: StatementDistribution,
+	deferred_evictions: DeferredEvictions,
 }
 
 impl PolkadotProtocol {
-	/// Instantiate a polkadot protocol handler.
-	pub fn new(collating_for: Option<(AccountId, ParaId)>) -> Self {
+	/// Instantiate a polkadot protocol handler, collating for the given parachain
+	/// with the given collator key if any.
+	pub fn new(collating_for: Option<(Arc<ed25519::Pair>, ParaId)>) -> Self {
 		PolkadotProtocol {
 			peers: HashMap::new(),
 			collators: CollatorPool::new(),
-			collating_for,
+			collator_whitelists: HashMap::new(),
+			collating_for: collating_for.as_ref().map(|&(ref key, para_id)| (key_to_account_id(key), para_id)),
+			collator_key: collating_for.map(|(key, _)| key),
 			validators: HashMap::new(),
 			local_collations: LocalCollations::new(),
 			live_validation_sessions: LiveValidationSessions::new(),
 			in_flight: HashMap::new(),
+			stale_requests: HashMap::new(),
 			pending: Vec::new(),
 			extrinsic_store: None,
 			next_req_id: 1,
+			capabilities: Capabilities::COMPRESSION,
+			reputation: Reputation::new(),
+			statement_distribution: StatementDistribution::new(),
+			deferred_evictions: DeferredEvictions::default(),
 		}
 	}
 
 	/// Fetch block data by candidate receipt.
-	fn fetch_block_data(&mut self, ctx: &mut Context<Block>, candidate: &CandidateReceipt, relay_parent: Hash) -> oneshot::Receiver<BlockData> {
+	fn fetch_block_data(
+		&mut self,
+		ctx: &mut Context<Block>,
+		candidate: &CandidateReceipt,
+		relay_parent: Hash,
+	) -> oneshot::Receiver<Result<BlockData, BlockDataError>> {
 		let (tx, rx) = oneshot::channel();
 
 		self.pending.push(BlockDataRequest {
@@ -202,6 +612,7 @@ impl PolkadotProtocol {
 			candidate_hash: candidate.hash(),
 			block_data_hash: candidate.block_data_hash,
 			sender: tx,
+			deadline: Instant::now(),
 		});
 
 		self.dispatch_pending_requests(ctx);
@@ -219,17 +630,60 @@ impl PolkadotProtocol {
 			for (id, peer_data) in self.peers.iter_mut()
 				.filter(|&(_, ref info)| info.should_send_key())
 			{
-				peer_data.collator_state.send_key(new_local, |msg| send_polkadot_message(
-					ctx,
-					*id,
-					msg
-				));
+				if let Some(announcement) = peer_data.announce_key(&new_local) {
+					peer_data.collator_state.send_key(announcement, |msg| send_polkadot_message(
+						ctx,
+						*id,
+						msg
+					));
+				}
 			}
 		}
 	}
 
 	fn remove_validation_session(&mut self, parent_hash: &Hash) {
-		self.live_validation_sessions.remove(parent_hash);
+		if let Some(validation_session) = self.live_validation_sessions.remove(parent_hash) {
+			let evictions = validation_session.expire_deferred();
+			debug!(target: "p_net", "Statements about unknown candidates on {:?} rejected or evicted: {:?}",
+				parent_hash, evictions);
+			self.deferred_evictions += evictions;
+		}
+		self.statement_distribution.remove_relay_parent(parent_hash);
+	}
+
+	/// Send a candidate statement to the validators with the given session keys.
+	///
+	/// Returns `true` if some of them can't be reached directly, because they are not
+	/// connected or don't support direct statements yet.
+	fn distribute_candidate_statement(
+		&mut self,
+		ctx: &mut Context<Block>,
+		relay_parent: Hash,
+		targets: HashSet<SessionKey>,
+		statement: SignedStatement,
+	) -> bool {
+		let message = Message::Statement(relay_parent, statement);
+		let encoded = message.encode();
+		let statement_hash = BlakeTwo256::hash(&encoded[..]);
+
+		let mut unreachable = false;
+		for key in targets {
+			let who = match self.validators.get(&key) {
+				Some(who) if self.peers.get(who).map_or(false, |info| info.supports(&message)) => *who,
+				_ => {
+					debug!(target: "p_net", "Validator {:?} can't be sent statements directly", key);
+					unreachable = true;
+					continue
+				}
+			};
+
+			if self.statement_distribution.note_sent(relay_parent, who, statement_hash) {
+				trace!(target: "p_net", "Sending statement {:?} to validator {:?}", statement_hash, key);
+				ctx.send_message(who, generic_message::Message::ChainSpecific(encoded.clone()));
+			}
+		}
+
+		unreachable
 	}
 
 	fn dispatch_pending_requests(&mut self, ctx: &mut Context<Block>) {
@@ -237,6 +691,8 @@ impl PolkadotProtocol {
 		let validator_keys = &mut self.validators;
 		let next_req_id = &mut self.next_req_id;
 		let in_flight = &mut self.in_flight;
+		let reputation = &self.reputation;
+		let now = Instant::now();
 
 		for mut pending in ::std::mem::replace(&mut self.pending, Vec::new()) {
 			let parent = pending.validation_session_parent;
@@ -245,17 +701,23 @@ impl PolkadotProtocol {
 			let still_pending = self.live_validation_sessions.with_block_data(&parent, &c_hash, |x| match x {
 				Ok(data @ &_) => {
 					// answer locally.
-					let _ = pending.sender.send(data.clone());
+					let _ = pending.sender.send(Ok(data.clone()));
 					None
 				}
 				Err(Some(known_keys)) => {
-					let next_peer = known_keys.iter()
+					// try the untried peer with the best reputation first.
+					let mut candidates: Vec<_> = known_keys.iter()
 						.filter_map(|x| validator_keys.get(x).map(|id| (*x, *id)))
-						.find(|&(ref key, _)| pending.attempted_peers.insert(*key))
-						.map(|(_, id)| id);
+						.filter(|&(ref key, ref id)| !pending.attempted_peers.contains(key) && !reputation.is_banned(id))
+						.collect();
+
+					candidates.sort_by_key(|&(_, ref id)| ::std::cmp::Reverse(reputation.score(id)));
+
+					// dispatch to peer, giving each further peer more time to answer.
+					if let Some((key, who)) = candidates.into_iter().next() {
+						pending.deadline = now + request_timeout(pending.attempted_peers.len());
+						pending.attempted_peers.insert(key);
 
-					// dispatch to peer
-					if let Some(who) = next_peer {
 						let req_id = *next_req_id;
 						*next_req_id += 1;
 
@@ -267,12 +729,20 @@ impl PolkadotProtocol {
 
 						in_flight.insert((req_id, who), pending);
 
+						None
+					} else if !known_keys.is_empty() && known_keys.iter().all(|k| pending.attempted_peers.contains(k)) {
+						debug!(target: "p_net", "Giving up on block data for candidate {:?}: all holders tried", c_hash);
+						let _ = pending.sender.send(Err(BlockDataError::AllPeersFailed));
 						None
 					} else {
 						Some(pending)
 					}
 				}
-				Err(None) => None, // no such known validation session. prune out.
+				Err(None) => {
+					// no such known validation session. prune out.
+					let _ = pending.sender.send(Err(BlockDataError::SessionEnded));
+					None
+				}
 			});
 
 			if let Some(pending) = still_pending {
@@ -285,8 +755,16 @@ impl PolkadotProtocol {
 
 	fn on_polkadot_message(&mut self, ctx: &mut Context<Block>, who: NodeIndex, msg: Message) {
 		trace!(target: "p_net", "Polkadot message from {}: {:?}", who, msg);
+
+		let supported = self.peers.get(&who).map_or(true, |info| info.supports(&msg));
+		if !supported {
+			ctx.report_peer(who, Severity::Bad("Sent message not negotiated for the connection".to_string()));
+			return
+		}
+
 		match msg {
-			Message::SessionKey(key) => self.on_session_key(ctx, who, key),
+			Message::SessionKey(key) => self.on_legacy_session_key(ctx, who, key),
+			Message::SignedSessionKey(announcement) => self.on_session_key(ctx, who, announcement),
 			Message::RequestBlockData(req_id, relay_parent, candidate_hash) => {
 				let block_data = self.live_validation_sessions
 					.with_block_data(
@@ -298,22 +776,169 @@ impl PolkadotProtocol {
 						.and_then(|s| s.block_data(relay_parent, candidate_hash))
 					);
 
-				send_polkadot_message(ctx, who, Message::BlockData(req_id, block_data));
+				let compress = self.peers.get(&who).map_or(false, |info| info.compresses());
+				send_compressible_message(ctx, who, compress, Message::BlockData(req_id, block_data));
 			}
 			Message::BlockData(req_id, data) => self.on_block_data(ctx, who, req_id, data),
 			Message::Collation(relay_parent, collation) => self.on_collation(ctx, who, relay_parent, collation),
 			Message::CollatorRole(role) => self.on_new_role(ctx, who, role),
+			Message::Statement(relay_parent, statement) => self.on_statement(ctx, who, relay_parent, statement),
+			Message::CollatorProof(proof) => self.on_collator_proof(ctx, who, proof),
+			Message::Compressed(size, data) => self.on_compressed_message(ctx, who, size, data),
+			Message::KeyChallenge(challenge) => self.on_key_challenge(ctx, who, challenge),
 		}
 	}
 
-	fn on_session_key(&mut self, ctx: &mut Context<Block>, who: NodeIndex, key: SessionKey) {
-		{
-			let info = match self.peers.get_mut(&who) {
-				Some(peer) => peer,
+	fn on_compressed_message(&mut self, ctx: &mut Context<Block>, who: NodeIndex, size: u32, data: Vec<u8>) {
+		let decompressed = match compression::decompress(&data, size) {
+			Ok(decompressed) => decompressed,
+			Err(e) => {
+				trace!(target: "p_net", "Bad compressed message from {}: {:?}", who, e);
+				ctx.report_peer(who, Severity::Bad("Invalid compressed polkadot message".to_string()));
+				return
+			}
+		};
+
+		match Message::decode(&mut decompressed.as_slice()) {
+			Some(Message::Compressed(..)) => {
+				ctx.report_peer(who, Severity::Bad("Nested compressed polkadot message".to_string()))
+			}
+			Some(msg) => self.on_polkadot_message(ctx, who, msg),
+			None => {
+				trace!(target: "p_net", "Bad message from {}", who);
+				ctx.report_peer(who, Severity::Bad("Invalid polkadot protocol message format".to_string()));
+			}
+		}
+	}
+
+	// the peer issued the challenge our announcements and collator proof must be
+	// signed over on this connection.
+	fn on_key_challenge(&mut self, ctx: &mut Context<Block>, who: NodeIndex, challenge: Hash) {
+		let info = match self.peers.get_mut(&who) {
+			Some(peer) => peer,
+			None => {
+				trace!(target: "p_net", "Network inconsistency: message received from unconnected peer {}", who);
+				return
+			}
+		};
+
+		if info.remote_challenge.is_some() {
+			ctx.report_peer(who, Severity::Bad("Sent key challenge twice".to_string()));
+			return
+		}
+
+		info.remote_challenge = Some(challenge);
+
+		// prove ownership of our collator account.
+		if let (Some((_, ref para_id)), Some(ref key)) = (self.collating_for, &self.collator_key) {
+			let proof = Message::CollatorProof(CollatorProof::new(key, para_id, &challenge));
+			if info.supports(&proof) {
+				send_polkadot_message(ctx, who, proof);
+			}
+		}
+
+		if info.should_send_key() {
+			send_session_keys(ctx, who, info, &self.live_validation_sessions);
+		}
+	}
+
+	// legacy peers announce their session keys without signature. those can't be told
+	// apart from keys claimed by somebody else, so they are accepted unauthenticated
+	// until the cut-over, and never take a key over from a peer which signed it.
+	fn on_legacy_session_key(&mut self, ctx: &mut Context<Block>, who: NodeIndex, key: SessionKey) {
+		match self.peers.get(&who) {
+			Some(info) if info.authenticates_keys() => {
+				ctx.report_peer(who, Severity::Bad("Sent session key without signature".to_string()));
+				return
+			}
+			Some(_) => {}
+			None => {
+				trace!(target: "p_net", "Network inconsistency: message received from unconnected peer {}", who);
+				return
+			}
+		}
+
+		let claimed_by_other = self.validators.get(&key)
+			.and_then(|holder| self.peers.get(holder).filter(|_| *holder != who))
+			.map_or(false, |holder| holder.authenticates_keys());
+
+		if claimed_by_other {
+			trace!(target: "p_net", "Ignoring unsigned session key {:?} of legacy peer {} announced by another peer", key, who);
+			return
+		}
+
+		debug!(target: "p_net", "Unauthenticated session key {:?} of legacy peer {}", key, who);
+		self.note_session_key(ctx, who, key);
+	}
+
+	fn on_collator_proof(&mut self, ctx: &mut Context<Block>, who: NodeIndex, proof: CollatorProof) {
+		let (account_id, para_id, challenge) = match self.peers.get(&who) {
+			None => {
+				trace!(target: "p_net", "Network inconsistency: message received from unconnected peer {}", who);
+				return
+			}
+			Some(info) => match info.collating_for {
 				None => {
-					trace!(target: "p_net", "Network inconsistency: message received from unconnected peer {}", who);
+					ctx.report_peer(who, Severity::Bad("Sent collator proof without registering collator intent".to_string()));
 					return
 				}
+				Some(_) if info.collator_authenticated => {
+					ctx.report_peer(who, Severity::Bad("Sent collator proof twice".to_string()));
+					return
+				}
+				Some((account_id, para_id)) => (account_id, para_id, info.local_challenge),
+			},
+		};
+
+		if !proof.verify(&account_id, &para_id, &challenge) {
+			ctx.report_peer(who, Severity::Bad("Collator proof with invalid signature".to_string()));
+			return
+		}
+
+		if !self.collator_allowed(&account_id, &para_id) {
+			ctx.report_peer(who, Severity::Useless("Collator not allowed on parachain".to_string()));
+			return
+		}
+
+		if self.collator_peer(account_id).is_some() {
+			ctx.report_peer(who, Severity::Useless("Collator account already connected".to_string()));
+			return
+		}
+
+		if self.reputation.identify(who, Identity::Collator(account_id)) {
+			ctx.report_peer(who, Severity::Bad("Collator still banned".to_string()));
+			return
+		}
+
+		let collator_role = self.collators.on_new_collator(account_id, para_id);
+		if let Some(info) = self.peers.get_mut(&who) {
+			info.collator_authenticated = true;
+			info.collator_state.set_role(collator_role, |msg| send_polkadot_message(
+				ctx,
+				who,
+				msg,
+			));
+		}
+	}
+
+	fn on_session_key(&mut self, ctx: &mut Context<Block>, who: NodeIndex, announcement: SessionKeyAnnouncement) {
+		match self.peers.get(&who) {
+			Some(info) if !announcement.verify(&info.local_challenge) => {
+				ctx.report_peer(who, Severity::Bad("Session key announcement with invalid signature".to_string()));
+			}
+			Some(_) => self.note_session_key(ctx, who, announcement.key),
+			None => {
+				trace!(target: "p_net", "Network inconsistency: message received from unconnected peer {}", who);
+			}
+		}
+	}
+
+	// a validator peer announced its session key, signed or unauthenticated.
+	fn note_session_key(&mut self, ctx: &mut Context<Block>, who: NodeIndex, key: SessionKey) {
+		{
+			let info = match self.peers.get_mut(&who) {
+				Some(peer) => peer,
+				None => return,
 			};
 
 			if !info.claimed_validator {
@@ -321,11 +946,19 @@ impl PolkadotProtocol {
 				return;
 			}
 
+			// unauthenticated keys could smear the reputation of their actual holder.
+			if info.authenticates_keys() && self.reputation.identify(who, Identity::Validator(key)) {
+				ctx.report_peer(who, Severity::Bad("Validator still banned".to_string()));
+				return;
+			}
+
 			let local_collations = &mut self.local_collations;
 			let new_collations = match info.validator_keys.insert(key) {
 				InsertedRecentKey::AlreadyKnown => Vec::new(),
 				InsertedRecentKey::New(Some(old_key)) => {
-					self.validators.remove(&old_key);
+					if self.validators.get(&old_key) == Some(&who) {
+						self.validators.remove(&old_key);
+					}
 					local_collations.fresh_key(&old_key, &key)
 				}
 				InsertedRecentKey::New(None) => info.collator_state.role()
@@ -333,10 +966,12 @@ impl PolkadotProtocol {
 					.unwrap_or_else(Vec::new),
 			};
 
+			let compress = info.compresses();
 			for (relay_parent, collation) in new_collations {
-				send_polkadot_message(
+				send_compressible_message(
 					ctx,
 					who,
+					compress,
 					Message::Collation(relay_parent, collation),
 				)
 			}
@@ -348,19 +983,137 @@ impl PolkadotProtocol {
 	}
 
 	fn on_block_data(&mut self, ctx: &mut Context<Block>, who: NodeIndex, req_id: RequestId, data: Option<BlockData>) {
-		match self.in_flight.remove(&(req_id, who)) {
-			Some(req) => {
-				if let Some(data) = data {
-					if data.hash() == req.block_data_hash {
-						let _ = req.sender.send(data);
-						return
-					}
-				}
+		let req = match self.in_flight.remove(&(req_id, who)) {
+			Some(req) => req,
+			None => return self.on_late_block_data(ctx, who, req_id, data),
+		};
+
+		match data.map(|data| req.verify(data)) {
+			Some(Ok(data)) => {
+				note_reputation(&mut self.reputation, ctx, who, reputation::GOOD_BLOCK_DATA, "Served block data");
+				let _ = req.sender.send(Ok(data));
+				return
+			}
+			Some(Err(served_hash)) => {
+				debug!(target: "p_net", "Peer {} served block data with hash {:?} for candidate {:?}, expected {:?}",
+					who, served_hash, req.candidate_hash, req.block_data_hash);
+				note_reputation(&mut self.reputation, ctx, who, reputation::INVALID_BLOCK_DATA, "Served invalid block data");
+			}
+			None => note_reputation(&mut self.reputation, ctx, who, reputation::MISSING_BLOCK_DATA, "Served no block data"),
+		}
+
+		// try the next holder.
+		self.pending.push(req);
+		self.dispatch_pending_requests(ctx);
+	}
+
+	// a peer answered a request we stopped waiting for. correct data still fulfils
+	// the request if nobody else served it yet. not answering in time was already
+	// penalised.
+	fn on_late_block_data(&mut self, ctx: &mut Context<Block>, who: NodeIndex, req_id: RequestId, data: Option<BlockData>) {
+		let stale = match self.stale_requests.remove(&(req_id, who)) {
+			Some(stale) => stale,
+			None => {
+				note_reputation(&mut self.reputation, ctx, who, reputation::UNEXPECTED_RESPONSE, "Unexpected block data response");
+				return
+			}
+		};
+
+		match data.map(|data| verify_block_data(&stale.block_data_hash, data)) {
+			Some(Ok(data)) => {
+				note_reputation(&mut self.reputation, ctx, who, reputation::GOOD_BLOCK_DATA, "Served block data late");
+				self.fulfil_requests(&stale, data);
+			}
+			Some(Err(served_hash)) => {
+				debug!(target: "p_net", "Peer {} served block data with hash {:?} for candidate {:?}, expected {:?}",
+					who, served_hash, stale.candidate_hash, stale.block_data_hash);
+				note_reputation(&mut self.reputation, ctx, who, reputation::INVALID_BLOCK_DATA, "Served invalid block data");
+			}
+			None => {}
+		}
+	}
+
+	// answer the requests for the data of a stale request with the data served late.
+	// answers of peers the requests are in flight to are still accepted.
+	fn fulfil_requests(&mut self, stale: &StaleRequest, data: BlockData) {
+		let (fulfilled, pending): (Vec<_>, Vec<_>) = ::std::mem::replace(&mut self.pending, Vec::new())
+			.into_iter()
+			.partition(|req| req.matches(stale));
+
+		self.pending = pending;
+
+		let in_flight: Vec<_> = self.in_flight.iter()
+			.filter(|&(_, req)| req.matches(stale))
+			.map(|(key, _)| *key)
+			.collect();
+
+		let mut fulfilled = fulfilled;
+		for key in in_flight {
+			if let Some(req) = self.in_flight.remove(&key) {
+				self.stale_requests.insert(key, StaleRequest::new(&req));
+				fulfilled.push(req);
+			}
+		}
+
+		for req in fulfilled {
+			let _ = req.sender.send(Ok(data.clone()));
+		}
+	}
 
+	// a validator sent us a statement directly.
+	fn on_statement(&mut self, ctx: &mut Context<Block>, who: NodeIndex, relay_parent: Hash, statement: SignedStatement) {
+		let signed_by_peer = match self.peers.get(&who) {
+			Some(info) => info.validator_keys.as_slice().contains(&statement.sender),
+			None => {
+				trace!(target: "p_net", "Network inconsistency: message received from unconnected peer {}", who);
+				return
+			}
+		};
+
+		// peers only send their own statements directly. the signature is checked
+		// by the validation session.
+		if !signed_by_peer {
+			note_reputation(&mut self.reputation, ctx, who, reputation::UNEXPECTED_STATEMENT, "Sent statement of another validator");
+			return
+		}
+
+		if !self.statement_distribution.allow_incoming(who, Instant::now()) {
+			note_reputation(&mut self.reputation, ctx, who, reputation::STATEMENT_FLOOD, "Exceeded statement rate limit");
+			return
+		}
+
+		if !self.live_validation_sessions.forward_statement(&relay_parent, statement.encode()) {
+			trace!(target: "p_net", "Dropping statement from {} on unknown relay parent {:?}", who, relay_parent);
+		}
+	}
+
+	// move requests whose peer missed the deadline back to pending. they are
+	// dispatched to the next holder of the data, if any. pending requests which
+	// found no holder to ask within the grace period fail.
+	fn time_out_requests(&mut self, ctx: &mut Context<Block>, now: Instant) {
+		let (expired, waiting): (Vec<_>, Vec<_>) = ::std::mem::replace(&mut self.pending, Vec::new())
+			.into_iter()
+			.partition(|req| req.deadline + HOLDER_GRACE_PERIOD <= now);
+
+		self.pending = waiting;
+		self.stale_requests.retain(|_, stale| stale.until > now);
+		for req in expired {
+			debug!(target: "p_net", "Giving up on block data for candidate {:?}: no holder left to ask", req.candidate_hash);
+			let _ = req.sender.send(Err(BlockDataError::AllPeersFailed));
+		}
+
+		let timed_out: Vec<_> = self.in_flight.iter()
+			.filter(|&(_, req)| req.deadline <= now)
+			.map(|(key, _)| *key)
+			.collect();
+
+		for (req_id, who) in timed_out {
+			if let Some(req) = self.in_flight.remove(&(req_id, who)) {
+				debug!(target: "p_net", "Block data request {} to peer {} timed out", req_id, who);
+				note_reputation(&mut self.reputation, ctx, who, reputation::UNANSWERED_REQUEST, "Block data request timed out");
+				self.stale_requests.insert((req_id, who), StaleRequest::new(&req));
 				self.pending.push(req);
-				self.dispatch_pending_requests(ctx);
 			}
-			None => ctx.report_peer(who, Severity::Bad("Unexpected block data response".to_string())),
 		}
 	}
 
@@ -382,18 +1135,23 @@ impl PolkadotProtocol {
 				Severity::Bad("Sent collator role without registering first as validator".to_string()),
 			);
 		} else {
-			// update role for all saved session keys for this validator.
+			// update role for all saved session keys for this validator, except
+			// for unauthenticated keys another peer has taken over since.
 			let local_collations = &mut self.local_collations;
+			let validators = &self.validators;
+			let compress = info.compresses();
 			for (relay_parent, collation) in info.validator_keys
 				.as_slice()
 				.iter()
 				.cloned()
+				.filter(|k| validators.get(k) == Some(&who))
 				.flat_map(|k| local_collations.note_validator_role(k, role))
 			{
 				debug!(target: "p_net", "Broadcasting collation on relay parent {:?}", relay_parent);
-				send_polkadot_message(
+				send_compressible_message(
 					ctx,
 					who,
+					compress,
 					Message::Collation(relay_parent, collation),
 				)
 			}
@@ -403,45 +1161,66 @@ impl PolkadotProtocol {
 
 impl Specialization<Block> for PolkadotProtocol {
 	fn status(&self) -> Vec<u8> {
-		Status { collating_for: self.collating_for.clone() }.encode()
+		Status {
+			collating_for: self.collating_for.clone(),
+			version: CURRENT_VERSION,
+			capabilities: self.capabilities,
+		}.encode()
 	}
 
 	fn on_connect(&mut self, ctx: &mut Context<Block>, who: NodeIndex, status: FullStatus) {
-		let local_status = match Status::decode(&mut &status.chain_status[..]) {
+		let remote_status = match Status::decode(&mut &status.chain_status[..]) {
 			Some(status) => status,
 			None => {
-				Status { collating_for: None }
+				ctx.report_peer(who, Severity::Bad("Invalid polkadot protocol status".to_string()));
+				return
 			}
 		};
 
+		if remote_status.version < MIN_SUPPORTED_VERSION {
+			ctx.report_peer(who, Severity::Useless(
+				format!("Unsupported polkadot protocol version {}", remote_status.version)
+			));
+			return
+		}
+
 		let validator = status.roles.contains(substrate_network::config::Roles::AUTHORITY);
 
 		let mut peer_info = PeerInfo {
-			collating_for: local_status.collating_for,
+			collating_for: remote_status.collating_for.clone(),
 			validator_keys: Default::default(),
 			claimed_validator: validator,
 			collator_state: CollatorState::Fresh,
+			local_challenge: rand::random::<[u8; 32]>().into(),
+			remote_challenge: None,
+			version: ::std::cmp::min(remote_status.version, CURRENT_VERSION),
+			capabilities: self.capabilities.intersection(remote_status.capabilities),
+			collator_authenticated: false,
 		};
 
-		if let Some((ref acc_id, ref para_id)) = local_status.collating_for {
-			if self.collator_peer(acc_id.clone()).is_some() {
-				ctx.report_peer(who, Severity::Useless("Unknown Polkadot-specific reason".to_string()));
+		// collators proving account ownership are registered once the proof arrives.
+		// older collators can't, and are only trusted on parachains without whitelist.
+		if let Some((ref acc_id, ref para_id)) = remote_status.collating_for {
+			if !self.collator_allowed(acc_id, para_id) {
+				ctx.report_peer(who, Severity::Useless("Collator not allowed on parachain".to_string()));
 				return
 			}
 
-			let collator_role = self.collators.on_new_collator(acc_id.clone(), para_id.clone());
+			if peer_info.version < COLLATOR_AUTH_VERSION {
+				if self.collator_whitelists.contains_key(para_id) {
+					ctx.report_peer(who, Severity::Useless("Collator cannot prove account ownership".to_string()));
+					return
+				}
 
-			peer_info.collator_state.set_role(collator_role, |msg| send_polkadot_message(
-				ctx,
-				who,
-				msg,
-			));
-		}
+				if self.collator_peer(acc_id.clone()).is_some() {
+					ctx.report_peer(who, Severity::Useless("Unknown Polkadot-specific reason".to_string()));
+					return
+				}
+
+				let collator_role = self.collators.on_new_collator(acc_id.clone(), para_id.clone());
 
-		// send session keys.
-		if peer_info.should_send_key() {
-			for local_session_key in self.live_validation_sessions.recent_keys() {
-				peer_info.collator_state.send_key(*local_session_key, |msg| send_polkadot_message(
+				peer_info.collator_authenticated = true;
+				peer_info.collator_state.set_role(collator_role, |msg| send_polkadot_message(
 					ctx,
 					who,
 					msg,
@@ -449,13 +1228,39 @@ impl Specialization<Block> for PolkadotProtocol {
 			}
 		}
 
+		// session keys and our collator proof are sent once the peer issued its challenge.
+		// legacy peers can't issue one, and get our bare session keys right away.
+		let challenge = Message::KeyChallenge(peer_info.local_challenge);
+		if peer_info.supports(&challenge) {
+			send_polkadot_message(ctx, who, challenge);
+		} else if peer_info.should_send_key() {
+			send_session_keys(ctx, who, &mut peer_info, &self.live_validation_sessions);
+		}
+
 		self.peers.insert(who, peer_info);
+		self.reputation.on_connect(who);
 		self.dispatch_pending_requests(ctx);
 	}
 
 	fn on_disconnect(&mut self, ctx: &mut Context<Block>, who: NodeIndex) {
 		if let Some(info) = self.peers.remove(&who) {
-			if let Some((acc_id, _)) = info.collating_for {
+			// requests in flight to the peer go back to pending.
+			let unanswered: Vec<_> = self.in_flight.keys()
+				.filter(|&&(_, ref peer)| peer == &who)
+				.cloned()
+				.collect();
+
+			for key in unanswered {
+				if let Some(req) = self.in_flight.remove(&key) {
+					note_reputation(&mut self.reputation, ctx, who, reputation::UNANSWERED_REQUEST, "Disconnected with request in flight");
+					self.pending.push(req);
+				}
+			}
+			self.stale_requests.retain(|&(_, ref peer), _| peer != &who);
+			self.reputation.on_disconnect(who);
+			self.statement_distribution.on_disconnect(who);
+
+			if let (Some((acc_id, _)), true) = (info.collating_for, info.collator_authenticated) {
 				let new_primary = self.collators.on_disconnect(acc_id)
 					.and_then(|new_primary| self.collator_peer(new_primary));
 
@@ -469,28 +1274,12 @@ impl Specialization<Block> for PolkadotProtocol {
 			}
 
 			for key in info.validator_keys.as_slice().iter() {
-				self.validators.remove(key);
-				self.local_collations.on_disconnect(key);
+				if self.validators.get(key) == Some(&who) {
+					self.validators.remove(key);
+					self.local_collations.on_disconnect(key);
+				}
 			}
 
-			{
-				let pending = &mut self.pending;
-				self.in_flight.retain(|&(_, ref peer), val| {
-					let retain = peer != &who;
-					if !retain {
-						let (sender, _) = oneshot::channel();
-						pending.push(::std::mem::replace(val, BlockDataRequest {
-							attempted_peers: Default::default(),
-							validation_session_parent: Default::default(),
-							candidate_hash: Default::default(),
-							block_data_hash: Default::default(),
-							sender,
-						}));
-					}
-
-					retain
-				});
-			}
 			self.dispatch_pending_requests(ctx);
 		}
 	}
@@ -515,22 +1304,15 @@ impl Specialization<Block> for PolkadotProtocol {
 	fn on_abort(&mut self) { }
 
 	fn maintain_peers(&mut self, ctx: &mut Context<Block>) {
+		let now = Instant::now();
+		self.reputation.decay(now);
 		self.collators.collect_garbage(None);
 		self.local_collations.collect_garbage(None);
+		self.time_out_requests(ctx, now);
 		self.dispatch_pending_requests(ctx);
 
-		for collator_action in self.collators.maintain_peers() {
-			match collator_action {
-				Action::Disconnect(collator) => self.disconnect_bad_collator(ctx, collator),
-				Action::NewRole(account_id, role) => if let Some((collator, info)) = self.collator_peer(account_id) {
-					info.collator_state.set_role(role, |msg| send_polkadot_message(
-						ctx,
-						collator,
-						msg,
-					))
-				},
-			}
-		}
+		let collator_actions = self.collators.maintain_peers();
+		self.apply_collator_actions(ctx, collator_actions);
 	}
 
 	fn on_block_imported(&mut self, _ctx: &mut Context<Block>, hash: Hash, header: &Header) {
@@ -549,13 +1331,20 @@ impl PolkadotProtocol {
 			None => ctx.report_peer(from, Severity::Useless("Unknown Polkadot specific reason".to_string())),
 			Some(peer_info) => match peer_info.collating_for {
 				None => ctx.report_peer(from, Severity::Bad("Sent collation without registering collator intent".to_string())),
+				Some(_) if !peer_info.collator_authenticated => {
+					ctx.report_peer(from, Severity::Bad("Sent collation without proving collator account ownership".to_string()))
+				}
+				Some((ref acc_id, ref para_id)) if !self.collator_allowed(acc_id, para_id) => {
+					ctx.report_peer(from, Severity::Useless("Collator not allowed on parachain".to_string()))
+				}
 				Some((ref acc_id, ref para_id)) => {
 					let structurally_valid = para_id == &collation_para && acc_id == &collated_acc;
 					if structurally_valid && collation.receipt.check_signature().is_ok() {
 						debug!(target: "p_net", "Received collation for parachain {:?} from peer {}", para_id, from);
+						note_reputation(&mut self.reputation, ctx, from, reputation::VALID_COLLATION, "Sent valid collation");
 						self.collators.on_collation(acc_id.clone(), relay_parent, collation)
 					} else {
-						ctx.report_peer(from, Severity::Bad("Sent malformed collation".to_string()))
+						note_reputation(&mut self.reputation, ctx, from, reputation::MALFORMED_COLLATION, "Sent malformed collation")
 					};
 				}
 			},
@@ -571,7 +1360,7 @@ impl PolkadotProtocol {
 
 	// get connected peer with given account ID for collation.
 	fn collator_peer(&mut self, account_id: AccountId) -> Option<(NodeIndex, &mut PeerInfo)> {
-		let check_info = |info: &PeerInfo| info
+		let check_info = |info: &PeerInfo| info.collator_authenticated && info
 			.collating_for
 			.as_ref()
 			.map_or(false, |&(ref acc_id, _)| acc_id == &account_id);
@@ -583,12 +1372,62 @@ impl PolkadotProtocol {
 			.next()
 	}
 
+	// whether the account may collate on the parachain.
+	fn collator_allowed(&self, account_id: &AccountId, para_id: &ParaId) -> bool {
+		self.collator_whitelists.get(para_id).map_or(true, |whitelist| whitelist.contains(account_id))
+	}
+
+	// replace the collator whitelists of permissioned parachains, as found in the runtime,
+	// and disconnect collators no longer allowed. this includes older collators which
+	// could not prove account ownership.
+	fn note_collator_whitelists(&mut self, ctx: &mut Context<Block>, whitelists: HashMap<ParaId, Vec<AccountId>>) {
+		self.collator_whitelists = whitelists;
+
+		let disallowed: Vec<_> = self.peers.iter()
+			.filter_map(|(who, info)| info.collating_for.as_ref().map(|c| (*who, info.version, c)))
+			.filter(|&(_, version, &(ref acc_id, ref para_id))| !self.collator_allowed(acc_id, para_id)
+				|| (version < COLLATOR_AUTH_VERSION && self.collator_whitelists.contains_key(para_id)))
+			.map(|(who, _, _)| who)
+			.collect();
+
+		for who in disallowed {
+			ctx.report_peer(who, Severity::Useless("Collator no longer allowed on parachain".to_string()));
+		}
+	}
+
 	// disconnect a collator by account-id.
 	fn disconnect_bad_collator(&mut self, ctx: &mut Context<Block>, account_id: AccountId) {
 		if let Some((who, _)) = self.collator_peer(account_id) {
 			ctx.report_peer(who, Severity::Bad("Consensus layer determined the given collator misbehaved".to_string()))
 		}
 	}
+
+	// note that a collation of the given collator was found valid.
+	fn note_good_collator(&mut self, account_id: AccountId) {
+		self.collators.note_good_collator(account_id);
+	}
+
+	// note that a collation of the given collator was found invalid, demoting it.
+	fn note_bad_collator(&mut self, ctx: &mut Context<Block>, account_id: AccountId) {
+		let collator_actions = self.collators.note_bad_collator(account_id);
+		self.apply_collator_actions(ctx, collator_actions);
+	}
+
+	fn apply_collator_actions(&mut self, ctx: &mut Context<Block>, actions: Vec<Action>) {
+		for collator_action in actions {
+			match collator_action {
+				Action::Disconnect(collator) => self.disconnect_bad_collator(ctx, collator),
+				Action::NewRole(account_id, role) => if let Some((collator, info)) = self.collator_peer(account_id) {
+					debug!(target: "p_net", "Collator {:?} assigned role {:?}", account_id, role);
+					info.collator_state.set_role(role, |msg| send_polkadot_message(
+						ctx,
+						collator,
+						msg,
+					))
+				},
+			}
+		}
+	}
 }
 
 impl PolkadotProtocol {
@@ -607,9 +1446,11 @@ impl PolkadotProtocol {
 			match self.validators.get(&primary) {
 				Some(who) => {
 					debug!(target: "p_net", "Sending local collation to {:?}", primary);
-					send_polkadot_message(
+					let compress = self.peers.get(who).map_or(false, |info| info.compresses());
+					send_compressible_message(
 						ctx,
 						*who,
+						compress,
 						Message::Collation(relay_parent, cloned_collation),
 					)
 				},
@@ -619,6 +1460,17 @@ impl PolkadotProtocol {
 		}
 	}
 
+	/// Reputation of connected and recently banned peers.
+	pub fn reputation(&self) -> &Reputation {
+		&self.reputation
+	}
+
+	/// Statements about unknown candidates rejected or evicted on all expired
+	/// relay parents so far.
+	pub fn deferred_evictions(&self) -> DeferredEvictions {
+		self.deferred_evictions
+	}
+
 	/// register availability store.
 	pub fn register_availability_store(&mut self, extrinsic_store: ::av_store::Store) {
 		self.extrinsic_store = Some(extrinsic_store);
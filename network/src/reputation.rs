@@ -0,0 +1,302 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reputation of peers on the polkadot sub-protocol.
+//!
+//! Peers gain reputation for serving useful data and lose it for invalid or missing
+//! responses. Scores decay towards zero over time. A peer whose score drops to
+//! `BAN_THRESHOLD` or below is banned and reported as bad to the network.
+//!
+//! Scores are kept per identity of the peer, so that they survive reconnects and a ban
+//! keeps decaying while the peer is away. The transport identity of peers is not exposed
+//! to the specialization, so the identities are those peers authenticate on the protocol:
+//! the session key of a validator, or the account of a collator. Peers without one are
+//! only known by their `NodeIndex`, which identifies a connection and may be reused for
+//! another peer, so their scores are forgotten when they disconnect.
+
+use polkadot_primitives::{AccountId, SessionKey};
+use substrate_network::NodeIndex;
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// A reputation score, or a change to one.
+pub type Score = i32;
+
+/// Peers with a score at or below this are banned.
+pub const BAN_THRESHOLD: Score = -100;
+/// The lowest score a peer can have.
+pub const MIN_SCORE: Score = -1000;
+/// The highest score a peer can have.
+pub const MAX_SCORE: Score = 100;
+
+// scores move this much towards zero every `DECAY_INTERVAL`.
+const DECAY_STEP: Score = 1;
+const DECAY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Peer served block data matching the requested candidate.
+pub const GOOD_BLOCK_DATA: Score = 5;
+/// Peer sent a collation which passed our checks.
+pub const VALID_COLLATION: Score = 5;
+/// Peer answered a block data request without data.
+pub const MISSING_BLOCK_DATA: Score = -5;
+/// Peer sent a response to a request which was not in flight.
+pub const UNEXPECTED_RESPONSE: Score = -10;
+/// Peer left a request unanswered.
+pub const UNANSWERED_REQUEST: Score = -20;
+/// Peer served block data not matching the requested candidate.
+pub const INVALID_BLOCK_DATA: Score = -60;
+/// Peer sent more statements than allowed by the rate limit.
+pub const STATEMENT_FLOOD: Score = -10;
+/// Peer sent a statement directly which it did not sign itself.
+pub const UNEXPECTED_STATEMENT: Score = -50;
+/// Peer sent a collation which was malformed or wrongly signed.
+pub const MALFORMED_COLLATION: Score = -100;
+
+/// The identity reputation is attached to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identity {
+	/// A validator, by a session key it signed an announcement with.
+	Validator(SessionKey),
+	/// A collator, by the account it proved ownership of.
+	Collator(AccountId),
+	/// A peer which did not authenticate, by its connection.
+	Connection(NodeIndex),
+}
+
+/// Tracks reputation scores of connected and recently connected peers.
+pub struct Reputation {
+	// identities of connected peers.
+	peers: HashMap<NodeIndex, Identity>,
+	scores: HashMap<Identity, Score>,
+	last_decay: Instant,
+}
+
+impl Reputation {
+	/// Create a new, empty reputation tracker.
+	pub fn new() -> Self {
+		Reputation {
+			peers: HashMap::new(),
+			scores: HashMap::new(),
+			last_decay: Instant::now(),
+		}
+	}
+
+	fn identity(&self, who: &NodeIndex) -> Identity {
+		self.peers.get(who).cloned().unwrap_or(Identity::Connection(*who))
+	}
+
+	/// The current score of a peer. Unknown peers have a neutral score.
+	pub fn score(&self, who: &NodeIndex) -> Score {
+		self.scores.get(&self.identity(who)).cloned().unwrap_or(0)
+	}
+
+	/// Whether a peer is currently banned.
+	pub fn is_banned(&self, who: &NodeIndex) -> bool {
+		self.score(who) <= BAN_THRESHOLD
+	}
+
+	/// Scores of all connected peers.
+	pub fn scores(&self) -> Vec<(NodeIndex, Score)> {
+		self.peers.keys().map(|who| (*who, self.score(who))).collect()
+	}
+
+	/// Note that a peer connected. Until it authenticates, it is known by its
+	/// connection only, with a neutral score.
+	pub(crate) fn on_connect(&mut self, who: NodeIndex) {
+		self.scores.remove(&Identity::Connection(who));
+		self.peers.insert(who, Identity::Connection(who));
+	}
+
+	/// Note that a peer disconnected. The score of an authenticated identity is kept
+	/// until it decayed, that of a connection is forgotten.
+	pub(crate) fn on_disconnect(&mut self, who: NodeIndex) {
+		if let Some(Identity::Connection(_)) = self.peers.remove(&who) {
+			self.scores.remove(&Identity::Connection(who));
+		}
+	}
+
+	/// Note that a connected peer authenticated with the given identity. Penalties of
+	/// either the identity or the peer so far carry over, as does the better score
+	/// otherwise. Returns `true` if the peer is banned under its new identity.
+	pub(crate) fn identify(&mut self, who: NodeIndex, identity: Identity) -> bool {
+		let previous = match self.peers.get(&who) {
+			Some(previous) if previous == &identity => return false,
+			Some(previous) => previous.clone(),
+			None => return false,
+		};
+
+		let carried = self.score(&who);
+		let known = self.scores.get(&identity).cloned().unwrap_or(0);
+		let score = if carried.min(known) < 0 { carried.min(known) } else { carried.max(known) };
+
+		if let Identity::Connection(_) = previous {
+			self.scores.remove(&previous);
+		}
+		self.scores.insert(identity.clone(), score);
+		self.peers.insert(who, identity);
+
+		score <= BAN_THRESHOLD
+	}
+
+	/// Apply a change to a peer's score. Returns `true` if this change got the peer banned.
+	pub(crate) fn note(&mut self, who: NodeIndex, change: Score) -> bool {
+		let was_banned = self.is_banned(&who);
+		let identity = self.identity(&who);
+		let score = self.scores.entry(identity).or_insert(0);
+		*score = score.saturating_add(change).max(MIN_SCORE).min(MAX_SCORE);
+
+		!was_banned && *score <= BAN_THRESHOLD
+	}
+
+	/// Decay all scores towards zero according to the time passed since the last decay.
+	pub(crate) fn decay(&mut self, now: Instant) {
+		if now <= self.last_decay { return }
+
+		let elapsed = now - self.last_decay;
+		let steps = elapsed.as_secs() / DECAY_INTERVAL.as_secs();
+		if steps == 0 { return }
+
+		self.last_decay += DECAY_INTERVAL * steps as u32;
+
+		let amount = (steps as Score).saturating_mul(DECAY_STEP);
+		for score in self.scores.values_mut() {
+			*score = if *score > 0 {
+				score.saturating_sub(amount).max(0)
+			} else {
+				score.saturating_add(amount).min(0)
+			};
+		}
+
+		// forget the neutral scores of identities no longer connected.
+		let connected: HashSet<_> = self.peers.values().collect();
+		self.scores.retain(|identity, score| *score != 0 || connected.contains(identity));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scores_are_clamped() {
+		let mut reputation = Reputation::new();
+		reputation.on_connect(1);
+
+		for _ in 0..100 {
+			reputation.note(1, GOOD_BLOCK_DATA);
+		}
+		assert_eq!(reputation.score(&1), MAX_SCORE);
+
+		for _ in 0..100 {
+			reputation.note(1, MALFORMED_COLLATION);
+		}
+		assert_eq!(reputation.score(&1), MIN_SCORE);
+	}
+
+	#[test]
+	fn ban_is_reported_once_and_decays() {
+		let mut reputation = Reputation::new();
+		let start = reputation.last_decay;
+		reputation.on_connect(1);
+
+		assert!(!reputation.note(1, INVALID_BLOCK_DATA));
+		assert!(reputation.note(1, INVALID_BLOCK_DATA));
+		assert!(!reputation.note(1, UNANSWERED_REQUEST));
+		assert!(reputation.is_banned(&1));
+		assert_eq!(reputation.score(&1), -140);
+
+		reputation.decay(start + Duration::from_secs(40));
+		assert!(reputation.is_banned(&1));
+		assert_eq!(reputation.score(&1), BAN_THRESHOLD);
+
+		reputation.decay(start + Duration::from_secs(41));
+		assert!(!reputation.is_banned(&1));
+		assert_eq!(reputation.score(&1), BAN_THRESHOLD + 1);
+
+		reputation.decay(start + Duration::from_secs(1000));
+		assert_eq!(reputation.scores(), vec![(1, 0)]);
+	}
+
+	#[test]
+	fn connection_scores_forgotten_on_disconnect() {
+		let mut reputation = Reputation::new();
+		reputation.on_connect(1);
+		reputation.on_connect(2);
+
+		reputation.note(1, VALID_COLLATION);
+		reputation.note(2, MALFORMED_COLLATION);
+		assert!(reputation.is_banned(&2));
+
+		reputation.on_disconnect(1);
+		reputation.on_disconnect(2);
+		assert!(reputation.scores().is_empty());
+		assert!(reputation.scores.is_empty());
+
+		// the connection slot may be reused by another peer.
+		reputation.on_connect(2);
+		assert!(!reputation.is_banned(&2));
+		assert_eq!(reputation.score(&2), 0);
+	}
+
+	#[test]
+	fn bans_decay_across_reconnects() {
+		let mut reputation = Reputation::new();
+		let start = reputation.last_decay;
+		let validator = Identity::Validator([1; 32].into());
+
+		reputation.on_connect(1);
+		assert!(!reputation.identify(1, validator.clone()));
+		assert!(!reputation.note(1, INVALID_BLOCK_DATA));
+		assert!(reputation.note(1, INVALID_BLOCK_DATA));
+		reputation.on_disconnect(1);
+		assert!(reputation.scores().is_empty());
+
+		// the ban decays while the peer is away, and holds on another connection.
+		reputation.decay(start + Duration::from_secs(10));
+		reputation.on_connect(2);
+		assert!(!reputation.is_banned(&2));
+		assert!(reputation.identify(2, validator.clone()));
+		assert_eq!(reputation.score(&2), 2 * INVALID_BLOCK_DATA + 10);
+		reputation.on_disconnect(2);
+
+		// and is forgotten once decayed.
+		reputation.decay(start + Duration::from_secs(1000));
+		assert!(reputation.scores.is_empty());
+		reputation.on_connect(3);
+		assert!(!reputation.identify(3, validator));
+		assert_eq!(reputation.score(&3), 0);
+	}
+
+	#[test]
+	fn identification_carries_penalties_over() {
+		let mut reputation = Reputation::new();
+		let collator = Identity::Collator([2; 32].into());
+
+		reputation.on_connect(1);
+		reputation.note(1, VALID_COLLATION);
+		assert!(!reputation.identify(1, collator.clone()));
+		assert_eq!(reputation.score(&1), VALID_COLLATION);
+
+		// a penalty on an unauthenticated connection is not escaped by authenticating.
+		reputation.on_connect(2);
+		reputation.note(2, UNANSWERED_REQUEST);
+		assert!(!reputation.identify(2, collator));
+		assert_eq!(reputation.score(&1), UNANSWERED_REQUEST);
+		assert_eq!(reputation.score(&2), UNANSWERED_REQUEST);
+		assert!(!reputation.scores.contains_key(&Identity::Connection(2)));
+	}
+}
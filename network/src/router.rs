@@ -25,7 +25,7 @@
 use sr_primitives::traits::{ProvideRuntimeApi, BlakeTwo256, Hash as HashT};
 use polkadot_validation::{
 	SharedTable, TableRouter, SignedStatement, GenericStatement, ParachainWork, Incoming,
-	Validated, Outgoing,
+	Validated, Outgoing, GroupInfo,
 };
 use polkadot_primitives::{Block, Hash, SessionKey};
 use polkadot_primitives::parachain::{
@@ -37,11 +37,13 @@ use futures::{future, prelude::*};
 use futures::sync::oneshot::{self, Receiver};
 use parking_lot::Mutex;
 
-use std::collections::{hash_map::{Entry, HashMap}, HashSet};
+use std::collections::{hash_map::{Entry, HashMap}, HashSet, VecDeque};
 use std::{io, mem};
+use std::ops::AddAssign;
 use std::sync::Arc;
 
 use validation::{NetworkService, Knowledge, Executor};
+use super::BlockDataError;
 
 type IngressPair = (ParaId, Vec<Message>);
 type IngressPairRef<'a> = (ParaId, &'a [Message]);
@@ -63,8 +65,8 @@ fn incoming_message_topic(parent_hash: Hash, parachain: ParaId) -> Hash {
 
 /// Receiver for block data.
 pub struct BlockDataReceiver {
-	outer: Receiver<Receiver<BlockData>>,
-	inner: Option<Receiver<BlockData>>
+	outer: Receiver<Receiver<Result<BlockData, BlockDataError>>>,
+	inner: Option<Receiver<Result<BlockData, BlockDataError>>>
 }
 
 impl Future for BlockDataReceiver {
@@ -77,14 +79,21 @@ impl Future for BlockDataReceiver {
 			"Sending end of channel hung up",
 		);
 
+		// the network gave up on fetching the data.
+		let flatten = |res: Async<Result<BlockData, BlockDataError>>| match res {
+			Async::Ready(Ok(data)) => Ok(Async::Ready(data)),
+			Async::Ready(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e)),
+			Async::NotReady => Ok(Async::NotReady),
+		};
+
 		if let Some(ref mut inner) = self.inner {
-			return inner.poll().map_err(map_err);
+			return inner.poll().map_err(map_err).and_then(flatten);
 		}
 		match self.outer.poll().map_err(map_err)? {
 			Async::Ready(mut inner) => {
 				let poll_result = inner.poll();
 				self.inner = Some(inner);
-				poll_result.map_err(map_err)
+				poll_result.map_err(map_err).and_then(flatten)
 			}
 			Async::NotReady => Ok(Async::NotReady),
 		}
@@ -124,6 +133,7 @@ pub struct Router<P, E, N: NetworkService, T> {
 	knowledge: Arc<Mutex<Knowledge>>,
 	fetch_incoming: Arc<Mutex<HashMap<ParaId, IncomingReceiver>>>,
 	deferred_statements: Arc<Mutex<DeferredStatements>>,
+	upcoming_authors: Arc<Vec<SessionKey>>,
 }
 
 impl<P, E, N: NetworkService, T> Router<P, E, N, T> {
@@ -135,6 +145,8 @@ impl<P, E, N: NetworkService, T> Router<P, E, N, T> {
 		parent_hash: Hash,
 		knowledge: Arc<Mutex<Knowledge>>,
 		exit: E,
+		upcoming_authors: Vec<SessionKey>,
+		deferred_statements: Arc<Mutex<DeferredStatements>>,
 	) -> Self {
 		Router {
 			table,
@@ -145,8 +157,9 @@ impl<P, E, N: NetworkService, T> Router<P, E, N, T> {
 			attestation_topic: attestation_topic(parent_hash),
 			knowledge,
 			fetch_incoming: Arc::new(Mutex::new(HashMap::new())),
-			deferred_statements: Arc::new(Mutex::new(DeferredStatements::new())),
+			deferred_statements,
 			exit,
+			upcoming_authors: Arc::new(upcoming_authors),
 		}
 	}
 
@@ -169,6 +182,7 @@ impl<P, E: Clone, N: NetworkService, T: Clone> Clone for Router<P, E, N, T> {
 			fetch_incoming: self.fetch_incoming.clone(),
 			knowledge: self.knowledge.clone(),
 			exit: self.exit.clone(),
+			upcoming_authors: self.upcoming_authors.clone(),
 		}
 	}
 }
@@ -194,7 +208,7 @@ impl<P: ProvideRuntimeApi + Send + Sync + 'static, E, N, T> Router<P, E, N, T> w
 			match candidate_data {
 				Some(x) => x,
 				None => {
-					self.deferred_statements.lock().push(statement);
+					self.deferred_statements.lock().push(statement, self.table.group_info());
 					return;
 				}
 			}
@@ -265,7 +279,9 @@ impl<P: ProvideRuntimeApi + Send + Sync + 'static, E, N, T> Router<P, E, N, T> w
 		let table = self.table.clone();
 		let network = self.network.clone();
 		let knowledge = self.knowledge.clone();
-		let attestation_topic = self.attestation_topic.clone();
+		let task_executor = self.task_executor.clone();
+		let parent_hash = self.parent_hash;
+		let upcoming_authors = self.upcoming_authors.clone();
 
 		producer.prime(self.api.clone())
 			.map(move |validated| {
@@ -277,9 +293,8 @@ impl<P: ProvideRuntimeApi + Send + Sync + 'static, E, N, T> Router<P, E, N, T> w
 				);
 
 				// propagate the statement.
-				// consider something more targeted than gossip in the future.
 				let signed = table.import_validated(validated);
-				network.gossip_message(attestation_topic, signed.encode());
+				distribute_statement(&network, &task_executor, &table, parent_hash, &upcoming_authors, signed);
 			})
 			.map_err(|e| debug!(target: "p_net", "Failed to produce statements: {:?}", e))
 	}
@@ -323,12 +338,12 @@ impl<P: ProvideRuntimeApi, E, N, T> Router<P, E, N, T> where
 		let work = canon_roots.into_future()
 			.and_then(move |ingress_roots| match ingress_roots {
 				None => Err(format!("No parachain {:?} registered at {}", parachain, parent_hash)),
-				Some(roots) => Ok(roots.into_iter().collect())
+				Some(roots) => Ok(roots)
 			})
 			.and_then(move |ingress_roots| ComputeIngress {
 				inner: gossip_messages,
 				ingress_roots,
-				incoming: Vec::new(),
+				found: HashMap::new(),
 			})
 			.map(move |incoming| if let Some(i) = incoming { let _ = tx.send(i); })
 			.select2(self.exit.clone())
@@ -358,7 +373,14 @@ impl<P: ProvideRuntimeApi + Send, E, N, T> TableRouter for Router<P, E, N, T> wh
 
 		// give to network to make available.
 		self.knowledge.lock().note_candidate(hash, Some(block_data), Some(extrinsic));
-		self.network.gossip_message(self.attestation_topic, statement.encode());
+		distribute_statement(
+			&self.network,
+			&self.task_executor,
+			&self.table,
+			self.parent_hash,
+			&self.upcoming_authors,
+			statement,
+		);
 	}
 
 	fn fetch_block_data(&self, candidate: &CandidateReceipt) -> BlockDataReceiver {
@@ -395,6 +417,53 @@ impl<P, E, N: NetworkService, T> Drop for Router<P, E, N, T> {
 	}
 }
 
+// send a local statement to the peers which need it.
+//
+// full candidate statements go directly to the validators assigned to the
+// parachain and to the authors of the upcoming blocks. compact `Valid` and `Invalid`
+// statements are small and go to everyone over gossip, as do candidates if some of
+// their targets can't be reached directly.
+fn distribute_statement<N: NetworkService, T: Executor>(
+	network: &Arc<N>,
+	task_executor: &T,
+	table: &SharedTable,
+	parent_hash: Hash,
+	upcoming_authors: &[SessionKey],
+	statement: SignedStatement,
+) {
+	let topic = attestation_topic(parent_hash);
+	let para_id = match statement.statement {
+		GenericStatement::Candidate(ref c) => c.parachain_index,
+		GenericStatement::Valid(_) | GenericStatement::Invalid(_) => {
+			network.gossip_message(topic, statement.encode());
+			return
+		}
+	};
+
+	let mut targets: HashSet<SessionKey> = table.group_info()
+		.get(&para_id)
+		.map(|group| group.validity_guarantors.clone())
+		.unwrap_or_default();
+
+	targets.extend(upcoming_authors.iter().cloned());
+	targets.remove(&table.session_key());
+
+	let encoded = statement.encode();
+	let (tx, rx) = oneshot::channel();
+	network.with_spec(move |spec, ctx| {
+		let unreachable = spec.distribute_candidate_statement(ctx, parent_hash, targets, statement);
+		let _ = tx.send(unreachable);
+	});
+
+	let network = network.clone();
+	task_executor.spawn(rx
+		.map(move |unreachable| if unreachable {
+			network.gossip_message(topic, encoded);
+		})
+		.map_err(|_| ())
+	);
+}
+
 // A unique trace for valid statements issued by a validator.
 #[derive(Hash, PartialEq, Eq, Clone, Debug)]
 enum StatementTrace {
@@ -402,30 +471,143 @@ enum StatementTrace {
 	Invalid(SessionKey, Hash),
 }
 
+impl StatementTrace {
+	fn of(statement: &SignedStatement) -> Option<Self> {
+		match statement.statement {
+			GenericStatement::Candidate(_) => None,
+			GenericStatement::Valid(hash) => Some(StatementTrace::Valid(statement.sender, hash)),
+			GenericStatement::Invalid(hash) => Some(StatementTrace::Invalid(statement.sender, hash)),
+		}
+	}
+
+	fn sender(&self) -> &SessionKey {
+		match *self {
+			StatementTrace::Valid(ref sender, _) | StatementTrace::Invalid(ref sender, _) => sender,
+		}
+	}
+
+	fn candidate_hash(&self) -> &Hash {
+		match *self {
+			StatementTrace::Valid(_, ref hash) | StatementTrace::Invalid(_, ref hash) => hash,
+		}
+	}
+}
+
+// maximum number of statements deferred for a single sender.
+const MAX_DEFERRED_PER_SENDER: usize = 16;
+// maximum number of statements deferred in total.
+const MAX_DEFERRED: usize = 1024;
+
+/// Counters of statements rejected by or evicted from the buffer of statements
+/// whose candidate is not known yet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeferredEvictions {
+	/// Rejected because the sender is not a validator in any group.
+	pub unknown_sender: u64,
+	/// Rejected because the sender had too many statements deferred already.
+	pub sender_limit: u64,
+	/// Evicted to make room for another sender's statement.
+	pub global_limit: u64,
+	/// Dropped because the relay parent expired before the candidate was known.
+	pub expired: u64,
+}
+
+impl AddAssign for DeferredEvictions {
+	fn add_assign(&mut self, other: Self) {
+		self.unknown_sender += other.unknown_sender;
+		self.sender_limit += other.sender_limit;
+		self.global_limit += other.global_limit;
+		self.expired += other.expired;
+	}
+}
+
 // helper for deferring statements whose associated candidate is unknown.
-struct DeferredStatements {
+pub(crate) struct DeferredStatements {
 	deferred: HashMap<Hash, Vec<SignedStatement>>,
 	known_traces: HashSet<StatementTrace>,
+	// deferred statements of each sender, oldest first.
+	by_sender: HashMap<SessionKey, VecDeque<StatementTrace>>,
+	len: usize,
+	evictions: DeferredEvictions,
 }
 
 impl DeferredStatements {
-	fn new() -> Self {
+	pub(crate) fn new() -> Self {
 		DeferredStatements {
 			deferred: HashMap::new(),
 			known_traces: HashSet::new(),
+			by_sender: HashMap::new(),
+			len: 0,
+			evictions: DeferredEvictions::default(),
 		}
 	}
 
-	fn push(&mut self, statement: SignedStatement) {
-		let (hash, trace) = match statement.statement {
-			GenericStatement::Candidate(_) => return,
-			GenericStatement::Valid(hash) => (hash, StatementTrace::Valid(statement.sender, hash)),
-			GenericStatement::Invalid(hash) => (hash, StatementTrace::Invalid(statement.sender, hash)),
+	pub(crate) fn push(&mut self, statement: SignedStatement, groups: &HashMap<ParaId, GroupInfo>) {
+		let trace = match StatementTrace::of(&statement) {
+			Some(trace) => trace,
+			None => return,
 		};
 
-		if self.known_traces.insert(trace) {
-			self.deferred.entry(hash).or_insert_with(Vec::new).push(statement);
+		if self.known_traces.contains(&trace) { return }
+
+		let known_sender = groups.values().any(|g| g.validity_guarantors.contains(&statement.sender));
+		if !known_sender {
+			self.evictions.unknown_sender += 1;
+			return
+		}
+
+		let sender_len = self.by_sender.get(&statement.sender).map_or(0, |t| t.len());
+		if sender_len >= MAX_DEFERRED_PER_SENDER {
+			self.evictions.sender_limit += 1;
+			return
+		}
+
+		// make room by evicting the oldest statement of the sender with most deferred.
+		if self.len >= MAX_DEFERRED {
+			let evict = self.by_sender.values()
+				.max_by_key(|traces| traces.len())
+				.and_then(|traces| traces.front().cloned());
+
+			if let Some(evict) = evict {
+				self.remove(&evict);
+				self.evictions.global_limit += 1;
+			}
+		}
+
+		self.known_traces.insert(trace.clone());
+		self.by_sender.entry(statement.sender).or_insert_with(VecDeque::new).push_back(trace.clone());
+		self.deferred.entry(*trace.candidate_hash()).or_insert_with(Vec::new).push(statement);
+		self.len += 1;
+	}
+
+	// remove a single deferred statement.
+	fn remove(&mut self, trace: &StatementTrace) {
+		if !self.known_traces.remove(trace) { return }
+
+		let sender_empty = match self.by_sender.get_mut(trace.sender()) {
+			Some(traces) => {
+				traces.retain(|t| t != trace);
+				traces.is_empty()
+			}
+			None => false,
+		};
+		if sender_empty {
+			self.by_sender.remove(trace.sender());
+		}
+
+		let hash = trace.candidate_hash();
+		let candidate_empty = match self.deferred.get_mut(hash) {
+			Some(statements) => {
+				statements.retain(|s| StatementTrace::of(s).as_ref() != Some(trace));
+				statements.is_empty()
+			}
+			None => false,
+		};
+		if candidate_empty {
+			self.deferred.remove(hash);
 		}
+
+		self.len -= 1;
 	}
 
 	fn get_deferred(&mut self, hash: &Hash) -> (Vec<SignedStatement>, Vec<StatementTrace>) {
@@ -434,28 +616,54 @@ impl DeferredStatements {
 			Some(deferred) => {
 				let mut traces = Vec::new();
 				for statement in deferred.iter() {
-					let trace = match statement.statement {
-						GenericStatement::Candidate(_) => continue,
-						GenericStatement::Valid(hash) => StatementTrace::Valid(statement.sender, hash),
-						GenericStatement::Invalid(hash) => StatementTrace::Invalid(statement.sender, hash),
+					let trace = match StatementTrace::of(statement) {
+						Some(trace) => trace,
+						None => continue,
 					};
 
 					self.known_traces.remove(&trace);
 					traces.push(trace);
 				}
 
+				for trace in &traces {
+					let sender_empty = match self.by_sender.get_mut(trace.sender()) {
+						Some(sender_traces) => {
+							sender_traces.retain(|t| t != trace);
+							sender_traces.is_empty()
+						}
+						None => false,
+					};
+					if sender_empty {
+						self.by_sender.remove(trace.sender());
+					}
+				}
+
+				self.len -= traces.len();
 				(deferred, traces)
 			}
 		}
 	}
+
+	// drop all deferred statements, as the relay parent they were issued on expired.
+	// returns the counters of all statements rejected or evicted on that relay parent.
+	pub(crate) fn expire(&mut self) -> DeferredEvictions {
+		self.evictions.expired += self.len as u64;
+		self.deferred.clear();
+		self.known_traces.clear();
+		self.by_sender.clear();
+		self.len = 0;
+		self.evictions
+	}
 }
 
 // computes ingress from incoming stream of messages.
 // returns `None` if the stream concludes too early.
 #[must_use = "futures do nothing unless polled"]
 struct ComputeIngress<S> {
-	ingress_roots: HashMap<ParaId, Hash>,
-	incoming: Vec<IngressPair>,
+	// canonical ingress roots, in the order they are to be processed.
+	ingress_roots: Vec<(ParaId, Hash)>,
+	// messages found so far for each canonical root.
+	found: HashMap<(ParaId, Hash), Vec<Message>>,
 	inner: S,
 }
 
@@ -465,10 +673,13 @@ impl<S> Future for ComputeIngress<S> where S: Stream<Item=IngressPair> {
 
 	fn poll(&mut self) -> Poll<Option<Incoming>, Self::Error> {
 		loop {
-			if self.ingress_roots.is_empty() {
-				return Ok(Async::Ready(
-					Some(mem::replace(&mut self.incoming, Vec::new()))
-				))
+			if self.ingress_roots.iter().all(|root| self.found.contains_key(root)) {
+				let found = mem::replace(&mut self.found, HashMap::new());
+				let incoming = self.ingress_roots.iter()
+					.map(|root| (root.0, found[root].clone()))
+					.collect();
+
+				return Ok(Async::Ready(Some(incoming)))
 			}
 
 			let (para_id, messages) = match try_ready!(self.inner.poll()) {
@@ -476,28 +687,14 @@ impl<S> Future for ComputeIngress<S> where S: Stream<Item=IngressPair> {
 				Some(next) => next,
 			};
 
-			match self.ingress_roots.entry(para_id) {
-				Entry::Vacant(_) => continue,
-				Entry::Occupied(occupied) => {
-					let canon_root = occupied.get().clone();
-					let messages = messages.iter().map(|m| &m.0[..]);
-					if ::polkadot_validation::message_queue_root(messages) != canon_root {
-						continue;
-					}
-
-					occupied.remove();
-				}
+			if !self.ingress_roots.iter().any(|&(id, _)| id == para_id) {
+				continue;
 			}
 
-			let pos = self.incoming.binary_search_by_key(
-				&para_id,
-				|&(id, _)| id,
-			)
-				.err()
-				.expect("incoming starts empty and only inserted when \
-					para_id not inserted before; qed");
-
-			self.incoming.insert(pos, (para_id, messages));
+			let root = ::polkadot_validation::message_queue_root(messages.iter().map(|m| &m.0[..]));
+			if self.ingress_roots.contains(&(para_id, root)) {
+				self.found.entry((para_id, root)).or_insert(messages);
+			}
 		}
 	}
 }
@@ -508,12 +705,40 @@ mod tests {
 	use substrate_primitives::H512;
 	use futures::stream;
 
+	fn group_info_with(validators: &[SessionKey]) -> HashMap<ParaId, GroupInfo> {
+		let mut groups = HashMap::new();
+		groups.insert(ParaId::from(1), GroupInfo {
+			validity_guarantors: validators.iter().cloned().collect(),
+			needed_validity: 1,
+		});
+		groups
+	}
+
+	fn bytes_of(i: u64) -> [u8; 32] {
+		let mut bytes = [0u8; 32];
+		bytes[..8].copy_from_slice(&i.encode());
+		bytes
+	}
+
+	fn hash_of(i: u64) -> Hash {
+		bytes_of(i).into()
+	}
+
+	fn valid_statement(sender: SessionKey, hash: Hash) -> SignedStatement {
+		SignedStatement {
+			statement: GenericStatement::Valid(hash),
+			sender,
+			signature: H512::from([2; 64]).into(),
+		}
+	}
+
 	#[test]
 	fn deferred_statements_works() {
 		let mut deferred = DeferredStatements::new();
 		let hash = [1; 32].into();
 		let sig = H512::from([2; 64]).into();
 		let sender = [255; 32].into();
+		let groups = group_info_with(&[sender]);
 
 		let statement = SignedStatement {
 			statement: GenericStatement::Valid(hash),
@@ -528,8 +753,8 @@ mod tests {
 			assert!(traces.is_empty());
 		}
 
-		deferred.push(statement.clone());
-		deferred.push(statement.clone());
+		deferred.push(statement.clone(), &groups);
+		deferred.push(statement.clone(), &groups);
 
 		// draining: second push should have been ignored.
 		{
@@ -547,6 +772,77 @@ mod tests {
 			assert!(signed.is_empty());
 			assert!(traces.is_empty());
 		}
+
+		assert_eq!(deferred.len, 0);
+		assert!(deferred.by_sender.is_empty());
+	}
+
+	#[test]
+	fn deferred_statements_from_unknown_senders_rejected() {
+		let mut deferred = DeferredStatements::new();
+		let hash = [1; 32].into();
+		let groups = group_info_with(&[[1; 32].into()]);
+
+		deferred.push(valid_statement([2; 32].into(), hash), &groups);
+
+		assert!(deferred.get_deferred(&hash).0.is_empty());
+		assert_eq!(deferred.evictions.unknown_sender, 1);
+	}
+
+	#[test]
+	fn deferred_statements_bounded_per_sender() {
+		let mut deferred = DeferredStatements::new();
+		let sender = [1; 32].into();
+		let groups = group_info_with(&[sender]);
+
+		for i in 0..(MAX_DEFERRED_PER_SENDER + 2) {
+			deferred.push(valid_statement(sender, hash_of(i as u64)), &groups);
+		}
+
+		assert_eq!(deferred.len, MAX_DEFERRED_PER_SENDER);
+		assert_eq!(deferred.evictions.sender_limit, 2);
+
+		// the oldest statements are kept.
+		assert_eq!(deferred.get_deferred(&hash_of(0)).0.len(), 1);
+		assert!(deferred.get_deferred(&hash_of(MAX_DEFERRED_PER_SENDER as u64)).0.is_empty());
+
+		// draining makes room again.
+		deferred.push(valid_statement(sender, hash_of(1000)), &groups);
+		assert_eq!(deferred.len, MAX_DEFERRED_PER_SENDER);
+		assert_eq!(deferred.evictions.sender_limit, 2);
+	}
+
+	#[test]
+	fn deferred_statements_bounded_globally() {
+		let mut deferred = DeferredStatements::new();
+		let senders: Vec<SessionKey> = (0..(MAX_DEFERRED / MAX_DEFERRED_PER_SENDER + 1))
+			.map(|i| bytes_of(i as u64 + 1).into())
+			.collect();
+		let groups = group_info_with(&senders);
+
+		let mut n = 0u64;
+		for sender in &senders[..senders.len() - 1] {
+			for _ in 0..MAX_DEFERRED_PER_SENDER {
+				deferred.push(valid_statement(*sender, hash_of(n)), &groups);
+				n += 1;
+			}
+		}
+		assert_eq!(deferred.len, MAX_DEFERRED);
+
+		// a new sender's statement evicts the oldest statement of a sender with most deferred.
+		let last = *senders.last().unwrap();
+		deferred.push(valid_statement(last, hash_of(n)), &groups);
+
+		assert_eq!(deferred.len, MAX_DEFERRED);
+		assert_eq!(deferred.evictions.global_limit, 1);
+		assert_eq!(deferred.by_sender[&last].len(), 1);
+		assert_eq!(deferred.get_deferred(&hash_of(n)).0.len(), 1);
+
+		let evictions = deferred.expire();
+		assert_eq!(deferred.len, 0);
+		assert!(deferred.known_traces.is_empty());
+		assert_eq!(evictions.expired, MAX_DEFERRED as u64 - 1);
+		assert_eq!(evictions.global_limit, 1);
 	}
 
 	#[test]
@@ -563,13 +859,17 @@ mod tests {
 					Message(b"hello world".to_vec()),
 				],
 			),
+			(
+				ParaId::from(2), // second queued batch from the same parachain.
+				vec![Message(vec![2, 2, 2])],
+			),
 			(
 				ParaId::from(5),
 				vec![Message(vec![1, 2, 3, 4, 5]), Message(vec![6, 9, 6, 9])],
 			),
 		];
 
-		let roots: HashMap<_, _> = actual_messages.iter()
+		let roots: Vec<_> = actual_messages.iter()
 			.map(|&(para_id, ref messages)| (
 				para_id,
 				::polkadot_validation::message_queue_root(messages.iter().map(|m| &m.0)),
@@ -598,6 +898,10 @@ mod tests {
 				ParaId::from(1234), // un-routed parachain.
 				vec![Message(vec![9, 9, 9, 9])],
 			),
+			(
+				ParaId::from(2), // newer batch arriving first
+				vec![Message(vec![2, 2, 2])],
+			),
 			(
 				ParaId::from(2),
 				vec![
@@ -608,7 +912,7 @@ mod tests {
 		];
 		let ingress = ComputeIngress {
 			ingress_roots: roots,
-			incoming: Vec::new(),
+			found: HashMap::new(),
 			inner: stream::iter_ok::<_, ()>(inputs.iter().cloned()),
 		};
 
@@ -0,0 +1,190 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bookkeeping for statements sent directly to peers.
+//!
+//! Full `Candidate` statements are only sent to the validators which need them: the
+//! group assigned to the candidate's parachain and the authors of the upcoming blocks.
+//! Compact `Valid` and `Invalid` statements go to everyone over gossip, as do candidates
+//! some of whose targets can't be reached directly. This tracks which statements were
+//! sent to which peers on each relay parent, so nothing is sent twice, and limits the
+//! rate at which each peer may send us statements.
+//!
+//! Gossiped statements don't reveal which peer they came from, so those are limited by
+//! their signer with the same `RateLimits`.
+
+use polkadot_primitives::Hash;
+use substrate_network::NodeIndex;
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash as StdHash;
+use std::time::{Duration, Instant};
+
+// number of statements a peer may send in a burst.
+const STATEMENT_BURST: u32 = 32;
+// a peer may send one further statement every `STATEMENT_INTERVAL`.
+const STATEMENT_INTERVAL: Duration = Duration::from_millis(125);
+
+// token bucket limiting statements received from a single peer or signer.
+struct RateLimit {
+	tokens: u32,
+	last_refill: Instant,
+}
+
+impl RateLimit {
+	fn new(now: Instant) -> Self {
+		RateLimit { tokens: STATEMENT_BURST, last_refill: now }
+	}
+
+	fn take(&mut self, now: Instant) -> bool {
+		if now > self.last_refill {
+			let elapsed = now - self.last_refill;
+			let interval_ms = STATEMENT_INTERVAL.as_secs() * 1000 + u64::from(STATEMENT_INTERVAL.subsec_millis());
+			let elapsed_ms = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+			let refill = elapsed_ms / interval_ms;
+
+			if refill > 0 {
+				self.tokens = ::std::cmp::min(STATEMENT_BURST as u64, self.tokens as u64 + refill) as u32;
+				self.last_refill += STATEMENT_INTERVAL * refill as u32;
+			}
+		}
+
+		if self.tokens == 0 {
+			false
+		} else {
+			self.tokens -= 1;
+			true
+		}
+	}
+}
+
+/// Rate limits of incoming statements, by peer or by signer.
+pub(crate) struct RateLimits<K> {
+	limits: HashMap<K, RateLimit>,
+}
+
+impl<K: StdHash + Eq> RateLimits<K> {
+	/// Create a new `RateLimits`, with a full budget for everyone.
+	pub(crate) fn new() -> Self {
+		RateLimits { limits: HashMap::new() }
+	}
+
+	/// Whether to accept another statement from the given sender.
+	pub(crate) fn allow(&mut self, who: K, now: Instant) -> bool {
+		self.limits.entry(who).or_insert_with(|| RateLimit::new(now)).take(now)
+	}
+
+	/// Forget the budget of a sender.
+	pub(crate) fn remove(&mut self, who: &K) {
+		self.limits.remove(who);
+	}
+}
+
+/// Tracks statements sent to and received from peers.
+pub(crate) struct StatementDistribution {
+	// hashes of statements sent to peers, by relay parent.
+	sent: HashMap<Hash, HashSet<(NodeIndex, Hash)>>,
+	limits: RateLimits<NodeIndex>,
+}
+
+impl StatementDistribution {
+	/// Create a new `StatementDistribution`.
+	pub(crate) fn new() -> Self {
+		StatementDistribution {
+			sent: HashMap::new(),
+			limits: RateLimits::new(),
+		}
+	}
+
+	/// Note that we are about to send a statement to a peer. Returns `false` if
+	/// it was already sent, in which case it should not be sent again.
+	pub(crate) fn note_sent(&mut self, relay_parent: Hash, who: NodeIndex, statement_hash: Hash) -> bool {
+		self.sent.entry(relay_parent).or_insert_with(HashSet::new).insert((who, statement_hash))
+	}
+
+	/// Whether to accept another statement from the given peer.
+	pub(crate) fn allow_incoming(&mut self, who: NodeIndex, now: Instant) -> bool {
+		self.limits.allow(who, now)
+	}
+
+	/// Forget everything about a relay parent.
+	pub(crate) fn remove_relay_parent(&mut self, relay_parent: &Hash) {
+		self.sent.remove(relay_parent);
+	}
+
+	/// Forget everything about a peer.
+	pub(crate) fn on_disconnect(&mut self, who: NodeIndex) {
+		self.limits.remove(&who);
+		for sent in self.sent.values_mut() {
+			sent.retain(|&(peer, _)| peer != who);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sent_statements_deduplicated_per_peer() {
+		let mut distribution = StatementDistribution::new();
+		let relay_parent = [1; 32].into();
+		let statement = [2; 32].into();
+
+		assert!(distribution.note_sent(relay_parent, 1, statement));
+		assert!(!distribution.note_sent(relay_parent, 1, statement));
+		assert!(distribution.note_sent(relay_parent, 2, statement));
+		assert!(distribution.note_sent([3; 32].into(), 1, statement));
+
+		distribution.on_disconnect(1);
+		assert!(distribution.note_sent(relay_parent, 1, statement));
+
+		distribution.remove_relay_parent(&relay_parent);
+		assert!(distribution.note_sent(relay_parent, 2, statement));
+	}
+
+	#[test]
+	fn incoming_statements_rate_limited() {
+		let mut distribution = StatementDistribution::new();
+		let start = Instant::now();
+
+		for _ in 0..STATEMENT_BURST {
+			assert!(distribution.allow_incoming(1, start));
+		}
+		assert!(!distribution.allow_incoming(1, start));
+
+		// other peers have their own budget.
+		assert!(distribution.allow_incoming(2, start));
+
+		// budget refills over time.
+		assert!(distribution.allow_incoming(1, start + STATEMENT_INTERVAL * 2));
+		assert!(distribution.allow_incoming(1, start + STATEMENT_INTERVAL * 2));
+		assert!(!distribution.allow_incoming(1, start + STATEMENT_INTERVAL * 2));
+	}
+
+	#[test]
+	fn gossiped_statements_rate_limited_by_signer() {
+		let mut limits = RateLimits::new();
+		let signer: Hash = [1; 32].into();
+		let start = Instant::now();
+
+		for _ in 0..STATEMENT_BURST {
+			assert!(limits.allow(signer, start));
+		}
+		assert!(!limits.allow(signer, start));
+		assert!(limits.allow([2; 32].into(), start));
+	}
+}
@@ -16,23 +16,29 @@
 
 //! Tests for polkadot and validation network.
 
-use super::{PolkadotProtocol, Status, Message, FullStatus};
+use super::{PolkadotProtocol, Status, Message, FullStatus, SessionKeyAnnouncement, BlockDataError, CollatorProof};
+use super::DeferredEvictions;
+use super::{Capabilities, CURRENT_VERSION, LEGACY_VERSION, MIN_SUPPORTED_VERSION};
 use validation::{ValidationSession, Knowledge};
+use collator_pool::{Role, MAX_PRIMARIES};
 
 use parking_lot::Mutex;
-use polkadot_validation::GenericStatement;
-use polkadot_primitives::{Block, SessionKey};
-use polkadot_primitives::parachain::{CandidateReceipt, HeadData, BlockData};
-use substrate_primitives::H512;
-use codec::Encode;
+use polkadot_validation::{GenericStatement, SignedStatement, GroupInfo};
+use polkadot_primitives::{AccountId, Block, Hash, SessionKey, key_to_account_id};
+use polkadot_primitives::parachain::{Id as ParaId, CandidateReceipt, HeadData, BlockData, Collation};
+use substrate_primitives::{H512, ed25519};
+use codec::{Decode, Encode};
 use substrate_network::{
 	Severity, NodeIndex, PeerInfo, ClientHandle, Context, config::Roles,
 	message::Message as SubstrateMessage, specialization::NetworkSpecialization,
 	generic_message::Message as GenericMessage
 };
 
+use std::collections::HashMap;
 use std::sync::Arc;
-use futures::Future;
+use std::time::{Duration, Instant};
+use futures::{Future, Stream};
+use futures::sync::mpsc;
 
 mod validation;
 
@@ -76,6 +82,14 @@ impl TestContext {
 	}
 }
 
+fn default_status() -> Status {
+	Status {
+		collating_for: None,
+		version: CURRENT_VERSION,
+		capabilities: Capabilities::NONE,
+	}
+}
+
 fn make_status(status: &Status, roles: Roles) -> FullStatus {
 	FullStatus {
 		version: 1,
@@ -87,13 +101,68 @@ fn make_status(status: &Status, roles: Roles) -> FullStatus {
 	}
 }
 
-fn make_validation_session(local_key: SessionKey) -> (ValidationSession, Arc<Mutex<Knowledge>>) {
+fn make_key_pair(seed: u8) -> Arc<ed25519::Pair> {
+	Arc::new(ed25519::Pair::from_seed(&[seed; 32]))
+}
+
+fn session_key(pair: &ed25519::Pair) -> SessionKey {
+	pair.public().into()
+}
+
+fn session_key_message(pair: &ed25519::Pair, challenge: &Hash) -> Message {
+	Message::SignedSessionKey(SessionKeyAnnouncement::new(pair, challenge))
+}
+
+fn collator_proof_message(pair: &ed25519::Pair, para_id: ParaId, challenge: &Hash) -> Message {
+	Message::CollatorProof(CollatorProof::new(pair, &para_id, challenge))
+}
+
+// the challenge the protocol issued to a connected peer.
+fn issued_challenge(protocol: &PolkadotProtocol, who: NodeIndex) -> Hash {
+	protocol.peers[&who].local_challenge
+}
+
+// connect a collator and prove ownership of its account.
+fn connect_collator(protocol: &mut PolkadotProtocol, who: NodeIndex, pair: &ed25519::Pair, para_id: ParaId) -> TestContext {
+	let status = Status { collating_for: Some((key_to_account_id(pair), para_id)), ..default_status() };
+
+	let mut ctx = TestContext::default();
+	protocol.on_connect(&mut ctx, who, make_status(&status, Roles::NONE));
+	let proof = collator_proof_message(pair, para_id, &issued_challenge(protocol, who));
+	on_message(protocol, &mut ctx, who, proof);
+	ctx
+}
+
+// connect a validator and announce its session key.
+fn connect_validator(protocol: &mut PolkadotProtocol, who: NodeIndex, pair: &ed25519::Pair, status: &Status) -> TestContext {
+	let mut ctx = TestContext::default();
+	protocol.on_connect(&mut ctx, who, make_status(status, Roles::AUTHORITY));
+	let announcement = session_key_message(pair, &issued_challenge(protocol, who));
+	on_message(protocol, &mut ctx, who, announcement);
+	ctx
+}
+
+fn make_validation_session(local_key: Arc<ed25519::Pair>) -> (ValidationSession, Arc<Mutex<Knowledge>>) {
 	let knowledge = Arc::new(Mutex::new(Knowledge::new()));
-	let c = ValidationSession::new(knowledge.clone(), local_key);
+	let c = ValidationSession::new(knowledge.clone(), local_key, mpsc::unbounded().0);
 
 	(c, knowledge)
 }
 
+// a candidate on parachain 5 committing to the given block data.
+fn make_candidate(block_data: &BlockData) -> CandidateReceipt {
+	CandidateReceipt {
+		parachain_index: 5.into(),
+		collator: [255; 32].into(),
+		head_data: HeadData(vec![9, 9, 9]),
+		signature: H512::from([1; 64]).into(),
+		balance_uploads: Vec::new(),
+		egress_queue_roots: Vec::new(),
+		fees: 1_000_000,
+		block_data_hash: block_data.hash(),
+	}
+}
+
 fn on_message(protocol: &mut PolkadotProtocol, ctx: &mut TestContext, from: NodeIndex, message: Message) {
 	let encoded = message.encode();
 	protocol.on_message(ctx, from, &mut Some(GenericMessage::ChainSpecific(encoded)));
@@ -106,28 +175,50 @@ fn sends_session_key() {
 	let peer_a = 1;
 	let peer_b = 2;
 	let parent_hash = [0; 32].into();
-	let local_key = [1; 32].into();
+	let local_key = make_key_pair(1);
+	let challenge_a = [10; 32].into();
+	let challenge_b = [11; 32].into();
 
-	let validator_status = Status { collating_for: None };
-	let collator_status = Status { collating_for: Some(([2; 32].into(), 5.into())) };
+	let collator_status = Status { collating_for: Some(([2; 32].into(), 5.into())), ..default_status() };
 
+	// peers are challenged on connection.
 	{
 		let mut ctx = TestContext::default();
-		protocol.on_connect(&mut ctx, peer_a, make_status(&validator_status, Roles::AUTHORITY));
-		assert!(ctx.messages.is_empty());
+		protocol.on_connect(&mut ctx, peer_a, make_status(&default_status(), Roles::AUTHORITY));
+		assert_eq!(ctx.messages.len(), 1);
+		assert!(ctx.has_message(peer_a, Message::KeyChallenge(issued_challenge(&protocol, peer_a))));
+
+		on_message(&mut protocol, &mut ctx, peer_a, Message::KeyChallenge(challenge_a));
+		assert_eq!(ctx.messages.len(), 1);
 	}
 
 	{
 		let mut ctx = TestContext::default();
-		let (session, _knowledge) = make_validation_session(local_key);
+		let (session, _knowledge) = make_validation_session(local_key.clone());
 		protocol.new_validation_session(&mut ctx, parent_hash, session);
-		assert!(ctx.has_message(peer_a, Message::SessionKey(local_key)));
+		assert!(ctx.has_message(peer_a, session_key_message(&local_key, &challenge_a)));
 	}
 
+	// keys are only announced once the peer issued its challenge.
 	{
 		let mut ctx = TestContext::default();
 		protocol.on_connect(&mut ctx, peer_b, make_status(&collator_status, Roles::NONE));
-		assert!(ctx.has_message(peer_b, Message::SessionKey(local_key)));
+		assert!(ctx.has_message(peer_b, Message::KeyChallenge(issued_challenge(&protocol, peer_b))));
+		assert_eq!(ctx.messages.len(), 1);
+
+		on_message(&mut protocol, &mut ctx, peer_b, Message::KeyChallenge(challenge_b));
+		assert!(ctx.has_message(peer_b, session_key_message(&local_key, &challenge_b)));
+		assert!(!ctx.has_message(peer_b, session_key_message(&local_key, &challenge_a)));
+	}
+
+	// every connection gets its own challenge, and may only issue one.
+	{
+		let mut ctx = TestContext::default();
+		assert!(issued_challenge(&protocol, peer_a) != issued_challenge(&protocol, peer_b));
+
+		on_message(&mut protocol, &mut ctx, peer_a, Message::KeyChallenge(challenge_b));
+		assert!(ctx.disabled.contains(&peer_a));
+		assert!(!ctx.has_message(peer_a, session_key_message(&local_key, &challenge_b)));
 	}
 }
 
@@ -138,7 +229,7 @@ fn fetches_from_those_with_knowledge() {
 	let peer_a = 1;
 	let peer_b = 2;
 	let parent_hash = [0; 32].into();
-	let local_key = [1; 32].into();
+	let local_key = make_key_pair(1);
 
 	let block_data = BlockData(vec![1, 2, 3, 4]);
 	let block_data_hash = block_data.hash();
@@ -154,12 +245,15 @@ fn fetches_from_those_with_knowledge() {
 	};
 
 	let candidate_hash = candidate_receipt.hash();
-	let a_key = [3; 32].into();
-	let b_key = [4; 32].into();
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+	let a_key = session_key(&a_pair);
+	let b_key = session_key(&b_pair);
+	let challenge: Hash = [10; 32].into();
 
-	let status = Status { collating_for: None };
+	let status = default_status();
 
-	let (session, knowledge) = make_validation_session(local_key);
+	let (session, knowledge) = make_validation_session(local_key.clone());
 	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
 
 	knowledge.lock().note_statement(a_key, &GenericStatement::Valid(candidate_hash));
@@ -169,13 +263,15 @@ fn fetches_from_those_with_knowledge() {
 	{
 		let mut ctx = TestContext::default();
 		protocol.on_connect(&mut ctx, peer_a, make_status(&status, Roles::AUTHORITY));
-		assert!(ctx.has_message(peer_a, Message::SessionKey(local_key)));
+		on_message(&mut protocol, &mut ctx, peer_a, Message::KeyChallenge(challenge));
+		assert!(ctx.has_message(peer_a, session_key_message(&local_key, &challenge)));
 	}
 
 	// peer A gives session key and gets asked for data.
 	{
 		let mut ctx = TestContext::default();
-		on_message(&mut protocol, &mut ctx, peer_a, Message::SessionKey(a_key));
+		let announcement = session_key_message(&a_pair, &issued_challenge(&protocol, peer_a));
+		on_message(&mut protocol, &mut ctx, peer_a, announcement);
 		assert!(protocol.validators.contains_key(&a_key));
 		assert!(ctx.has_message(peer_a, Message::RequestBlockData(1, parent_hash, candidate_hash)));
 	}
@@ -184,9 +280,8 @@ fn fetches_from_those_with_knowledge() {
 
 	// peer B connects and sends session key. request already assigned to A
 	{
-		let mut ctx = TestContext::default();
-		protocol.on_connect(&mut ctx, peer_b, make_status(&status, Roles::AUTHORITY));
-		on_message(&mut protocol, &mut ctx, peer_b, Message::SessionKey(b_key));
+		let ctx = connect_validator(&mut protocol, peer_b, &b_pair, &status);
+		assert!(protocol.validators.contains_key(&b_key));
 		assert!(!ctx.has_message(peer_b, Message::RequestBlockData(2, parent_hash, candidate_hash)));
 
 	}
@@ -204,7 +299,7 @@ fn fetches_from_those_with_knowledge() {
 		let mut ctx = TestContext::default();
 		on_message(&mut protocol, &mut ctx, peer_b, Message::BlockData(2, Some(block_data.clone())));
 		drop(protocol);
-		assert_eq!(recv.wait().unwrap(), block_data);
+		assert_eq!(recv.wait().unwrap(), Ok(block_data));
 	}
 }
 
@@ -232,7 +327,7 @@ fn fetches_available_block_data() {
 	let candidate_hash = candidate_receipt.hash();
 	let av_store = ::av_store::Store::new_in_memory();
 
-	let status = Status { collating_for: None };
+	let status = default_status();
 
 	protocol.register_availability_store(av_store.clone());
 
@@ -263,19 +358,364 @@ fn remove_bad_collator() {
 	let mut protocol = PolkadotProtocol::new(None);
 
 	let who = 1;
-	let account_id = [2; 32].into();
+	let collator = make_key_pair(2);
 
-	let status = Status { collating_for: Some((account_id, 5.into())) };
+	connect_collator(&mut protocol, who, &collator, 5.into());
 
 	{
 		let mut ctx = TestContext::default();
-		protocol.on_connect(&mut ctx, who, make_status(&status, Roles::NONE));
+		protocol.disconnect_bad_collator(&mut ctx, key_to_account_id(&collator));
+		assert!(ctx.disabled.contains(&who));
+	}
+}
+
+#[test]
+fn bad_collator_demoted_in_favour_of_backup() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let parent_hash = [0; 32].into();
+	let local_key = make_key_pair(1);
+
+	{
+		let (session, _knowledge) = make_validation_session(local_key.clone());
+		protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
 	}
 
+	let collators: Vec<_> = (0..MAX_PRIMARIES + 1).map(|i| {
+		let who = i + 1;
+		let collator = make_key_pair(10 + i as u8);
+		let ctx = connect_collator(&mut protocol, who, &collator, 5.into());
+
+		let role = if i < MAX_PRIMARIES { Role::Primary } else { Role::Backup };
+		assert!(ctx.has_message(who, Message::CollatorRole(role)));
+		(who, key_to_account_id(&collator))
+	}).collect();
+
+	let (bad_primary, bad_account) = collators[0];
+	let (backup, _) = collators[MAX_PRIMARIES];
+
+	{
+		let mut ctx = TestContext::default();
+		protocol.note_bad_collator(&mut ctx, bad_account);
+		assert!(ctx.has_message(backup, Message::CollatorRole(Role::Primary)));
+		assert!(ctx.has_message(bad_primary, Message::CollatorRole(Role::Backup)));
+		assert!(ctx.disabled.is_empty());
+	}
+
+	// collators with repeatedly invalid collations are disconnected.
+	{
+		let mut ctx = TestContext::default();
+		protocol.note_bad_collator(&mut ctx, bad_account);
+		assert!(ctx.disabled.contains(&bad_primary));
+	}
+}
+
+#[test]
+fn collators_must_prove_account_ownership() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let para_id: ParaId = 5.into();
+	let collator = make_key_pair(2);
+	let status = Status { collating_for: Some((key_to_account_id(&collator), para_id)), ..default_status() };
+
+	let block_data = BlockData(vec![1, 2, 3]);
+	let collation = Collation {
+		receipt: CandidateReceipt {
+			collator: key_to_account_id(&collator),
+			..make_candidate(&block_data)
+		},
+		block_data,
+	};
+
+	// collations before the proof are rejected.
 	{
 		let mut ctx = TestContext::default();
-		protocol.disconnect_bad_collator(&mut ctx, account_id);
+		protocol.on_connect(&mut ctx, 1, make_status(&status, Roles::NONE));
+		assert!(!protocol.peers[&1].collator_authenticated);
+
+		on_message(&mut protocol, &mut ctx, 1, Message::Collation([0; 32].into(), collation));
+		assert!(ctx.disabled.contains(&1));
+	}
+
+	// proofs by another key or over another challenge are rejected.
+	let wrong_key = make_key_pair(3);
+	for (who, pair, proof_para) in vec![
+		(2, &*wrong_key, para_id),
+		(3, &*collator, para_id),
+		(4, &*collator, 6.into()),
+	] {
+		let mut ctx = TestContext::default();
+		protocol.on_connect(&mut ctx, who, make_status(&status, Roles::NONE));
+
+		// peer 3 answers with the challenge issued to peer 1.
+		let challenge = issued_challenge(&protocol, if who == 3 { 1 } else { who });
+		on_message(&mut protocol, &mut ctx, who, collator_proof_message(pair, proof_para, &challenge));
 		assert!(ctx.disabled.contains(&who));
+		assert!(!protocol.peers[&who].collator_authenticated);
+	}
+
+	{
+		let mut ctx = TestContext::default();
+		protocol.on_connect(&mut ctx, 5, make_status(&status, Roles::NONE));
+		let challenge = issued_challenge(&protocol, 5);
+		on_message(&mut protocol, &mut ctx, 5, collator_proof_message(&collator, para_id, &challenge));
+		assert!(ctx.disabled.is_empty());
+		assert!(protocol.peers[&5].collator_authenticated);
+
+		// a second proof is a protocol violation.
+		on_message(&mut protocol, &mut ctx, 5, collator_proof_message(&collator, para_id, &challenge));
+		assert!(ctx.disabled.contains(&5));
+	}
+
+	// the other peers claiming the account were never registered, so their
+	// disconnection leaves the authenticated collator in place.
+	protocol.on_disconnect(&mut TestContext::default(), 2);
+	assert!(protocol.collator_peer(key_to_account_id(&collator)).is_some());
+}
+
+#[test]
+fn replayed_collator_proofs_rejected() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let para_id: ParaId = 5.into();
+	let collator = make_key_pair(2);
+	let status = Status { collating_for: Some((key_to_account_id(&collator), para_id)), ..default_status() };
+
+	let mut ctx = TestContext::default();
+	protocol.on_connect(&mut ctx, 1, make_status(&status, Roles::NONE));
+	protocol.on_connect(&mut ctx, 2, make_status(&status, Roles::NONE));
+	assert!(issued_challenge(&protocol, 1) != issued_challenge(&protocol, 2));
+
+	// the proof the collator sent on its own connection...
+	let proof = collator_proof_message(&collator, para_id, &issued_challenge(&protocol, 1));
+	on_message(&mut protocol, &mut ctx, 1, proof.clone());
+	assert!(protocol.peers[&1].collator_authenticated);
+
+	// ...doesn't authenticate another connection it is replayed on.
+	on_message(&mut protocol, &mut ctx, 2, proof);
+	assert!(ctx.disabled.contains(&2));
+	assert!(!protocol.peers[&2].collator_authenticated);
+	assert!(!ctx.disabled.contains(&1));
+}
+
+#[test]
+fn collator_whitelists_enforced() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let permissioned: ParaId = 5.into();
+	let open: ParaId = 6.into();
+	let allowed = make_key_pair(2);
+	let unlisted = make_key_pair(3);
+	let legacy = make_key_pair(4);
+
+	let mut whitelists = HashMap::new();
+	whitelists.insert(permissioned, vec![key_to_account_id(&allowed)]);
+	protocol.note_collator_whitelists(&mut TestContext::default(), whitelists);
+
+	{
+		let ctx = connect_collator(&mut protocol, 1, &unlisted, permissioned);
+		assert!(ctx.disconnected.contains(&1));
+		assert!(!protocol.peers.contains_key(&1));
+	}
+
+	// older collators can't prove ownership, so they can't collate on permissioned parachains.
+	{
+		let mut ctx = TestContext::default();
+		let status = Status { collating_for: Some((key_to_account_id(&allowed), permissioned)), version: LEGACY_VERSION, ..default_status() };
+		protocol.on_connect(&mut ctx, 2, make_status(&status, Roles::NONE));
+		assert!(ctx.disconnected.contains(&2));
+	}
+
+	{
+		let mut ctx = TestContext::default();
+		let status = Status { collating_for: Some((key_to_account_id(&legacy), open)), version: LEGACY_VERSION, ..default_status() };
+		protocol.on_connect(&mut ctx, 3, make_status(&status, Roles::NONE));
+		assert!(ctx.disconnected.is_empty());
+		assert!(protocol.peers[&3].collator_authenticated);
+	}
+
+	{
+		let ctx = connect_collator(&mut protocol, 4, &allowed, permissioned);
+		assert!(ctx.disconnected.is_empty());
+		assert!(protocol.peers[&4].collator_authenticated);
+	}
+
+	// both parachains become permissioned, without the connected collators.
+	{
+		let mut ctx = TestContext::default();
+		let mut whitelists = HashMap::new();
+		whitelists.insert(permissioned, vec![key_to_account_id(&unlisted)]);
+		whitelists.insert(open, vec![key_to_account_id(&legacy)]);
+		protocol.note_collator_whitelists(&mut ctx, whitelists);
+
+		assert!(ctx.disconnected.contains(&3));
+		assert!(ctx.disconnected.contains(&4));
+	}
+
+	// collations arriving before the disconnection are not accepted.
+	{
+		let mut ctx = TestContext::default();
+		let block_data = BlockData(vec![1, 2, 3]);
+		let collation = Collation {
+			receipt: CandidateReceipt {
+				collator: key_to_account_id(&allowed),
+				..make_candidate(&block_data)
+			},
+			block_data,
+		};
+
+		on_message(&mut protocol, &mut ctx, 4, Message::Collation([0; 32].into(), collation));
+		assert!(ctx.disconnected.contains(&4));
+		assert_eq!(protocol.reputation().score(&4), 0);
+	}
+}
+
+#[test]
+fn collator_sends_proof_to_upgraded_peers() {
+	let collator = make_key_pair(2);
+	let para_id: ParaId = 5.into();
+	let mut protocol = PolkadotProtocol::new(Some((collator.clone(), para_id)));
+
+	let status = default_status();
+	let legacy_status = Status { version: LEGACY_VERSION, ..default_status() };
+
+	let challenge: Hash = [10; 32].into();
+
+	let mut ctx = TestContext::default();
+	protocol.on_connect(&mut ctx, 1, make_status(&status, Roles::AUTHORITY));
+	protocol.on_connect(&mut ctx, 2, make_status(&legacy_status, Roles::AUTHORITY));
+	assert!(!ctx.has_message(1, collator_proof_message(&collator, para_id, &challenge)));
+
+	on_message(&mut protocol, &mut ctx, 1, Message::KeyChallenge(challenge));
+	assert!(ctx.has_message(1, collator_proof_message(&collator, para_id, &challenge)));
+	assert_eq!(ctx.messages.iter().filter(|&&(who, _)| who == 2).count(), 0);
+
+	// our status claims the account of the key.
+	let own_status = Status::decode(&mut &protocol.status()[..]).unwrap();
+	assert_eq!(own_status.collating_for, Some((key_to_account_id(&collator), para_id)));
+}
+
+// make block data for a candidate available, returning the candidate hash.
+fn make_available(protocol: &mut PolkadotProtocol, parent_hash: Hash, block_data: &BlockData) -> Hash {
+	let candidate_receipt = make_candidate(block_data);
+	let para_id = candidate_receipt.parachain_index;
+
+	let candidate_hash = candidate_receipt.hash();
+	let av_store = ::av_store::Store::new_in_memory();
+	protocol.register_availability_store(av_store.clone());
+
+	av_store.make_available(::av_store::Data {
+		relay_parent: parent_hash,
+		parachain_id: para_id,
+		candidate_hash,
+		block_data: block_data.clone(),
+		extrinsic: None,
+	}).unwrap();
+
+	candidate_hash
+}
+
+#[test]
+fn large_messages_compressed_for_negotiating_peers() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let parent_hash = [0; 32].into();
+	let block_data = BlockData(vec![7; ::compression::COMPRESSION_THRESHOLD]);
+	let candidate_hash = make_available(&mut protocol, parent_hash, &block_data);
+
+	let compressing = 1;
+	let plain = 2;
+	let legacy = 3;
+
+	let mut ctx = TestContext::default();
+	let compressing_status = Status { capabilities: Capabilities::COMPRESSION, ..default_status() };
+	let legacy_status = Status { version: LEGACY_VERSION, capabilities: Capabilities::COMPRESSION, ..default_status() };
+	protocol.on_connect(&mut ctx, compressing, make_status(&compressing_status, Roles::FULL));
+	protocol.on_connect(&mut ctx, plain, make_status(&default_status(), Roles::FULL));
+	protocol.on_connect(&mut ctx, legacy, make_status(&legacy_status, Roles::FULL));
+
+	let mut ctx = TestContext::default();
+	for &who in &[compressing, plain, legacy] {
+		on_message(&mut protocol, &mut ctx, who, Message::RequestBlockData(1, parent_hash, candidate_hash));
+	}
+
+	let response = Message::BlockData(1, Some(block_data));
+	assert!(ctx.has_message(plain, response.clone()));
+	assert!(ctx.has_message(legacy, response.clone()));
+	assert!(!ctx.has_message(compressing, response.clone()));
+
+	let compressed = ctx.messages.iter().filter_map(|&(ref who, ref msg)| match msg {
+		GenericMessage::ChainSpecific(ref data) if who == &compressing => Message::decode(&mut data.as_slice()),
+		_ => None,
+	}).next().unwrap();
+
+	match compressed {
+		Message::Compressed(size, data) => {
+			let encoded = response.encode();
+			assert_eq!(size as usize, encoded.len());
+			assert!(data.len() < encoded.len());
+			assert_eq!(::compression::decompress(&data, size).unwrap(), encoded);
+		}
+		other => panic!("Expected compressed message, got {:?}", other),
+	}
+}
+
+#[test]
+fn compressed_messages_only_from_negotiating_peers() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let parent_hash = [0; 32].into();
+	let block_data = BlockData(vec![1, 2, 3, 4]);
+	let candidate_hash = make_available(&mut protocol, parent_hash, &block_data);
+
+	let compress = |message: Message| {
+		let encoded = message.encode();
+		Message::Compressed(encoded.len() as u32, ::zstd::block::compress(&encoded, 3).unwrap())
+	};
+	let request = Message::RequestBlockData(1, parent_hash, candidate_hash);
+
+	let compressing_status = Status { capabilities: Capabilities::COMPRESSION, ..default_status() };
+	for who in 1..5 {
+		protocol.on_connect(&mut TestContext::default(), who, make_status(&compressing_status, Roles::FULL));
+	}
+	protocol.on_connect(&mut TestContext::default(), 5, make_status(&default_status(), Roles::FULL));
+
+	// compressed requests are served, small responses are sent uncompressed.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, 1, compress(request.clone()));
+		assert!(ctx.disabled.is_empty());
+		assert!(ctx.has_message(1, Message::BlockData(1, Some(block_data))));
+	}
+
+	// a wrong uncompressed size is rejected.
+	{
+		let mut ctx = TestContext::default();
+		let message = match compress(request.clone()) {
+			Message::Compressed(size, data) => Message::Compressed(size + 1, data),
+			_ => unreachable!(),
+		};
+
+		on_message(&mut protocol, &mut ctx, 2, message);
+		assert!(ctx.disabled.contains(&2));
+	}
+
+	// as are sizes beyond the limit.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, 3, Message::Compressed(u32::max_value(), vec![1, 2, 3]));
+		assert!(ctx.disabled.contains(&3));
+		assert!(ctx.messages.is_empty());
+	}
+
+	// and compressed messages within compressed messages.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, 4, compress(compress(request.clone())));
+		assert!(ctx.disabled.contains(&4));
+		assert!(ctx.messages.is_empty());
+	}
+
+	// peers which did not negotiate compression may not send compressed messages.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, 5, compress(request));
+		assert!(ctx.disabled.contains(&5));
+		assert!(ctx.messages.is_empty());
 	}
 }
 
@@ -286,16 +726,22 @@ fn many_session_keys() {
 	let parent_a = [1; 32].into();
 	let parent_b = [2; 32].into();
 
-	let local_key_a = [3; 32].into();
-	let local_key_b = [4; 32].into();
+	let local_key_a = make_key_pair(3);
+	let local_key_b = make_key_pair(4);
 
-	let (session_a, _knowledge_a) = make_validation_session(local_key_a);
-	let (session_b, _knowledge_b) = make_validation_session(local_key_b);
+	let (session_a, _knowledge_a) = make_validation_session(local_key_a.clone());
+	let (session_b, _knowledge_b) = make_validation_session(local_key_b.clone());
 
 	protocol.new_validation_session(&mut TestContext::default(), parent_a, session_a);
 	protocol.new_validation_session(&mut TestContext::default(), parent_b, session_b);
 
-	assert_eq!(protocol.live_validation_sessions.recent_keys(), &[local_key_a, local_key_b]);
+	assert_eq!(
+		protocol.live_validation_sessions.recent_keys(),
+		&[session_key(&local_key_a), session_key(&local_key_b)],
+	);
+
+	let status = default_status();
+	let challenge: Hash = [10; 32].into();
 
 	let peer_a = 1;
 
@@ -303,11 +749,11 @@ fn many_session_keys() {
 	{
 		let mut ctx = TestContext::default();
 
-		let status = Status { collating_for: None };
 		protocol.on_connect(&mut ctx, peer_a, make_status(&status, Roles::AUTHORITY));
+		on_message(&mut protocol, &mut ctx, peer_a, Message::KeyChallenge(challenge));
 
-		assert!(ctx.has_message(peer_a, Message::SessionKey(local_key_a)));
-		assert!(ctx.has_message(peer_a, Message::SessionKey(local_key_b)));
+		assert!(ctx.has_message(peer_a, session_key_message(&local_key_a, &challenge)));
+		assert!(ctx.has_message(peer_a, session_key_message(&local_key_b, &challenge)));
 	}
 
 	let peer_b = 2;
@@ -317,10 +763,677 @@ fn many_session_keys() {
 	{
 		let mut ctx = TestContext::default();
 
-		let status = Status { collating_for: None };
 		protocol.on_connect(&mut ctx, peer_b, make_status(&status, Roles::AUTHORITY));
+		on_message(&mut protocol, &mut ctx, peer_b, Message::KeyChallenge(challenge));
 
-		assert!(!ctx.has_message(peer_b, Message::SessionKey(local_key_a)));
-		assert!(ctx.has_message(peer_b, Message::SessionKey(local_key_b)));
+		assert!(!ctx.has_message(peer_b, session_key_message(&local_key_a, &challenge)));
+		assert!(ctx.has_message(peer_b, session_key_message(&local_key_b, &challenge)));
 	}
 }
+
+#[test]
+fn deferred_statements_expire_with_validation_session() {
+	let mut protocol = PolkadotProtocol::new(None);
+	let parent_hash = [0; 32].into();
+	let (session, _knowledge) = make_validation_session(Arc::new(make_key_pair(1)));
+	let deferred = session.deferred_statements();
+
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	let sender = session_key(&make_key_pair(2));
+	let statement = SignedStatement {
+		statement: GenericStatement::Valid([1; 32].into()),
+		sender,
+		signature: H512::from([2; 64]).into(),
+	};
+
+	let mut groups = HashMap::new();
+	groups.insert(ParaId::from(5), GroupInfo {
+		validity_guarantors: ::std::iter::once(sender).collect(),
+		needed_validity: 1,
+	});
+
+	deferred.lock().push(statement.clone(), &groups);
+	deferred.lock().push(statement, &HashMap::new());
+
+	// nothing is counted while the relay parent is live.
+	assert_eq!(protocol.deferred_evictions(), DeferredEvictions::default());
+
+	protocol.remove_validation_session(&parent_hash);
+
+	let expected = DeferredEvictions { unknown_sender: 1, expired: 1, ..Default::default() };
+	assert_eq!(protocol.deferred_evictions(), expected);
+
+	// removing again does not count anything twice.
+	protocol.remove_validation_session(&parent_hash);
+	assert_eq!(protocol.deferred_evictions(), expected);
+}
+
+#[test]
+fn rejects_unverified_session_keys() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let a_pair = make_key_pair(3);
+	let a_key = session_key(&a_pair);
+	let victim_key = session_key(&make_key_pair(4));
+
+	let peer_b = 2;
+
+	let status = default_status();
+	protocol.on_connect(&mut TestContext::default(), peer_a, make_status(&status, Roles::AUTHORITY));
+	protocol.on_connect(&mut TestContext::default(), peer_b, make_status(&status, Roles::AUTHORITY));
+
+	// announcement signed over the challenge of another connection.
+	{
+		let mut ctx = TestContext::default();
+		let replayed = session_key_message(&a_pair, &issued_challenge(&protocol, peer_b));
+		on_message(&mut protocol, &mut ctx, peer_a, replayed);
+		assert!(ctx.disabled.contains(&peer_a));
+		assert!(!protocol.validators.contains_key(&a_key));
+	}
+
+	// claiming somebody else's key with our own signature.
+	{
+		let mut ctx = TestContext::default();
+		let mut announcement = SessionKeyAnnouncement::new(&a_pair, &issued_challenge(&protocol, peer_a));
+		announcement.key = victim_key;
+
+		on_message(&mut protocol, &mut ctx, peer_a, Message::SignedSessionKey(announcement));
+		assert!(ctx.disabled.contains(&peer_a));
+		assert!(!protocol.validators.contains_key(&victim_key));
+	}
+
+	assert!(protocol.peers.get(&peer_a).unwrap().validator_keys.as_slice().is_empty());
+}
+
+#[test]
+fn decodes_legacy_status() {
+	let collating_for = Some(([2; 32].into(), 5.into()));
+
+	// status of a node predating version negotiation.
+	let legacy = collating_for.encode();
+	let status = Status::decode(&mut &legacy[..]).unwrap();
+
+	assert_eq!(status.collating_for, collating_for);
+	assert_eq!(status.version, LEGACY_VERSION);
+	assert_eq!(status.capabilities, Capabilities::NONE);
+
+	// and the legacy fields are a prefix of the current encoding.
+	let current = Status { collating_for, ..default_status() }.encode();
+	assert!(current.starts_with(&legacy));
+}
+
+#[test]
+fn connects_legacy_peers() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let legacy = 1;
+	let current = 2;
+	let local_key = make_key_pair(1);
+	let legacy_pair = make_key_pair(3);
+	let legacy_key = session_key(&legacy_pair);
+
+	let (session, _knowledge) = make_validation_session(local_key.clone());
+	protocol.new_validation_session(&mut TestContext::default(), [0; 32].into(), session);
+
+	// a node predating version negotiation only puts what it collates for in its status.
+	let mut legacy_status = make_status(&default_status(), Roles::AUTHORITY);
+	legacy_status.chain_status = None::<(AccountId, ParaId)>.encode();
+
+	// it can't sign announcements, so it gets our bare session key right away.
+	{
+		let mut ctx = TestContext::default();
+		protocol.on_connect(&mut ctx, legacy, legacy_status);
+		assert!(ctx.disabled.is_empty());
+		assert!(ctx.disconnected.is_empty());
+		assert_eq!(protocol.peers[&legacy].version, LEGACY_VERSION);
+		assert!(!protocol.peers[&legacy].authenticates_keys());
+		assert!(ctx.has_message(legacy, Message::SessionKey(session_key(&local_key))));
+		assert!(!ctx.has_message(legacy, Message::KeyChallenge(issued_challenge(&protocol, legacy))));
+	}
+
+	// its unsigned session key is accepted, unauthenticated.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, legacy, Message::SessionKey(legacy_key));
+		assert!(ctx.disabled.is_empty());
+		assert_eq!(protocol.validators.get(&legacy_key), Some(&legacy));
+	}
+
+	// newer peers must sign their announcements.
+	{
+		let mut ctx = TestContext::default();
+		protocol.on_connect(&mut ctx, current, make_status(&default_status(), Roles::AUTHORITY));
+		assert!(protocol.peers[&current].authenticates_keys());
+		on_message(&mut protocol, &mut ctx, current, Message::SessionKey(legacy_key));
+		assert!(ctx.disabled.contains(&current));
+		assert_eq!(protocol.validators.get(&legacy_key), Some(&legacy));
+	}
+
+	// a signed announcement takes the key over, and the legacy peer can't take it back.
+	{
+		let mut ctx = TestContext::default();
+		let announcement = session_key_message(&legacy_pair, &issued_challenge(&protocol, current));
+		on_message(&mut protocol, &mut ctx, current, announcement);
+		assert!(ctx.disabled.is_empty());
+		assert_eq!(protocol.validators.get(&legacy_key), Some(&current));
+
+		on_message(&mut protocol, &mut ctx, legacy, Message::SessionKey(legacy_key));
+		assert!(ctx.disabled.is_empty());
+		assert_eq!(protocol.validators.get(&legacy_key), Some(&current));
+	}
+
+	// nor remove it when disconnecting.
+	protocol.on_disconnect(&mut TestContext::default(), legacy);
+	assert_eq!(protocol.validators.get(&legacy_key), Some(&current));
+}
+
+#[test]
+fn legacy_validators_receive_collations() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let legacy = 1;
+	let validator_key = session_key(&make_key_pair(3));
+	let relay_parent = [1; 32].into();
+	let block_data = BlockData(vec![1, 2, 3]);
+	let collation = Collation { receipt: make_candidate(&block_data), block_data };
+	let targets = vec![validator_key].into_iter().collect();
+
+	protocol.add_local_collation(&mut TestContext::default(), relay_parent, targets, collation.clone());
+
+	let mut ctx = TestContext::default();
+	let legacy_status = Status { version: LEGACY_VERSION, ..default_status() };
+	protocol.on_connect(&mut ctx, legacy, make_status(&legacy_status, Roles::AUTHORITY));
+	on_message(&mut protocol, &mut ctx, legacy, Message::SessionKey(validator_key));
+	on_message(&mut protocol, &mut ctx, legacy, Message::CollatorRole(Role::Primary));
+
+	assert!(ctx.disabled.is_empty());
+	assert!(ctx.has_message(legacy, Message::Collation(relay_parent, collation)));
+}
+
+#[test]
+fn negotiates_version_and_capabilities() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+
+	let status_a = Status {
+		version: CURRENT_VERSION + 1,
+		capabilities: Capabilities::COMPRESSION.union(Capabilities(1 << 10)),
+		..default_status()
+	};
+	let status_b = Status { version: LEGACY_VERSION, ..default_status() };
+
+	protocol.on_connect(&mut TestContext::default(), peer_a, make_status(&status_a, Roles::FULL));
+	protocol.on_connect(&mut TestContext::default(), peer_b, make_status(&status_b, Roles::FULL));
+
+	let info_a = protocol.peers.get(&peer_a).unwrap();
+	assert_eq!(info_a.version, CURRENT_VERSION);
+	assert_eq!(info_a.capabilities, Capabilities::COMPRESSION);
+
+	let info_b = protocol.peers.get(&peer_b).unwrap();
+	assert_eq!(info_b.version, LEGACY_VERSION);
+	assert_eq!(info_b.capabilities, Capabilities::NONE);
+}
+
+#[test]
+fn rejects_bad_status() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+
+	{
+		let mut ctx = TestContext::default();
+		let mut status = make_status(&default_status(), Roles::FULL);
+		status.chain_status = vec![5];
+
+		protocol.on_connect(&mut ctx, peer_a, status);
+		assert!(ctx.disabled.contains(&peer_a));
+		assert!(!protocol.peers.contains_key(&peer_a));
+	}
+
+	{
+		let mut ctx = TestContext::default();
+		let status = Status { version: MIN_SUPPORTED_VERSION - 1, ..default_status() };
+
+		protocol.on_connect(&mut ctx, peer_b, make_status(&status, Roles::FULL));
+		assert!(ctx.disconnected.contains(&peer_b));
+		assert!(!protocol.peers.contains_key(&peer_b));
+	}
+}
+
+#[test]
+fn fetches_from_peers_with_best_reputation() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+	let parent_hash = [0; 32].into();
+
+	let block_data = BlockData(vec![1, 2, 3, 4]);
+	let candidate_receipt = make_candidate(&block_data);
+	let candidate_hash = candidate_receipt.hash();
+
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+	let status = default_status();
+
+	let (session, knowledge) = make_validation_session(make_key_pair(1));
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	knowledge.lock().note_statement(session_key(&a_pair), &GenericStatement::Valid(candidate_hash));
+	knowledge.lock().note_statement(session_key(&b_pair), &GenericStatement::Valid(candidate_hash));
+
+	for &(peer, ref pair) in &[(peer_a, &a_pair), (peer_b, &b_pair)] {
+		connect_validator(&mut protocol, peer, pair, &status);
+	}
+
+	protocol.reputation.note(peer_b, 50);
+
+	// the better peer gets asked first.
+	{
+		let mut ctx = TestContext::default();
+		let _recv = protocol.fetch_block_data(&mut ctx, &candidate_receipt, parent_hash);
+		assert!(ctx.has_message(peer_b, Message::RequestBlockData(1, parent_hash, candidate_hash)));
+		assert!(!ctx.has_message(peer_a, Message::RequestBlockData(1, parent_hash, candidate_hash)));
+	}
+
+	// serving bad data costs reputation and moves the request to the next peer.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, peer_b, Message::BlockData(1, Some(BlockData(vec![5]))));
+		assert_eq!(protocol.reputation().score(&peer_b), 50 + ::reputation::INVALID_BLOCK_DATA);
+		assert!(ctx.has_message(peer_a, Message::RequestBlockData(2, parent_hash, candidate_hash)));
+	}
+}
+
+#[test]
+fn bans_peers_with_low_reputation() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let who = 1;
+	let collator = make_key_pair(2);
+	let account_id = key_to_account_id(&collator);
+	let status = Status { collating_for: Some((account_id, 5.into())), ..default_status() };
+
+	connect_collator(&mut protocol, who, &collator, 5.into());
+
+	// a badly signed collation gets the collator banned.
+	{
+		let mut ctx = TestContext::default();
+		let block_data = BlockData(vec![1, 2, 3]);
+		let collation = Collation {
+			receipt: CandidateReceipt {
+				collator: account_id,
+				..make_candidate(&block_data)
+			},
+			block_data,
+		};
+
+		on_message(&mut protocol, &mut ctx, who, Message::Collation([0; 32].into(), collation));
+		assert!(ctx.disabled.contains(&who));
+		assert!(protocol.reputation().is_banned(&who));
+	}
+
+	protocol.on_disconnect(&mut TestContext::default(), who);
+
+	// the node index may be reused by another peer, which starts out neutral.
+	{
+		let mut ctx = TestContext::default();
+		protocol.on_connect(&mut ctx, who, make_status(&status, Roles::NONE));
+		assert!(ctx.disconnected.is_empty());
+		assert!(protocol.peers.contains_key(&who));
+		assert_eq!(protocol.reputation().score(&who), 0);
+		protocol.on_disconnect(&mut ctx, who);
+	}
+
+	// but the ban follows the collator account to any connection until it decayed.
+	{
+		let ctx = connect_collator(&mut protocol, 2, &collator, 5.into());
+		assert!(ctx.disabled.contains(&2));
+		assert!(!protocol.peers[&2].collator_authenticated);
+		assert!(protocol.reputation().is_banned(&2));
+	}
+}
+
+#[test]
+fn block_data_requests_time_out() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+	let parent_hash = [0; 32].into();
+
+	let block_data = BlockData(vec![1, 2, 3, 4]);
+	let candidate_receipt = make_candidate(&block_data);
+	let candidate_hash = candidate_receipt.hash();
+
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+	let status = default_status();
+
+	let (session, knowledge) = make_validation_session(make_key_pair(1));
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	knowledge.lock().note_statement(session_key(&a_pair), &GenericStatement::Valid(candidate_hash));
+	knowledge.lock().note_statement(session_key(&b_pair), &GenericStatement::Valid(candidate_hash));
+
+	connect_validator(&mut protocol, peer_a, &a_pair, &status);
+
+	let start = Instant::now();
+	let recv = protocol.fetch_block_data(&mut TestContext::default(), &candidate_receipt, parent_hash);
+	assert!(protocol.in_flight.contains_key(&(1, peer_a)));
+
+	// nothing happens before the deadline.
+	protocol.time_out_requests(&mut TestContext::default(), start);
+	assert!(protocol.in_flight.contains_key(&(1, peer_a)));
+
+	connect_validator(&mut protocol, peer_b, &b_pair, &status);
+
+	// peer A never answers, so B gets asked with a longer deadline.
+	{
+		let mut ctx = TestContext::default();
+		protocol.time_out_requests(&mut ctx, start + Duration::from_secs(5));
+		protocol.dispatch_pending_requests(&mut ctx);
+
+		assert!(protocol.in_flight.get(&(1, peer_a)).is_none());
+		assert!(ctx.has_message(peer_b, Message::RequestBlockData(2, parent_hash, candidate_hash)));
+		assert_eq!(protocol.reputation().score(&peer_a), ::reputation::UNANSWERED_REQUEST);
+
+		let deadline = protocol.in_flight.get(&(2, peer_b)).unwrap().deadline;
+		assert!(deadline >= start + Duration::from_secs(8));
+		assert!(deadline < start + Duration::from_secs(16));
+	}
+
+	// once every holder was tried, the fetch fails.
+	{
+		let mut ctx = TestContext::default();
+		protocol.time_out_requests(&mut ctx, start + Duration::from_secs(60));
+		protocol.dispatch_pending_requests(&mut ctx);
+
+		assert!(protocol.in_flight.is_empty());
+		assert!(protocol.pending.is_empty());
+		assert_eq!(recv.wait().unwrap(), Err(BlockDataError::AllPeersFailed));
+	}
+}
+
+#[test]
+fn accepts_late_block_data() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+	let peer_c = 3;
+	let parent_hash = [0; 32].into();
+
+	let block_data = BlockData(vec![1, 2, 3, 4]);
+	let candidate_receipt = make_candidate(&block_data);
+	let candidate_hash = candidate_receipt.hash();
+
+	let pairs = [make_key_pair(3), make_key_pair(4), make_key_pair(5)];
+	let status = default_status();
+
+	let (session, knowledge) = make_validation_session(make_key_pair(1));
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	for (who, pair) in [peer_a, peer_b, peer_c].iter().zip(pairs.iter()) {
+		knowledge.lock().note_statement(session_key(pair), &GenericStatement::Valid(candidate_hash));
+		connect_validator(&mut protocol, *who, pair, &status);
+	}
+
+	let start = Instant::now();
+	let recv = protocol.fetch_block_data(&mut TestContext::default(), &candidate_receipt, parent_hash);
+	let (first_id, first) = protocol.in_flight.keys().cloned().next().unwrap();
+
+	// the first peer asked times out, and the request moves on to the next one.
+	protocol.time_out_requests(&mut TestContext::default(), start + Duration::from_secs(5));
+	protocol.dispatch_pending_requests(&mut TestContext::default());
+	let (second_id, second) = protocol.in_flight.keys().cloned().next().unwrap();
+	assert!(second != first);
+
+	// its late answer is still verified and fulfils the request.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, first, Message::BlockData(first_id, Some(block_data.clone())));
+
+		assert!(ctx.disabled.is_empty());
+		assert_eq!(protocol.reputation().score(&first), ::reputation::UNANSWERED_REQUEST + ::reputation::GOOD_BLOCK_DATA);
+		assert!(protocol.in_flight.is_empty());
+		assert!(protocol.pending.is_empty());
+		assert_eq!(recv.wait().unwrap(), Ok(block_data.clone()));
+	}
+
+	// nor is the peer asked since penalised for answering too.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, second, Message::BlockData(second_id, Some(block_data.clone())));
+		assert_eq!(protocol.reputation().score(&second), ::reputation::GOOD_BLOCK_DATA);
+	}
+
+	// late answers are only accepted for a while.
+	let recv = protocol.fetch_block_data(&mut TestContext::default(), &candidate_receipt, parent_hash);
+	let (req_id, who) = protocol.in_flight.keys().cloned().next().unwrap();
+	let deadline = protocol.in_flight[&(req_id, who)].deadline;
+	protocol.time_out_requests(&mut TestContext::default(), deadline);
+	protocol.time_out_requests(&mut TestContext::default(), deadline + Duration::from_secs(64));
+	assert!(protocol.stale_requests.is_empty());
+
+	{
+		let mut ctx = TestContext::default();
+		let score = protocol.reputation().score(&who);
+		on_message(&mut protocol, &mut ctx, who, Message::BlockData(req_id, Some(block_data)));
+		assert_eq!(protocol.reputation().score(&who), score + ::reputation::UNEXPECTED_RESPONSE);
+	}
+
+	drop(recv);
+}
+
+#[test]
+fn block_data_requests_fail_when_holders_do_not_connect() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let parent_hash = [0; 32].into();
+
+	let block_data = BlockData(vec![1, 2, 3, 4]);
+	let candidate_receipt = make_candidate(&block_data);
+	let candidate_hash = candidate_receipt.hash();
+
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+
+	let (session, knowledge) = make_validation_session(make_key_pair(1));
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	// B holds the data as well, but never connects.
+	knowledge.lock().note_statement(session_key(&a_pair), &GenericStatement::Valid(candidate_hash));
+	knowledge.lock().note_statement(session_key(&b_pair), &GenericStatement::Valid(candidate_hash));
+
+	connect_validator(&mut protocol, peer_a, &a_pair, &default_status());
+
+	let start = Instant::now();
+	let recv = protocol.fetch_block_data(&mut TestContext::default(), &candidate_receipt, parent_hash);
+	assert!(protocol.in_flight.contains_key(&(1, peer_a)));
+
+	// A never answers, and there is nobody else to ask.
+	{
+		let mut ctx = TestContext::default();
+		protocol.time_out_requests(&mut ctx, start + Duration::from_secs(5));
+		protocol.dispatch_pending_requests(&mut ctx);
+
+		assert!(protocol.in_flight.is_empty());
+		assert_eq!(protocol.pending.len(), 1);
+		assert!(ctx.messages.is_empty());
+	}
+
+	// the request waits for another holder during the grace period.
+	protocol.time_out_requests(&mut TestContext::default(), start + Duration::from_secs(10));
+	assert_eq!(protocol.pending.len(), 1);
+
+	// and fails once it passed.
+	protocol.time_out_requests(&mut TestContext::default(), start + Duration::from_secs(60));
+	assert!(protocol.pending.is_empty());
+	assert_eq!(recv.wait().unwrap(), Err(BlockDataError::AllPeersFailed));
+}
+
+#[test]
+fn corrupted_block_data_is_rejected() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+	let parent_hash = [0; 32].into();
+
+	let block_data = BlockData(vec![1, 2, 3, 4]);
+	let candidate_receipt = make_candidate(&block_data);
+	let candidate_hash = candidate_receipt.hash();
+
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+	let status = default_status();
+
+	let (session, knowledge) = make_validation_session(make_key_pair(1));
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	knowledge.lock().note_statement(session_key(&a_pair), &GenericStatement::Valid(candidate_hash));
+	knowledge.lock().note_statement(session_key(&b_pair), &GenericStatement::Valid(candidate_hash));
+
+	for &(peer, ref pair) in &[(peer_a, &a_pair), (peer_b, &b_pair)] {
+		connect_validator(&mut protocol, peer, pair, &status);
+	}
+
+	let recv = protocol.fetch_block_data(&mut TestContext::default(), &candidate_receipt, parent_hash);
+	assert!(protocol.in_flight.contains_key(&(1, peer_a)));
+
+	// peer A serves a corrupted payload, which is dropped and the request moves on to B.
+	{
+		let mut ctx = TestContext::default();
+		let mut corrupted = block_data.clone();
+		corrupted.0[0] = 255;
+
+		on_message(&mut protocol, &mut ctx, peer_a, Message::BlockData(1, Some(corrupted)));
+		assert!(ctx.has_message(peer_b, Message::RequestBlockData(2, parent_hash, candidate_hash)));
+		assert_eq!(protocol.reputation().score(&peer_a), ::reputation::INVALID_BLOCK_DATA);
+	}
+
+	// the honest peer B serves the real data.
+	{
+		let mut ctx = TestContext::default();
+		on_message(&mut protocol, &mut ctx, peer_b, Message::BlockData(2, Some(block_data.clone())));
+		assert_eq!(protocol.reputation().score(&peer_b), ::reputation::GOOD_BLOCK_DATA);
+	}
+
+	drop(protocol);
+	assert_eq!(recv.wait().unwrap(), Ok(block_data));
+}
+
+#[test]
+fn candidate_statements_sent_to_targets_only() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+	let peer_c = 3;
+	let parent_hash = [0; 32].into();
+
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+	let c_pair = make_key_pair(5);
+	let unconnected_key = session_key(&make_key_pair(6));
+
+	let (session, _knowledge) = make_validation_session(make_key_pair(1));
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	connect_validator(&mut protocol, peer_a, &a_pair, &default_status());
+	connect_validator(&mut protocol, peer_c, &c_pair, &default_status());
+
+	// peer B predates direct statements and signed session keys.
+	{
+		let mut ctx = TestContext::default();
+		let legacy_status = Status { version: LEGACY_VERSION, ..default_status() };
+		protocol.on_connect(&mut ctx, peer_b, make_status(&legacy_status, Roles::AUTHORITY));
+		on_message(&mut protocol, &mut ctx, peer_b, Message::SessionKey(session_key(&b_pair)));
+	}
+
+	let statement = SignedStatement {
+		statement: GenericStatement::Candidate(make_candidate(&BlockData(vec![2]))),
+		signature: H512::from([2; 64]).into(),
+		sender: session_key(&make_key_pair(1)),
+	};
+
+	let targets: ::std::collections::HashSet<_> = vec![
+		session_key(&a_pair),
+		session_key(&b_pair),
+	].into_iter().collect();
+
+	// B can only be reached over gossip.
+	{
+		let mut ctx = TestContext::default();
+		let unreachable = protocol.distribute_candidate_statement(&mut ctx, parent_hash, targets.clone(), statement.clone());
+
+		assert!(unreachable);
+		assert_eq!(ctx.messages.len(), 1);
+		assert!(ctx.has_message(peer_a, Message::Statement(parent_hash, statement.clone())));
+	}
+
+	// nothing is sent twice, and targets which aren't connected can't be reached.
+	{
+		let mut ctx = TestContext::default();
+		let targets = vec![session_key(&a_pair), unconnected_key].into_iter().collect();
+		let unreachable = protocol.distribute_candidate_statement(&mut ctx, parent_hash, targets, statement.clone());
+
+		assert!(unreachable);
+		assert!(ctx.messages.is_empty());
+	}
+
+	{
+		let mut ctx = TestContext::default();
+		let targets = vec![session_key(&a_pair), session_key(&c_pair)].into_iter().collect();
+		let unreachable = protocol.distribute_candidate_statement(&mut ctx, parent_hash, targets, statement.clone());
+
+		assert!(!unreachable);
+		assert!(ctx.has_message(peer_c, Message::Statement(parent_hash, statement.clone())));
+		assert!(!ctx.has_message(peer_a, Message::Statement(parent_hash, statement)));
+	}
+}
+
+#[test]
+fn direct_statements_must_come_from_signer() {
+	let mut protocol = PolkadotProtocol::new(None);
+
+	let peer_a = 1;
+	let peer_b = 2;
+	let parent_hash = [0; 32].into();
+
+	let a_pair = make_key_pair(3);
+	let b_pair = make_key_pair(4);
+
+	let (statements_tx, statements_rx) = mpsc::unbounded();
+	let knowledge = Arc::new(Mutex::new(Knowledge::new()));
+	let session = ValidationSession::new(knowledge, make_key_pair(1), statements_tx);
+	protocol.new_validation_session(&mut TestContext::default(), parent_hash, session);
+
+	for &(peer, ref pair) in &[(peer_a, &a_pair), (peer_b, &b_pair)] {
+		connect_validator(&mut protocol, peer, pair, &default_status());
+	}
+
+	let statement = SignedStatement {
+		statement: GenericStatement::Valid([5; 32].into()),
+		signature: H512::from([2; 64]).into(),
+		sender: session_key(&a_pair),
+	};
+
+	// peer B relaying A's statement is penalised and the statement dropped.
+	on_message(&mut protocol, &mut TestContext::default(), peer_b, Message::Statement(parent_hash, statement.clone()));
+	assert_eq!(protocol.reputation().score(&peer_b), ::reputation::UNEXPECTED_STATEMENT);
+
+	// A's own statement is passed on to the validation session.
+	on_message(&mut protocol, &mut TestContext::default(), peer_a, Message::Statement(parent_hash, statement.clone()));
+
+	drop(protocol);
+	let received: Vec<_> = statements_rx.collect().wait().unwrap();
+	assert_eq!(received, vec![statement.encode()]);
+}
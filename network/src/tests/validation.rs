@@ -23,7 +23,7 @@ use substrate_keyring::Keyring;
 use {PolkadotProtocol};
 
 use polkadot_validation::{SharedTable, MessagesFrom, Network, TableRouter};
-use polkadot_primitives::{AccountId, Block, Hash, Header, BlockId};
+use polkadot_primitives::{AccountId, Block, BlockNumber, Hash, Header, BlockId};
 use polkadot_primitives::parachain::{Id as ParaId, Chain, DutyRoster, ParachainHost, OutgoingMessage};
 use parking_lot::Mutex;
 use substrate_client::error::Result as ClientResult;
@@ -158,6 +158,7 @@ struct ApiData {
 	duties: Vec<Chain>,
 	active_parachains: Vec<ParaId>,
 	ingress: HashMap<ParaId, Vec<(ParaId, Hash)>>,
+	collators: HashMap<ParaId, Vec<AccountId>>,
 }
 
 #[derive(Default, Clone)]
@@ -286,6 +287,26 @@ impl ParachainHost<Block> for RuntimeApi {
 		Ok(NativeOrEncoded::Native(Some(Vec::new())))
 	}
 
+	fn parachain_head_at_runtime_api_impl(
+		&self,
+		_at: &BlockId,
+		_: ExecutionContext,
+		_: Option<(ParaId, BlockNumber)>,
+		_: Vec<u8>,
+	) -> ClientResult<NativeOrEncoded<Option<Vec<u8>>>> {
+		Ok(NativeOrEncoded::Native(Some(Vec::new())))
+	}
+
+	fn parachain_head_history_runtime_api_impl(
+		&self,
+		_at: &BlockId,
+		_: ExecutionContext,
+		_: Option<(ParaId, u32)>,
+		_: Vec<u8>,
+	) -> ClientResult<NativeOrEncoded<Vec<(BlockNumber, Vec<u8>)>>> {
+		Ok(NativeOrEncoded::Native(Vec::new()))
+	}
+
 	fn ingress_runtime_api_impl(
 		&self,
 		_at: &BlockId,
@@ -296,6 +317,38 @@ impl ParachainHost<Block> for RuntimeApi {
 		let id = id.unwrap();
 		Ok(NativeOrEncoded::Native(self.data.lock().ingress.get(&id).cloned()))
 	}
+
+	fn head_storage_key_runtime_api_impl(
+		&self,
+		_at: &BlockId,
+		_: ExecutionContext,
+		id: Option<ParaId>,
+		_: Vec<u8>,
+	) -> ClientResult<NativeOrEncoded<Vec<u8>>> {
+		Ok(NativeOrEncoded::Native(::polkadot_primitives::proof::head_storage_key(id.unwrap())))
+	}
+
+	fn routing_storage_key_runtime_api_impl(
+		&self,
+		_at: &BlockId,
+		_: ExecutionContext,
+		ids: Option<(ParaId, ParaId)>,
+		_: Vec<u8>,
+	) -> ClientResult<NativeOrEncoded<Vec<u8>>> {
+		let (from, to) = ids.unwrap();
+		Ok(NativeOrEncoded::Native(::polkadot_primitives::proof::routing_storage_key(from, to)))
+	}
+
+	fn collators_runtime_api_impl(
+		&self,
+		_at: &BlockId,
+		_: ExecutionContext,
+		id: Option<ParaId>,
+		_: Vec<u8>,
+	) -> ClientResult<NativeOrEncoded<Option<Vec<AccountId>>>> {
+		let id = id.unwrap();
+		Ok(NativeOrEncoded::Native(self.data.lock().collators.get(&id).cloned()))
+	}
 }
 
 type TestValidationNetwork = ::validation::ValidationNetwork<
@@ -437,20 +490,24 @@ fn ingress_fetch_works() {
 				key_c.to_raw_public().into(),
 			],
 			ingress,
+			collators: HashMap::new(),
 		};
 
 		(
 			built.networks[0].communication_for(
 				make_table(&*api_handle, &key_a, parent_hash),
 				vec![MessagesFrom::from_messages(id_a, messages_from_a)],
+				Vec::new(),
 			),
 			built.networks[1].communication_for(
 				make_table(&*api_handle, &key_b, parent_hash),
 				vec![MessagesFrom::from_messages(id_b, messages_from_b)],
+				Vec::new(),
 			),
 			built.networks[2].communication_for(
 				make_table(&*api_handle, &key_c, parent_hash),
 				vec![MessagesFrom::from_messages(id_c, messages_from_c)],
+				Vec::new(),
 			),
 		)
 	};
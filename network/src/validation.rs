@@ -22,22 +22,26 @@
 use sr_primitives::traits::ProvideRuntimeApi;
 use substrate_network::{consensus_gossip::ConsensusMessage, Context as NetContext};
 use polkadot_validation::{Network as ParachainNetwork, SharedTable, Collators, Statement, GenericStatement};
-use polkadot_primitives::{AccountId, Block, Hash, SessionKey};
+use polkadot_primitives::{AccountId, Block, BlockId, Hash, SessionKey};
+use substrate_primitives::ed25519;
 use polkadot_primitives::parachain::{Id as ParaId, Collation, Extrinsic, ParachainHost, BlockData};
 use codec::Decode;
 
 use futures::prelude::*;
 use futures::future::Executor as FutureExecutor;
+use futures::stream::Select;
 use futures::sync::mpsc;
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use arrayvec::ArrayVec;
 use tokio::runtime::TaskExecutor;
 use parking_lot::Mutex;
 
-use router::Router;
+use router::{Router, DeferredStatements, DeferredEvictions};
+use statement_distribution::RateLimits;
 use super::PolkadotProtocol;
 
 /// An executor suitable for dispatching async consensus tasks.
@@ -113,11 +117,12 @@ impl NetworkService for super::NetworkService {
 }
 
 // task that processes all gossipped consensus messages,
-// checking signatures
+// checking signatures and limiting the rate of statements by signer.
 struct MessageProcessTask<P, E, N: NetworkService, T> {
-	inner_stream: mpsc::UnboundedReceiver<ConsensusMessage>,
+	inner_stream: Select<mpsc::UnboundedReceiver<ConsensusMessage>, mpsc::UnboundedReceiver<ConsensusMessage>>,
 	parent_hash: Hash,
 	table_router: Router<P, E, N, T>,
+	limits: RateLimits<SessionKey>,
 }
 
 impl<P, E, N, T> MessageProcessTask<P, E, N, T> where
@@ -127,7 +132,7 @@ impl<P, E, N, T> MessageProcessTask<P, E, N, T> where
 	N: NetworkService,
 	T: Clone + Executor + Send + 'static,
 {
-	fn process_message(&self, msg: ConsensusMessage) -> Option<Async<()>> {
+	fn process_message(&mut self, msg: ConsensusMessage) -> Option<Async<()>> {
 		use polkadot_validation::SignedStatement;
 
 		debug!(target: "validation", "Processing validation statement for live session");
@@ -138,7 +143,11 @@ impl<P, E, N, T> MessageProcessTask<P, E, N, T> where
 				statement.sender,
 				&self.parent_hash
 			) {
-				self.table_router.import_statement(statement);
+				if self.limits.allow(statement.sender, Instant::now()) {
+					self.table_router.import_statement(statement);
+				} else {
+					debug!(target: "validation", "Dropping statement by {:?}: exceeded statement rate limit", statement.sender);
+				}
 			}
 		}
 
@@ -170,6 +179,38 @@ impl<P, E, N, T> Future for MessageProcessTask<P, E, N, T> where
 	}
 }
 
+// the collator whitelists of permissioned parachains as of the given block,
+// or `None` if they could not be fetched.
+fn collator_whitelists<P>(api: &P, parent_hash: Hash) -> Option<HashMap<ParaId, Vec<AccountId>>> where
+	P: ProvideRuntimeApi,
+	P::Api: ParachainHost<Block>,
+{
+	let runtime_api = api.runtime_api();
+	let at = BlockId::hash(parent_hash);
+
+	let active_parachains = match runtime_api.active_parachains(&at) {
+		Ok(active_parachains) => active_parachains,
+		Err(e) => {
+			warn!(target: "validation", "Unable to fetch active parachains at {:?}: {:?}", parent_hash, e);
+			return None
+		}
+	};
+
+	let mut whitelists = HashMap::new();
+	for para_id in active_parachains {
+		match runtime_api.collators(&at, para_id) {
+			Ok(Some(whitelist)) => { whitelists.insert(para_id, whitelist); }
+			Ok(None) => {}
+			Err(e) => {
+				warn!(target: "validation", "Unable to fetch collators of {:?} at {:?}: {:?}", para_id, parent_hash, e);
+				return None
+			}
+		}
+	}
+
+	Some(whitelists)
+}
+
 /// Wrapper around the network service
 pub struct ValidationNetwork<P, E, N, T> {
 	network: Arc<N>,
@@ -210,20 +251,28 @@ impl<P, E, N, T> ParachainNetwork for ValidationNetwork<P, E, N, T> where
 		&self,
 		table: Arc<SharedTable>,
 		outgoing: polkadot_validation::Outgoing,
+		upcoming_authors: Vec<SessionKey>,
 	) -> Self::TableRouter {
 		let parent_hash = table.consensus_parent_hash().clone();
 
 		let knowledge = Arc::new(Mutex::new(Knowledge::new()));
 
-		let local_session_key = table.session_key();
+		let local_session_key = table.session_key_pair();
+		let collator_whitelists = collator_whitelists(&*self.api, parent_hash);
+
+		let (direct_tx, direct_rx) = mpsc::unbounded();
+		let validation_session = ValidationSession::new(knowledge.clone(), local_session_key, direct_tx);
+
 		let table_router = Router::new(
 			table,
 			self.network.clone(),
 			self.api.clone(),
 			self.executor.clone(),
 			parent_hash,
-			knowledge.clone(),
+			knowledge,
 			self.exit.clone(),
+			upcoming_authors,
+			validation_session.deferred_statements(),
 		);
 
 		table_router.broadcast_egress(outgoing);
@@ -233,19 +282,25 @@ impl<P, E, N, T> ParachainNetwork for ValidationNetwork<P, E, N, T> where
 		let table_router_clone = table_router.clone();
 		let executor = self.executor.clone();
 
-		// spin up a task in the background that processes all incoming statements
+		// spin up a task in the background that processes all incoming statements,
+		// gossiped or sent to us directly.
 		// TODO: propagate statements on a timer?
-		let inner_stream = self.network.gossip_messages_for(attestation_topic);
+		let inner_stream = self.network.gossip_messages_for(attestation_topic).select(direct_rx);
 		self.network
 			.with_spec(move |spec, ctx| {
-				spec.new_validation_session(ctx, parent_hash, ValidationSession {
-					knowledge,
-					local_session_key,
-				});
+				if let Some(collator_whitelists) = collator_whitelists {
+					spec.note_collator_whitelists(ctx, collator_whitelists);
+				}
+				spec.new_validation_session(
+					ctx,
+					parent_hash,
+					validation_session,
+				);
 				let process_task = MessageProcessTask {
 					inner_stream,
 					parent_hash,
 					table_router: table_router_clone,
+					limits: RateLimits::new(),
 				};
 
 				executor.spawn(process_task);
@@ -303,7 +358,11 @@ impl<P, E: Clone, N, T: Clone> Collators for ValidationNetwork<P, E, N, T> where
 
 
 	fn note_bad_collator(&self, collator: AccountId) {
-		self.network.with_spec(move |spec, ctx| spec.disconnect_bad_collator(ctx, collator));
+		self.network.with_spec(move |spec, ctx| spec.note_bad_collator(ctx, collator));
+	}
+
+	fn note_good_collator(&self, collator: AccountId) {
+		self.network.with_spec(move |spec, _| spec.note_good_collator(collator));
 	}
 }
 
@@ -363,17 +422,40 @@ impl Knowledge {
 pub(crate) struct ValidationSession {
 	knowledge: Arc<Mutex<Knowledge>>,
 	local_session_key: SessionKey,
+	// used to sign session key announcements to peers.
+	local_key_pair: Arc<ed25519::Pair>,
+	// encoded statements sent to us directly by peers.
+	statements: mpsc::UnboundedSender<ConsensusMessage>,
+	// statements about candidates not known yet, shared with the table router.
+	deferred_statements: Arc<Mutex<DeferredStatements>>,
 }
 
 impl ValidationSession {
-	#[cfg(test)]
-	pub(crate) fn new(knowledge: Arc<Mutex<Knowledge>>, local_session_key: SessionKey) -> Self {
+	pub(crate) fn new(
+		knowledge: Arc<Mutex<Knowledge>>,
+		local_key_pair: Arc<ed25519::Pair>,
+		statements: mpsc::UnboundedSender<ConsensusMessage>,
+	) -> Self {
 		ValidationSession {
 			knowledge,
-			local_session_key
+			local_session_key: local_key_pair.public().into(),
+			local_key_pair,
+			statements,
+			deferred_statements: Arc::new(Mutex::new(DeferredStatements::new())),
 		}
 	}
 
+	/// Statements about candidates not known yet, shared with the table router.
+	pub(crate) fn deferred_statements(&self) -> Arc<Mutex<DeferredStatements>> {
+		self.deferred_statements.clone()
+	}
+
+	/// Drop all statements still deferred, as the relay parent expired.
+	/// Returns the counters of statements rejected or evicted on it.
+	pub(crate) fn expire_deferred(&self) -> DeferredEvictions {
+		self.deferred_statements.lock().expire()
+	}
+
 	// execute a closure with locally stored block data for a candidate, or a slice of session identities
 	// we believe should have the data.
 	fn with_block_data<F, U>(&self, hash: &Hash, f: F) -> U
@@ -452,15 +534,15 @@ impl LiveValidationSessions {
 	}
 
 	/// Note new validation session. If the used session key is new,
-	/// it returns it to be broadcasted to peers.
+	/// it returns its key pair so the key can be announced to peers.
 	pub(crate) fn new_validation_session(
 		&mut self,
 		parent_hash: Hash,
 		session: ValidationSession,
-	) -> Option<SessionKey> {
+	) -> Option<Arc<ed25519::Pair>> {
 		let inserted_key = self.recent.insert(session.local_session_key);
 		let maybe_new = if let InsertedRecentKey::New(_) = inserted_key {
-			Some(session.local_session_key)
+			Some(session.local_key_pair.clone())
 		} else {
 			None
 		};
@@ -470,16 +552,17 @@ impl LiveValidationSessions {
 		maybe_new
 	}
 
-	/// Remove validation session.
-	pub(crate) fn remove(&mut self, parent_hash: &Hash) {
-		if let Some(validation_session) = self.live_instances.remove(parent_hash) {
-			let key_still_used = self.live_instances.values()
-				.any(|c| c.local_session_key == validation_session.local_session_key);
+	/// Remove validation session, returning it if it was live.
+	pub(crate) fn remove(&mut self, parent_hash: &Hash) -> Option<ValidationSession> {
+		let validation_session = self.live_instances.remove(parent_hash)?;
+		let key_still_used = self.live_instances.values()
+			.any(|c| c.local_session_key == validation_session.local_session_key);
 
-			if !key_still_used {
-				self.recent.remove(&validation_session.local_session_key)
-			}
+		if !key_still_used {
+			self.recent.remove(&validation_session.local_session_key)
 		}
+
+		Some(validation_session)
 	}
 
 	/// Recent session keys as a slice.
@@ -487,6 +570,23 @@ impl LiveValidationSessions {
 		self.recent.as_slice()
 	}
 
+	/// Get the key pair for one of the recent local session keys, if any live
+	/// validation session still uses it.
+	pub(crate) fn key_pair(&self, key: &SessionKey) -> Option<Arc<ed25519::Pair>> {
+		self.live_instances.values()
+			.find(|c| &c.local_session_key == key)
+			.map(|c| c.local_key_pair.clone())
+	}
+
+	/// Pass an encoded statement received directly from a peer to the validation
+	/// session at parent hash. Returns `false` if there is no such live session.
+	pub(crate) fn forward_statement(&self, parent_hash: &Hash, statement: ConsensusMessage) -> bool {
+		match self.live_instances.get(parent_hash) {
+			Some(c) => c.statements.unbounded_send(statement).is_ok(),
+			None => false,
+		}
+	}
+
 	/// Call a closure with block data from validation session at parent hash.
 	///
 	/// This calls the closure with `Some(data)` where the session and data are live,
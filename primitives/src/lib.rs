@@ -41,6 +41,9 @@ extern crate serde_derive;
 #[cfg(feature = "std")]
 extern crate serde;
 
+#[cfg(feature = "std")]
+extern crate substrate_state_machine as state_machine;
+
 #[macro_use]
 extern crate substrate_client;
 
@@ -50,6 +53,8 @@ use runtime_primitives::{generic, traits::Extrinsic};
 pub use runtime_primitives::traits::{BlakeTwo256, Hash as HashT};
 
 pub mod parachain;
+#[cfg(feature = "std")]
+pub mod proof;
 
 pub use codec::Compact;
 
@@ -64,6 +69,13 @@ pub type BlockNumber = u64;
 /// Alias to Ed25519 pubkey that identifies an account on the relay chain.
 pub type AccountId = primitives::hash::H256;
 
+/// The account controlled by an Ed25519 key pair.
+#[cfg(feature = "std")]
+pub fn key_to_account_id(key: &primitives::ed25519::Pair) -> AccountId {
+	let pubkey_bytes: [u8; 32] = key.public().into();
+	pubkey_bytes.into()
+}
+
 /// The type for looking up accounts. We don't expect more than 4 billion of them, but you
 /// never know...
 pub type AccountIndex = u32;
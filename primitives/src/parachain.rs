@@ -18,7 +18,7 @@
 
 use rstd::prelude::*;
 use rstd::cmp::Ordering;
-use super::{Hash, SessionKey};
+use super::{Hash, SessionKey, BlockNumber};
 
 use {AccountId};
 
@@ -27,6 +27,30 @@ use primitives::bytes;
 
 pub use polkadot_parachain::Id;
 
+/// Maximum size in bytes of a single message posted from one parachain to another.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Maximum number of messages a parachain candidate may post to a single other parachain.
+pub const MAX_MESSAGES_PER_ROUTE: usize = 256;
+
+/// Maximum number of unprocessed egress queue roots which may be queued from
+/// one parachain to another. Candidates routing to a full queue are rejected.
+pub const MAX_QUEUED_ROOTS: usize = 16;
+
+/// Fee charged to the sending parachain for each message posted.
+pub const MESSAGE_FEE: u64 = 10;
+
+/// Fee charged to the sending parachain for each byte of message data posted.
+pub const MESSAGE_BYTE_FEE: u64 = 1;
+
+/// Compute the fees owed by a parachain for posting the given messages.
+pub fn message_fees<'a, I: IntoIterator<Item=&'a [u8]>>(messages: I) -> u64 {
+	messages.into_iter().fold(0u64, |fees, data| fees
+		.saturating_add(MESSAGE_FEE)
+		.saturating_add(MESSAGE_BYTE_FEE.saturating_mul(data.len() as u64))
+	)
+}
+
 /// Signature on candidate's block data by a collator.
 pub type CandidateSignature = ::runtime_primitives::Ed25519Signature;
 
@@ -206,7 +230,7 @@ pub struct ValidationCode(#[cfg_attr(feature = "std", serde(with="bytes"))] pub
 pub struct Activity(#[cfg_attr(feature = "std", serde(with="bytes"))] pub Vec<u8>);
 
 /// Statements which can be made about parachain candidates.
-#[derive(Clone, PartialEq, Eq, Encode)]
+#[derive(Clone, PartialEq, Eq, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum Statement {
 	/// Proposal of a parachain candidate.
@@ -257,6 +281,41 @@ impl AttestedCandidate {
 	}
 }
 
+/// Proof of misbehavior by a validator during parachain attestation.
+///
+/// All statements are signed over the encoded statement concatenated with
+/// the relay parent hash of the report.
+#[derive(Clone, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum MisbehaviorProof {
+	/// Voted more than one way on the validity of the same candidate.
+	/// Either by issuing the candidate and then voting on its validity, or
+	/// by voting both valid and invalid.
+	#[codec(index = "1")]
+	ValidityDoubleVote(Statement, CandidateSignature, Statement, CandidateSignature),
+	/// Issued two different candidates.
+	#[codec(index = "2")]
+	MultipleCandidates(CandidateReceipt, CandidateSignature, CandidateReceipt, CandidateSignature),
+	/// Issued a candidate for a parachain the validator was not assigned to.
+	#[codec(index = "3")]
+	UnauthorizedStatement(Statement, CandidateSignature),
+	/// Produced two different signatures on the same statement.
+	#[codec(index = "4")]
+	DoubleSign(Statement, CandidateSignature, CandidateSignature),
+}
+
+/// A report of validator misbehavior, to be submitted to the relay chain.
+#[derive(Clone, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct MisbehaviorReport {
+	/// The relay parent the misbehavior's statements were localized to.
+	pub parent_hash: Hash,
+	/// The session key of the misbehaving validator.
+	pub offender: SessionKey,
+	/// Proof of the misbehavior.
+	pub proof: MisbehaviorProof,
+}
+
 decl_runtime_apis! {
 	/// The API for querying the state of parachains on-chain.
 	pub trait ParachainHost {
@@ -270,9 +329,25 @@ decl_runtime_apis! {
 		fn parachain_head(id: Id) -> Option<Vec<u8>>;
 		/// Get the given parachain's head code blob.
 		fn parachain_code(id: Id) -> Option<Vec<u8>>;
-		/// Get the ingress roots to a specific parachain at a
-		/// block.
+		/// Get the given parachain's head data blob as of the given relay chain
+		/// block number, if still within the retained head history.
+		fn parachain_head_at(id: Id, relay_number: BlockNumber) -> Option<Vec<u8>>;
+		/// Get up to `count` of the given parachain's most recent head data blobs,
+		/// newest first, along with the relay chain block number at which each
+		/// was included.
+		fn parachain_head_history(id: Id, count: u32) -> Vec<(BlockNumber, Vec<u8>)>;
+		/// Get the unprocessed ingress roots to a specific parachain at a
+		/// block, ordered by sending parachain and then from oldest to newest.
 		fn ingress(to: Id) -> Option<Vec<(Id, Hash)>>;
+		/// Get the storage key of the given parachain's head, for proving it to
+		/// clients which hold only relay chain headers.
+		fn head_storage_key(id: Id) -> Vec<u8>;
+		/// Get the storage key of the message roots queued from one parachain to
+		/// another, for proving them to clients which hold only relay chain headers.
+		fn routing_storage_key(from: Id, to: Id) -> Vec<u8>;
+		/// Get the accounts allowed to collate on the given parachain, or `None`
+		/// if anyone may collate on it.
+		fn collators(id: Id) -> Option<Vec<AccountId>>;
 	}
 }
 
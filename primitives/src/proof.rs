@@ -0,0 +1,137 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verification of relay chain storage proofs of parachain state.
+//!
+//! These allow parachain nodes and their light clients to check what the relay chain
+//! recorded for a parachain given only a relay chain header, without a full client.
+
+use codec::{Decode, Encode};
+use primitives::{Blake2Hasher, hashing::twox_128};
+use state_machine::read_proof_check;
+
+use parachain::Id;
+use {BlockNumber, Hash, Header};
+
+/// An error verifying a storage proof.
+#[derive(Debug, PartialEq)]
+pub enum ProofError {
+	/// The proof does not contain the storage entry under the header's state root.
+	InvalidProof,
+	/// The proven storage entry could not be decoded.
+	BadValue,
+}
+
+/// The relay chain storage key of a parachain's head.
+pub fn head_storage_key(id: Id) -> Vec<u8> {
+	let mut key = b"Parachains Heads".to_vec();
+	id.encode_to(&mut key);
+	twox_128(&key).to_vec()
+}
+
+/// The relay chain storage key of the message roots queued from one parachain to another.
+pub fn routing_storage_key(from: Id, to: Id) -> Vec<u8> {
+	let mut key = b"Parachains RoutingQueue".to_vec();
+	(from, to).encode_to(&mut key);
+	twox_128(&key).to_vec()
+}
+
+/// Verify a proof of a parachain's head against a relay chain header.
+///
+/// Returns the head data recorded at that block, or `None` if the parachain
+/// had no head.
+pub fn verify_head_proof(header: &Header, id: Id, proof: Vec<Vec<u8>>) -> Result<Option<Vec<u8>>, ProofError> {
+	match check(header, &head_storage_key(id), proof)? {
+		Some(encoded) => Vec::<u8>::decode(&mut &encoded[..]).map(Some).ok_or(ProofError::BadValue),
+		None => Ok(None),
+	}
+}
+
+/// Verify a proof of the message roots queued from one parachain to another against
+/// a relay chain header.
+///
+/// Returns the queued roots, oldest first, along with the relay chain block number
+/// at which each was routed.
+pub fn verify_routing_proof(
+	header: &Header,
+	from: Id,
+	to: Id,
+	proof: Vec<Vec<u8>>,
+) -> Result<Vec<(BlockNumber, Hash)>, ProofError> {
+	match check(header, &routing_storage_key(from, to), proof)? {
+		Some(encoded) => Decode::decode(&mut &encoded[..]).ok_or(ProofError::BadValue),
+		None => Ok(Vec::new()),
+	}
+}
+
+fn check(header: &Header, key: &[u8], proof: Vec<Vec<u8>>) -> Result<Option<Vec<u8>>, ProofError> {
+	read_proof_check::<Blake2Hasher>(header.state_root, proof, key)
+		.map_err(|_| ProofError::InvalidProof)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+	use state_machine::{prove_read, backend::{Backend, InMemory}};
+
+	fn prove(storage: HashMap<Vec<u8>, Vec<u8>>, key: &[u8]) -> (Header, Vec<Vec<u8>>) {
+		let backend = InMemory::<Blake2Hasher>::from(storage);
+		let state_root = backend.storage_root(::std::iter::empty()).0;
+		let proof = prove_read(backend, key).unwrap().1;
+		let header = Header {
+			parent_hash: Default::default(),
+			number: 1,
+			state_root,
+			extrinsics_root: Default::default(),
+			digest: Default::default(),
+		};
+
+		(header, proof)
+	}
+
+	#[test]
+	fn head_proof_works() {
+		let id = Id::from(5);
+		let mut storage = HashMap::new();
+		storage.insert(head_storage_key(id), vec![1u8, 2, 3].encode());
+		storage.insert(head_storage_key(Id::from(6)), vec![4u8, 5, 6].encode());
+
+		let (header, proof) = prove(storage, &head_storage_key(id));
+		assert_eq!(verify_head_proof(&header, id, proof.clone()), Ok(Some(vec![1, 2, 3])));
+
+		// the proof is only valid under the header it was made for.
+		let mut other = header.clone();
+		other.state_root = [1; 32].into();
+		assert_eq!(verify_head_proof(&other, id, proof), Err(ProofError::InvalidProof));
+	}
+
+	#[test]
+	fn routing_proof_works() {
+		let (from, to) = (Id::from(5), Id::from(6));
+		let queue: Vec<(BlockNumber, Hash)> = vec![(1, [1; 32].into()), (2, [2; 32].into())];
+		let mut storage = HashMap::new();
+		storage.insert(routing_storage_key(from, to), queue.encode());
+		storage.insert(head_storage_key(from), vec![1u8, 2, 3].encode());
+
+		let (header, proof) = prove(storage.clone(), &routing_storage_key(from, to));
+		assert_eq!(verify_routing_proof(&header, from, to, proof), Ok(queue));
+
+		// an empty queue is proven by the absence of the entry.
+		let (header, proof) = prove(storage, &routing_storage_key(to, from));
+		assert_eq!(verify_routing_proof(&header, to, from, proof), Ok(Vec::new()));
+	}
+}
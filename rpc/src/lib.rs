@@ -0,0 +1,74 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Polkadot-specific RPC API.
+//!
+//! Substrate's RPC servers only serve the substrate API, so these methods are
+//! served on a separate endpoint.
+
+#![warn(missing_docs)]
+
+extern crate jsonrpc_core;
+#[macro_use]
+extern crate jsonrpc_derive;
+extern crate jsonrpc_http_server as http;
+extern crate polkadot_primitives;
+extern crate serde;
+extern crate sr_primitives;
+extern crate substrate_client as client;
+extern crate substrate_primitives as primitives;
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod parachain;
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use client::ChainHead;
+use polkadot_primitives::Block;
+use polkadot_primitives::parachain::ParachainHost;
+use sr_primitives::traits::ProvideRuntimeApi;
+
+pub use http::Server as HttpServer;
+pub use parachain::ProvideStorageProof;
+pub use jsonrpc_core::IoHandler;
+
+/// Create a handler for all Polkadot-specific RPC methods, backed by the given client.
+pub fn rpc_handler<P>(client: Arc<P>) -> IoHandler where
+	P: ProvideRuntimeApi + ProvideStorageProof + ChainHead<Block> + Send + Sync + 'static,
+	P::Api: ParachainHost<Block>,
+{
+	use parachain::ParachainApi;
+
+	let mut io = IoHandler::new();
+	io.extend_with(parachain::Parachain::new(client).to_delegate());
+	io
+}
+
+/// Start an HTTP server for the given RPC handler.
+pub fn start_http(addr: &SocketAddr, io: IoHandler) -> io::Result<HttpServer> {
+	http::ServerBuilder::new(io)
+		.threads(1)
+		.rest_api(http::RestApi::Unsecure)
+		.cors(http::DomainsValidation::Disabled)
+		.start_http(addr)
+}
@@ -0,0 +1,145 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Parachain RPC API.
+
+use std::sync::Arc;
+
+use client::{self, ChainHead};
+use jsonrpc_core::{Error, Result};
+use polkadot_primitives::{Block, BlockId, BlockNumber, Hash};
+use polkadot_primitives::parachain::{Id as ParaId, ParachainHost};
+use primitives::{Blake2Hasher, Bytes};
+use sr_primitives::traits::{ProvideRuntimeApi, Header as HeaderT};
+
+/// Maximum number of heads returned by a single history query.
+const MAX_HEAD_HISTORY: u32 = 256;
+
+/// A parachain head along with the relay chain block at which it was included.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadRecord {
+	/// The relay chain block number at which the head was included.
+	pub relay_number: BlockNumber,
+	/// The head data.
+	pub head_data: Bytes,
+}
+
+/// A proof of a relay chain storage entry, which can be checked against the
+/// state root of the header of the block it was made at.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+	/// The hash of the relay chain block the proof was made at.
+	pub at: Hash,
+	/// The trie nodes making up the proof.
+	pub proof: Vec<Bytes>,
+}
+
+/// Something which can prove the values of storage entries at a block.
+pub trait ProvideStorageProof {
+	/// Prove the value of the storage entry under the given key at a block.
+	fn storage_proof(&self, at: &BlockId, key: &[u8]) -> client::error::Result<Vec<Vec<u8>>>;
+}
+
+impl<B, E, RA> ProvideStorageProof for client::Client<B, E, Block, RA> where
+	B: client::backend::Backend<Block, Blake2Hasher>,
+	E: client::CallExecutor<Block, Blake2Hasher>,
+{
+	fn storage_proof(&self, at: &BlockId, key: &[u8]) -> client::error::Result<Vec<Vec<u8>>> {
+		self.read_proof(at, key)
+	}
+}
+
+/// Parachain RPC API.
+#[rpc]
+pub trait ParachainApi {
+	/// Get up to `count` of the most recent heads of a parachain as of the best
+	/// block, newest first.
+	#[rpc(name = "parachain_headHistory")]
+	fn head_history(&self, id: ParaId, count: u32) -> Result<Vec<HeadRecord>>;
+
+	/// Prove the head of a parachain as recorded at the given relay chain block,
+	/// or the best block if none is given.
+	#[rpc(name = "parachain_headProof")]
+	fn head_proof(&self, id: ParaId, at: Option<Hash>) -> Result<StorageProof>;
+
+	/// Prove the message roots queued from one parachain to another as of the given
+	/// relay chain block, or the best block if none is given.
+	#[rpc(name = "parachain_routingProof")]
+	fn routing_proof(&self, from: ParaId, to: ParaId, at: Option<Hash>) -> Result<StorageProof>;
+}
+
+/// Parachain RPC API implementation, backed by a client.
+pub struct Parachain<P> {
+	client: Arc<P>,
+}
+
+impl<P> Parachain<P> {
+	/// Create a new parachain RPC API handler.
+	pub fn new(client: Arc<P>) -> Self {
+		Parachain { client }
+	}
+}
+
+impl<P> Parachain<P> where
+	P: ProvideRuntimeApi + ProvideStorageProof + ChainHead<Block>,
+	P::Api: ParachainHost<Block>,
+{
+	fn prove<F>(&self, at: Option<Hash>, storage_key: F) -> Result<StorageProof> where
+		F: FnOnce(&P::Api, &BlockId) -> client::error::Result<Vec<u8>>,
+	{
+		let at = match at {
+			Some(at) => at,
+			None => self.client.best_block_header().map_err(client_error)?.hash(),
+		};
+		let block = BlockId::hash(at);
+		let key = storage_key(&*self.client.runtime_api(), &block).map_err(client_error)?;
+		let proof = self.client.storage_proof(&block, &key).map_err(client_error)?;
+
+		Ok(StorageProof { at, proof: proof.into_iter().map(Into::into).collect() })
+	}
+}
+
+impl<P> ParachainApi for Parachain<P> where
+	P: ProvideRuntimeApi + ProvideStorageProof + ChainHead<Block> + Send + Sync + 'static,
+	P::Api: ParachainHost<Block>,
+{
+	fn head_history(&self, id: ParaId, count: u32) -> Result<Vec<HeadRecord>> {
+		let best = self.client.best_block_header().map_err(client_error)?.hash();
+		let history = self.client.runtime_api()
+			.parachain_head_history(&BlockId::hash(best), id, count.min(MAX_HEAD_HISTORY))
+			.map_err(client_error)?;
+
+		Ok(history.into_iter()
+			.map(|(relay_number, head_data)| HeadRecord { relay_number, head_data: head_data.into() })
+			.collect())
+	}
+
+	fn head_proof(&self, id: ParaId, at: Option<Hash>) -> Result<StorageProof> {
+		self.prove(at, |api, block| api.head_storage_key(block, id))
+	}
+
+	fn routing_proof(&self, from: ParaId, to: ParaId, at: Option<Hash>) -> Result<StorageProof> {
+		self.prove(at, |api, block| api.routing_storage_key(block, from, to))
+	}
+}
+
+fn client_error(e: client::error::Error) -> Error {
+	warn!(target: "rpc", "Unable to serve parachain RPC request: {:?}", e);
+	Error::internal_error()
+}
@@ -17,12 +17,13 @@
 //! Module to process claims from Ethereum addresses.
 
 use rstd::prelude::*;
+use rstd::cmp;
 use sr_io::{keccak_256, secp256k1_ecdsa_recover};
-use srml_support::{StorageValue, StorageMap};
-use system::ensure_signed;
+use srml_support::{StorageValue, StorageMap, dispatch::Result};
+use system::{ensure_signed, ensure_root, ensure_inherent};
 use codec::Encode;
-#[cfg(feature = "std")]
-use sr_primitives::traits::Zero;
+use sr_primitives::traits::{Zero, As, CheckedAdd, CheckedMul};
+use sr_primitives::{ApplyError, transaction_validity::{TransactionValidity, TransactionLongevity, TransactionPriority}};
 use balances;
 
 /// Configuration trait.
@@ -33,6 +34,9 @@ pub trait Trait: balances::Trait {
 
 type EthereumAddress = [u8; 20];
 
+/// Priority of unsigned claim transactions in the transaction pool.
+const CLAIM_PRIORITY: TransactionPriority = 1 << 20;
+
 // This is a bit of a workaround until codec supports [u8; 65] directly.
 #[derive(Encode, Decode, Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -55,6 +59,47 @@ impl EcdsaSignature {
 	}
 }
 
+/// An Ethereum signature over the claim, along with the scheme it was made with.
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum EthereumSignature {
+	/// Signed by the Ethereum RPC's `personal_sign` or `eth_sign`.
+	PersonalSign(EcdsaSignature),
+	/// Signed as EIP-712 typed structured data by the Ethereum RPC's `eth_signTypedData`.
+	TypedData(EcdsaSignature),
+}
+
+/// A linear vesting schedule for claimed balance which is still locked.
+///
+/// The balances module cannot lock part of an account's balance, so the locked
+/// balance is not credited with the rest of the claim. It is withheld instead and
+/// minted to the account by `vest` as it is released.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct VestingSchedule<Balance, BlockNumber> {
+	/// The balance which is still locked.
+	pub locked: Balance,
+	/// The balance released per block.
+	pub per_block: Balance,
+	/// The block from which the locked balance starts to be released.
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance: As<u64> + Zero + Ord + CheckedMul + Copy, BlockNumber: As<u64> + Ord + Copy> VestingSchedule<Balance, BlockNumber> {
+	/// The amount of the locked balance which has been released by block `n`.
+	pub fn unlocked_at(&self, n: BlockNumber) -> Balance {
+		if n <= self.starting_block {
+			return Zero::zero();
+		}
+
+		let elapsed = Balance::sa(n.as_() - self.starting_block.as_());
+		match self.per_block.checked_mul(&elapsed) {
+			Some(unlocked) => cmp::min(unlocked, self.locked),
+			None => self.locked,
+		}
+	}
+}
+
 /// An event in this module.
 decl_event!(
 	pub enum Event<T> where
@@ -63,6 +108,8 @@ decl_event!(
 	{
 		/// Someone claimed some DOTs.
 		Claimed(A, EthereumAddress, B),
+		/// Someone's vested DOTs were released from their claim's lock.
+		Vested(A, B),
 	}
 );
 
@@ -77,6 +124,16 @@ decl_storage! {
 		Total get(total) build(|config: &GenesisConfig<T>| {
 			config.claims.iter().fold(Zero::zero(), |acc: T::Balance, &(_, n)| acc + n)
 		}): T::Balance;
+		/// Vesting schedules of unclaimed claims: the amount of the claim which is locked,
+		/// the amount released per block and the block from which it starts to be released.
+		ClaimVesting get(claim_vesting) config(vesting):
+			map EthereumAddress => Option<(T::Balance, T::Balance, T::BlockNumber)>;
+		/// Whether an unclaimed claim must be signed together with the statement.
+		Signing get(signing) config(): map EthereumAddress => bool;
+		/// Hash of the statement text which claimants must sign, if any.
+		Statement get(statement): Option<T::Hash>;
+		/// The claimed balance of each account which is still locked.
+		Vesting get(vesting): map T::AccountId => Option<VestingSchedule<T::Balance, T::BlockNumber>>;
 	}
 	add_extra_genesis {
 		config(claims): Vec<(EthereumAddress, T::Balance)>;
@@ -99,10 +156,40 @@ fn ethereum_signable_message(what: &[u8]) -> Vec<u8> {
 	v
 }
 
+// The EIP-712 domain claims are signed in. There is no `chainId` as claims are not
+// made on any Ethereum chain.
+const EIP712_DOMAIN_TYPE: &[u8] = b"EIP712Domain(string name,string version)";
+const EIP712_DOMAIN_NAME: &[u8] = b"Polkadot Claims";
+const EIP712_DOMAIN_VERSION: &[u8] = b"1";
+
+// The EIP-712 type of a claim. The payload is the same data which would be signed
+// by `personal_sign`: the encoded account, followed by the statement if required.
+const EIP712_CLAIM_TYPE: &[u8] = b"Claim(bytes payload)";
+
+// Constructs the message whose hash `eth_signTypedData` would sign for a claim.
+fn eip712_signable_message(what: &[u8]) -> Vec<u8> {
+	let mut domain = Vec::with_capacity(96);
+	domain.extend_from_slice(&keccak_256(EIP712_DOMAIN_TYPE));
+	domain.extend_from_slice(&keccak_256(EIP712_DOMAIN_NAME));
+	domain.extend_from_slice(&keccak_256(EIP712_DOMAIN_VERSION));
+
+	let mut claim = Vec::with_capacity(64);
+	claim.extend_from_slice(&keccak_256(EIP712_CLAIM_TYPE));
+	claim.extend_from_slice(&keccak_256(what));
+
+	let mut v = b"\x19\x01".to_vec();
+	v.extend_from_slice(&keccak_256(&domain));
+	v.extend_from_slice(&keccak_256(&claim));
+	v
+}
+
 // Attempts to recover the Ethereum address from a message signature signed by using
-// the Ethereum RPC's `personal_sign` and `eth_sign`.
-fn eth_recover(s: &EcdsaSignature, what: &[u8]) -> Option<EthereumAddress> {
-	let msg = keccak_256(&ethereum_signable_message(what));
+// the Ethereum RPC's `personal_sign` and `eth_sign`, or `eth_signTypedData`.
+fn eth_recover(s: &EthereumSignature, what: &[u8]) -> Option<EthereumAddress> {
+	let (s, msg) = match *s {
+		EthereumSignature::PersonalSign(ref s) => (s, keccak_256(&ethereum_signable_message(what))),
+		EthereumSignature::TypedData(ref s) => (s, keccak_256(&eip712_signable_message(what))),
+	};
 	let mut res = EthereumAddress::default();
 	res.copy_from_slice(&keccak_256(&secp256k1_ecdsa_recover(&s.to_blob(), &msg).ok()?[..])[12..]);
 	Some(res)
@@ -114,31 +201,168 @@ decl_module! {
 		fn deposit_event<T>() = default;
 
 		/// Make a claim.
-		fn claim(origin, ethereum_signature: EcdsaSignature) {
+		fn claim(origin, ethereum_signature: EthereumSignature) {
 			// This is a public call, so we ensure that the origin is some signed account.
 			let sender = ensure_signed(origin)?;
-			
-			let signer = sender.using_encoded(|data|
-					eth_recover(&ethereum_signature, data)
-				).ok_or("Invalid Ethereum signature")?;
-			
-			let balance_due = <Claims<T>>::take(&signer)
+
+			let signer = Self::claimant(&sender.encode(), &ethereum_signature)
+				.ok_or("Ethereum address has no claim")?;
+
+			Self::process_claim(signer, sender)?;
+		}
+
+		/// Make a claim to a destination account.
+		///
+		/// This is submitted unsigned, so that accounts without any balance can
+		/// claim. The Ethereum signature over the destination account is checked
+		/// in the transaction pool before the claim can be included.
+		fn claim_unsigned(origin, dest: T::AccountId, ethereum_signature: EthereumSignature) {
+			ensure_inherent(origin)?;
+
+			let signer = Self::claimant(&dest.encode(), &ethereum_signature)
 				.ok_or("Ethereum address has no claim")?;
-			
-			<Total<T>>::mutate(|t| if *t < balance_due {
-				panic!("Logic error: Pot less than the total of claims!")
+
+			Self::process_claim(signer, dest)?;
+		}
+
+		/// Release the vested part of the sender's claimed balance.
+		fn vest(origin) {
+			let sender = ensure_signed(origin)?;
+			let schedule = Self::vesting(&sender).ok_or("Account has no vesting schedule")?;
+
+			let now = <system::Module<T>>::block_number();
+			let unlocked = schedule.unlocked_at(now);
+			if unlocked.is_zero() {
+				return Ok(());
+			}
+
+			if unlocked == schedule.locked {
+				<Vesting<T>>::remove(&sender);
 			} else {
-				*t -= balance_due
-			});
+				<Vesting<T>>::insert(&sender, VestingSchedule {
+					locked: schedule.locked - unlocked,
+					per_block: schedule.per_block,
+					starting_block: now,
+				});
+			}
+
+			<balances::Module<T>>::increase_free_balance_creating(&sender, unlocked);
+			Self::deposit_event(RawEvent::Vested(sender, unlocked));
+		}
 
-			<balances::Module<T>>::increase_free_balance_creating(&sender, balance_due);
+		/// Add a new claim. Must be called by the root origin.
+		///
+		/// Optionally, part of the claimed balance is locked and released linearly per
+		/// block from a starting block, given as `(locked, per_block, starting_block)`.
+		/// If `signing` is set, the claimant must sign the statement along with their account.
+		fn mint_claim(
+			origin,
+			who: EthereumAddress,
+			value: T::Balance,
+			vesting: Option<(T::Balance, T::Balance, T::BlockNumber)>,
+			signing: bool
+		) {
+			ensure_root(origin)?;
+			ensure!(!<Claims<T>>::exists(&who), "Ethereum address already has a claim");
+			let total = Self::total().checked_add(&value).ok_or("Total of claims overflows")?;
 
-			// Let's deposit an event to let the outside world know this happened.
-			Self::deposit_event(RawEvent::Claimed(sender, signer, balance_due));
+			<Total<T>>::put(total);
+			<Claims<T>>::insert(&who, value);
+			if let Some(vesting) = vesting {
+				<ClaimVesting<T>>::insert(&who, vesting);
+			}
+			if signing {
+				<Signing<T>>::insert(&who, true);
+			}
+		}
+
+		/// Set the hash of the statement text claimants must sign. Must be called by
+		/// the root origin.
+		fn set_statement(origin, statement: T::Hash) {
+			ensure_root(origin)?;
+			<Statement<T>>::put(statement);
 		}
 	}
 }
 
+impl<T: Trait> Module<T> {
+	/// Check the validity of an unsigned claim for inclusion in the transaction pool.
+	///
+	/// Each claim may only be made once, so the claiming Ethereum address is
+	/// provided as a tag to keep other claims of it out of the pool.
+	pub fn validate_claim(dest: &T::AccountId, signature: &EthereumSignature) -> TransactionValidity {
+		let signer = match Self::claimant(&dest.encode(), signature) {
+			Some(signer) => signer,
+			None => return TransactionValidity::Invalid(ApplyError::BadSignature as i8),
+		};
+
+		if <ClaimVesting<T>>::exists(&signer) && <Vesting<T>>::exists(dest) {
+			return TransactionValidity::Invalid(ApplyError::CantPay as i8);
+		}
+
+		TransactionValidity::Valid {
+			priority: CLAIM_PRIORITY,
+			requires: Vec::new(),
+			provides: vec![(b"claims", signer).encode()],
+			longevity: TransactionLongevity::max_value(),
+		}
+	}
+
+	// Find the claimant who signed the given data, along with the statement
+	// if their claim requires it.
+	fn claimant(data: &[u8], signature: &EthereumSignature) -> Option<EthereumAddress> {
+		if let Some(statement) = Self::statement() {
+			let mut with_statement = data.to_vec();
+			with_statement.extend_from_slice(statement.as_ref());
+
+			let signer = eth_recover(signature, &with_statement)
+				.filter(|signer| <Claims<T>>::exists(signer) && Self::signing(signer));
+			if signer.is_some() {
+				return signer;
+			}
+		}
+
+		eth_recover(signature, data)
+			.filter(|signer| <Claims<T>>::exists(signer) && !Self::signing(signer))
+	}
+
+	// Pay out the claim of `signer` to `dest`, withholding any vesting part of it
+	// until it is released.
+	fn process_claim(signer: EthereumAddress, dest: T::AccountId) -> Result {
+		let balance_due = Self::claims(&signer).ok_or("Ethereum address has no claim")?;
+		let vesting = Self::claim_vesting(&signer);
+		ensure!(
+			vesting.is_none() || !<Vesting<T>>::exists(&dest),
+			"Destination account already has a vesting schedule"
+		);
+
+		<Claims<T>>::remove(&signer);
+		<ClaimVesting<T>>::remove(&signer);
+		<Signing<T>>::remove(&signer);
+		<Total<T>>::mutate(|t| if *t < balance_due {
+			panic!("Logic error: Pot less than the total of claims!")
+		} else {
+			*t -= balance_due
+		});
+
+		let locked = match vesting {
+			Some((locked, per_block, starting_block)) => {
+				let locked = cmp::min(locked, balance_due);
+				<Vesting<T>>::insert(&dest, VestingSchedule { locked, per_block, starting_block });
+				locked
+			}
+			None => Zero::zero(),
+		};
+
+		<balances::Module<T>>::increase_free_balance_creating(&dest, balance_due - locked);
+
+		// Let's deposit an event to let the outside world know this happened.
+		Self::deposit_event(RawEvent::Claimed(dest, signer, balance_due));
+
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use secp256k1;
@@ -201,20 +425,32 @@ mod tests {
 		res.copy_from_slice(&keccak256(&alice_public().serialize()[1..65])[12..]);
 		res
 	}
-	fn alice_sig(what: &[u8]) -> EcdsaSignature {
+	fn alice_sig(what: &[u8]) -> EthereumSignature {
 		let msg = keccak256(&ethereum_signable_message(what));
 		let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), &alice_secret()).unwrap();
 		let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
-		EcdsaSignature(sig.0, sig.1, recovery_id.serialize() as i8)
+		EthereumSignature::PersonalSign(EcdsaSignature(sig.0, sig.1, recovery_id.serialize() as i8))
+	}
+	fn alice_typed_sig(what: &[u8]) -> EthereumSignature {
+		let msg = keccak256(&eip712_signable_message(what));
+		let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), &alice_secret()).unwrap();
+		let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
+		EthereumSignature::TypedData(EcdsaSignature(sig.0, sig.1, recovery_id.serialize() as i8))
 	}
 	fn bob_secret() -> secp256k1::SecretKey {
 		secp256k1::SecretKey::parse(&keccak256(b"Bob")).unwrap()
 	}
-	fn bob_sig(what: &[u8]) -> EcdsaSignature {
+	fn bob_eth() -> EthereumAddress {
+		let mut res = EthereumAddress::default();
+		let public = secp256k1::PublicKey::from_secret_key(&bob_secret());
+		res.copy_from_slice(&keccak256(&public.serialize()[1..65])[12..]);
+		res
+	}
+	fn bob_sig(what: &[u8]) -> EthereumSignature {
 		let msg = keccak256(&ethereum_signable_message(what));
 		let (sig, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), &bob_secret()).unwrap();
 		let sig: ([u8; 32], [u8; 32]) = Decode::decode(&mut &sig.serialize()[..]).unwrap();
-		EcdsaSignature(sig.0, sig.1, recovery_id.serialize() as i8)
+		EthereumSignature::PersonalSign(EcdsaSignature(sig.0, sig.1, recovery_id.serialize() as i8))
 	}
 
 	// This function basically just builds a genesis storage key/value store according to
@@ -225,6 +461,8 @@ mod tests {
 		t.extend(balances::GenesisConfig::<Test>::default().build_storage().unwrap().0);
 		t.extend(GenesisConfig::<Test>{
 			claims: vec![(alice_eth(), 100)],
+			vesting: vec![],
+			signing: vec![],
 		}.build_storage().unwrap().0);
 		t.into()
 	}
@@ -272,12 +510,179 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn unsigned_claiming_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert!(Claims::claim_unsigned(Origin::signed(69), 42, alice_sig(&42u64.encode())).is_err());
+			assert_noop!(
+				Claims::claim_unsigned(Origin::INHERENT, 69, alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_ok!(Claims::claim_unsigned(Origin::INHERENT, 42, alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
+
+	#[test]
+	fn validate_claim_works() {
+		with_externalities(&mut new_test_ext(), || {
+			match Claims::validate_claim(&42, &alice_sig(&42u64.encode())) {
+				TransactionValidity::Valid { priority, requires, provides, .. } => {
+					assert_eq!(priority, CLAIM_PRIORITY);
+					assert!(requires.is_empty());
+					assert_eq!(provides, vec![(b"claims", alice_eth()).encode()]);
+				}
+				_ => panic!("claim should be valid"),
+			}
+
+			// the signature must be over the destination account.
+			match Claims::validate_claim(&69, &alice_sig(&42u64.encode())) {
+				TransactionValidity::Invalid(_) => {}
+				_ => panic!("claim to another account should be invalid"),
+			}
+
+			// once claimed, it cannot be replayed.
+			assert_ok!(Claims::claim_unsigned(Origin::INHERENT, 42, alice_sig(&42u64.encode())));
+			match Claims::validate_claim(&42, &alice_sig(&42u64.encode())) {
+				TransactionValidity::Invalid(_) => {}
+				_ => panic!("replayed claim should be invalid"),
+			}
+		});
+	}
+
 	#[test]
 	fn real_eth_sig_works() {
 		let sig = hex!["7505f2880114da51b3f5d535f8687953c0ab9af4ab81e592eaebebf53b728d2b6dfd9b5bcd70fee412b1f31360e7c2774009305cb84fc50c1d0ff8034dfa5fff1c"];
-		let sig = EcdsaSignature::from_blob(&sig);
+		let sig = EthereumSignature::PersonalSign(EcdsaSignature::from_blob(&sig));
 		let who = 42u64.encode();
 		let signer = eth_recover(&sig, &who).unwrap();
 		assert_eq!(signer, hex!["DF67EC7EAe23D2459694685257b6FC59d1BAA1FE"]);
 	}
+
+	#[test]
+	fn real_typed_data_sig_works() {
+		// signed with the private key `keccak256("cow")` from the EIP-712 examples.
+		let who = 42u64.encode();
+		assert_eq!(
+			keccak256(&eip712_signable_message(&who)),
+			hex!["f541469a5091833b88748cebf229a3ca65a4cd89a7eba3361f094ce08e4f3398"]
+		);
+
+		let sig = hex!["c3c84f42450a295bb6c3d59341293f3da6270f4e993c7e4bddeb7e0edef6433f31d685aaeac4fb822f0428c3702dfefdba1250bd7ab4bede839dcfd3318f9ec61b"];
+		let sig = EthereumSignature::TypedData(EcdsaSignature::from_blob(&sig));
+		let signer = eth_recover(&sig, &who).unwrap();
+		assert_eq!(signer, hex!["CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"]);
+
+		// the same signature doesn't recover the signer under the other scheme.
+		let sig = match sig {
+			EthereumSignature::TypedData(s) => EthereumSignature::PersonalSign(s),
+			_ => unreachable!(),
+		};
+		assert!(eth_recover(&sig, &who) != Some(hex!["CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"]));
+	}
+
+	#[test]
+	fn typed_data_claiming_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Balances::free_balance(&42), 0);
+			assert_ok!(Claims::claim(Origin::signed(42), alice_typed_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+			assert_noop!(
+				Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())),
+				"Ethereum address has no claim"
+			);
+		});
+	}
+
+	#[test]
+	fn mint_claim_requires_root() {
+		with_externalities(&mut new_test_ext(), || {
+			assert!(Claims::mint_claim(Origin::signed(42), bob_eth(), 200, None, false).is_err());
+			assert_ok!(Claims::mint_claim(Origin::ROOT, bob_eth(), 200, None, false));
+			assert_eq!(Claims::total(), 300);
+			assert_noop!(
+				Claims::mint_claim(Origin::ROOT, bob_eth(), 200, None, false),
+				"Ethereum address already has a claim"
+			);
+			assert_noop!(
+				Claims::mint_claim(Origin::ROOT, [9; 20], u64::max_value(), None, false),
+				"Total of claims overflows"
+			);
+
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 200);
+			assert_eq!(Claims::total(), 100);
+		});
+	}
+
+	#[test]
+	fn vesting_claim_is_released_linearly() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::mint_claim(Origin::ROOT, bob_eth(), 200, Some((150, 10, 5)), false));
+
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+			assert_eq!(Balances::free_balance(&69), 50);
+			assert_eq!(Claims::vesting(&69), Some(VestingSchedule { locked: 150, per_block: 10, starting_block: 5 }));
+
+			// nothing is released before the starting block.
+			assert_ok!(Claims::vest(Origin::signed(69)));
+			assert_eq!(Balances::free_balance(&69), 50);
+
+			system::Module::<Test>::set_block_number(10);
+			assert_ok!(Claims::vest(Origin::signed(69)));
+			assert_eq!(Balances::free_balance(&69), 100);
+			assert_eq!(Claims::vesting(&69), Some(VestingSchedule { locked: 100, per_block: 10, starting_block: 10 }));
+
+			system::Module::<Test>::set_block_number(100);
+			assert_ok!(Claims::vest(Origin::signed(69)));
+			assert_eq!(Balances::free_balance(&69), 200);
+			assert_eq!(Claims::vesting(&69), None);
+			assert_noop!(Claims::vest(Origin::signed(69)), "Account has no vesting schedule");
+		});
+	}
+
+	#[test]
+	fn vesting_claim_to_vesting_account_doesnt_work() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Claims::mint_claim(Origin::ROOT, bob_eth(), 200, Some((150, 10, 5)), false));
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())));
+
+			<ClaimVesting<Test>>::insert(&alice_eth(), (50, 1, 0));
+			assert_noop!(
+				Claims::claim(Origin::signed(69), alice_sig(&69u64.encode())),
+				"Destination account already has a vesting schedule"
+			);
+		});
+	}
+
+	#[test]
+	fn statement_must_be_signed_when_required() {
+		with_externalities(&mut new_test_ext(), || {
+			let statement = H256::from([7; 32]);
+			assert!(Claims::set_statement(Origin::signed(42), statement).is_err());
+			assert_ok!(Claims::set_statement(Origin::ROOT, statement));
+			assert_ok!(Claims::mint_claim(Origin::ROOT, bob_eth(), 200, None, true));
+
+			let mut with_statement = 69u64.encode();
+			with_statement.extend_from_slice(statement.as_ref());
+
+			assert_noop!(
+				Claims::claim(Origin::signed(69), bob_sig(&69u64.encode())),
+				"Ethereum address has no claim"
+			);
+			assert_ok!(Claims::claim(Origin::signed(69), bob_sig(&with_statement)));
+			assert_eq!(Balances::free_balance(&69), 200);
+
+			// claims which do not require the statement are unaffected.
+			assert_ok!(Claims::claim(Origin::signed(42), alice_sig(&42u64.encode())));
+			assert_eq!(Balances::free_balance(&42), 100);
+		});
+	}
 }
@@ -102,6 +102,8 @@ pub use consensus::Call as ConsensusCall;
 pub use timestamp::Call as TimestampCall;
 pub use balances::Call as BalancesCall;
 pub use parachains::{Call as ParachainsCall, INHERENT_IDENTIFIER as PARACHAIN_INHERENT_IDENTIFIER};
+pub use claims::Call as ClaimsCall;
+pub use sudo::Call as SudoCall;
 pub use sr_primitives::{Permill, Perbill};
 pub use timestamp::BlockPeriod;
 pub use srml_support::StorageValue;
@@ -180,6 +182,14 @@ impl Convert<AccountId, SessionKey> for SessionKeyConversion {
 	}
 }
 
+/// Account ID conversion. Accounts named in parachain candidates are relay chain accounts.
+pub struct AccountIdConversion;
+impl Convert<AccountId, AccountId> for AccountIdConversion {
+	fn convert(a: AccountId) -> AccountId {
+		a
+	}
+}
+
 impl session::Trait for Runtime {
 	type ConvertAccountIdToSessionKey = SessionKeyConversion;
 	type OnSessionChange = (Staking, grandpa::SyncedAuthorities<Runtime>);
@@ -225,7 +235,10 @@ impl grandpa::Trait for Runtime {
 	type Event = Event;
 }
 
-impl parachains::Trait for Runtime {}
+impl parachains::Trait for Runtime {
+	type Event = Event;
+	type ConvertAccountId = AccountIdConversion;
+}
 
 impl upgrade_key::Trait for Runtime {
 	type Event = Event;
@@ -267,7 +280,7 @@ construct_runtime!(
 		CouncilMotions: council_motions::{Module, Call, Storage, Event<T>, Origin},
 		CouncilSeats: council_seats::{Config<T>},
 		Treasury: treasury,
-		Parachains: parachains::{Module, Call, Storage, Config<T>, Inherent},
+		Parachains: parachains::{Module, Call, Storage, Config<T>, Inherent, Event<T>},
 		Sudo: sudo,
 		UpgradeKey: upgrade_key,
 		Claims: claims,
@@ -292,6 +305,39 @@ pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Nonce, Call>;
 /// Executive: handles dispatch to the various modules.
 pub type Executive = executive::Executive<Runtime, Block, system::ChainContext<Runtime>, Fees, AllModules>;
 
+// Combine the validity of a transaction with additional requirements on it: it is
+// valid only if both are, with the sum of their priorities and the tags of both.
+fn combine_validity(a: TransactionValidity, b: TransactionValidity) -> TransactionValidity {
+	match (a, b) {
+		(
+			TransactionValidity::Valid { priority, mut requires, mut provides, longevity },
+			TransactionValidity::Valid {
+				priority: extra_priority,
+				requires: extra_requires,
+				provides: extra_provides,
+				longevity: extra_longevity,
+			},
+		) => {
+			requires.extend(extra_requires);
+			provides.extend(extra_provides);
+			TransactionValidity::Valid {
+				priority: priority.saturating_add(extra_priority),
+				requires,
+				provides,
+				longevity: rstd::cmp::min(longevity, extra_longevity),
+			}
+		}
+		(TransactionValidity::Valid { .. }, other) | (other, _) => other,
+	}
+}
+
+// whether the transaction is signed by the account holding the sudo key.
+fn is_signed_by_sudo_key(tx: &UncheckedExtrinsic) -> bool {
+	tx.signature.as_ref()
+		.and_then(|&(ref address, _, _, _)| <Indices as StaticLookup>::lookup(address.clone()).ok())
+		.map_or(false, |signer| signer == Sudo::key())
+}
+
 impl_runtime_apis! {
 	impl client_api::Core<Block> for Runtime {
 		fn version() -> RuntimeVersion {
@@ -331,7 +377,23 @@ impl_runtime_apis! {
 		}
 
 		fn check_inherents(block: Block, data: InherentData) -> CheckInherentsResult {
-			data.check_extrinsics(&block)
+			let mut result = data.check_extrinsics(&block);
+
+			// the parachain heads inherent must be included even if it is empty,
+			// whether or not this node knows of any attested candidates.
+			let has_heads = block.extrinsics.iter().any(|xt| xt.signature.is_none() && match xt.function {
+				Call::Parachains(ParachainsCall::set_heads(_)) => true,
+				_ => false,
+			});
+			if !has_heads {
+				// an error already noted for the heads is fatal as well.
+				let _ = result.put_error(
+					PARACHAIN_INHERENT_IDENTIFIER,
+					&parachains::InherentError::MissingHeads,
+				);
+			}
+
+			result
 		}
 
 		fn random_seed() -> <Block as BlockT>::Hash {
@@ -341,7 +403,39 @@ impl_runtime_apis! {
 
 	impl client_api::TaggedTransactionQueue<Block> for Runtime {
 		fn validate_transaction(tx: <Block as BlockT>::Extrinsic) -> TransactionValidity {
-			Executive::validate_transaction(tx)
+			// misbehavior reports and claims are submitted unsigned and carry their own proof.
+			if tx.signature.is_none() {
+				match tx.function {
+					Call::Parachains(ParachainsCall::report_misbehavior(ref report)) =>
+						return Parachains::validate_misbehavior_report(report),
+					Call::Claims(ClaimsCall::claim_unsigned(ref dest, ref signature)) =>
+						return Claims::validate_claim(dest, signature),
+					_ => {}
+				}
+			}
+
+			// calls to the parachains module, directly or through sudo, are checked
+			// before the generic checks of the transaction carrying them. root calls
+			// are only checked for the sudo key, so nobody else can claim their tags.
+			let parachain_validity = match tx.function {
+				Call::Parachains(ref call) => Parachains::validate_call(call),
+				Call::Sudo(SudoCall::sudo(ref proposal)) => match **proposal {
+					Call::Parachains(ref call) => Some(if is_signed_by_sudo_key(&tx) {
+						Parachains::validate_root_call(call)
+					} else {
+						TransactionValidity::Invalid(parachains::InvalidCall::BadOrigin as i8)
+					}),
+					_ => None,
+				},
+				_ => None,
+			};
+
+			match parachain_validity {
+				Some(validity @ TransactionValidity::Valid { .. }) =>
+					combine_validity(Executive::validate_transaction(tx), validity),
+				Some(invalid) => invalid,
+				None => Executive::validate_transaction(tx),
+			}
 		}
 	}
 
@@ -361,9 +455,24 @@ impl_runtime_apis! {
 		fn parachain_code(id: parachain::Id) -> Option<Vec<u8>> {
 			Parachains::parachain_code(&id)
 		}
+		fn parachain_head_at(id: parachain::Id, relay_number: BlockNumber) -> Option<Vec<u8>> {
+			Parachains::parachain_head_at(&id, relay_number)
+		}
+		fn parachain_head_history(id: parachain::Id, count: u32) -> Vec<(BlockNumber, Vec<u8>)> {
+			Parachains::recent_heads(&id, count as usize)
+		}
 		fn ingress(to: parachain::Id) -> Option<Vec<(parachain::Id, Hash)>> {
 			Parachains::ingress(to)
 		}
+		fn head_storage_key(id: parachain::Id) -> Vec<u8> {
+			Parachains::head_storage_key(&id)
+		}
+		fn routing_storage_key(from: parachain::Id, to: parachain::Id) -> Vec<u8> {
+			Parachains::routing_storage_key(from, to)
+		}
+		fn collators(id: parachain::Id) -> Option<Vec<AccountId>> {
+			Parachains::collators(&id)
+		}
 	}
 
 	impl fg_primitives::GrandpaApi<Block> for Runtime {
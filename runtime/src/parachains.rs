@@ -20,15 +20,23 @@ use rstd::prelude::*;
 use codec::Decode;
 
 use bitvec::BigEndian;
-use sr_primitives::traits::{Hash as HashT, BlakeTwo256};
-use primitives::Hash;
-use primitives::parachain::{Id as ParaId, Chain, DutyRoster, AttestedCandidate, Statement};
-use {system, session};
+use sr_primitives::traits::{Hash as HashT, BlakeTwo256, One, Zero, Saturating, As, Convert, CheckedSub};
+use primitives::{Hash, SessionKey, AccountId};
+use primitives::parachain::{
+	Id as ParaId, Chain, DutyRoster, AttestedCandidate, CandidateReceipt, Statement, CandidateSignature,
+	MisbehaviorReport, MisbehaviorProof, MAX_QUEUED_ROOTS,
+};
+use {system, session, staking, balances};
 
 use srml_support::{StorageValue, StorageMap};
 use srml_support::dispatch::Result;
+use srml_support::traits::{Currency, TransferAsset};
+use sr_io::twox_128;
+use sr_primitives::{ApplyError, transaction_validity::{
+	TransactionValidity, TransactionPriority, TransactionLongevity,
+}};
 
-use inherents::{ProvideInherent, InherentData, RuntimeString, MakeFatalError, InherentIdentifier};
+use inherents::{ProvideInherent, InherentData, IsFatalError, InherentIdentifier};
 
 #[cfg(any(feature = "std", test))]
 use sr_primitives::{StorageOverlay, ChildrenStorageOverlay};
@@ -36,9 +44,74 @@ use sr_primitives::{StorageOverlay, ChildrenStorageOverlay};
 #[cfg(any(feature = "std", test))]
 use rstd::marker::PhantomData;
 
-use system::ensure_inherent;
+use system::{ensure_inherent, ensure_signed};
 
-pub trait Trait: session::Trait {}
+pub trait Trait: session::Trait + staking::Trait + balances::Trait {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+	/// Conversion of the accounts named in candidate receipts into relay chain accounts.
+	type ConvertAccountId: Convert<AccountId, Self::AccountId>;
+}
+
+/// An event in this module.
+decl_event!(
+	pub enum Event<T> where A = <T as system::Trait>::AccountId {
+		/// A candidate was included, advancing the parachain's head to the head data
+		/// with the given hash. The candidate's collator is included.
+		CandidateIncluded(ParaId, Hash, A),
+		/// An egress queue root was routed from one parachain to another.
+		MessageRouted(ParaId, ParaId, Hash),
+		/// A parachain was registered.
+		ParachainRegistered(ParaId),
+		/// A parachain was deregistered.
+		ParachainDeregistered(ParaId),
+		/// The set of accounts allowed to collate on a parachain changed.
+		CollatorsChanged(ParaId),
+		/// An account moved some of its balance into the relay chain balance of a parachain.
+		ParachainFunded(ParaId, A, u64),
+	}
+);
+
+/// Number of recent heads kept in the history of each parachain.
+const HEAD_HISTORY_LENGTH: u32 = 256;
+
+/// Number of recent relay parents for which misbehavior may be reported.
+const MISBEHAVIOR_REPORT_WINDOW: usize = 64;
+
+/// Multiple of the staking module's current offline slash which misbehavior is slashed by.
+const MISBEHAVIOR_SLASH_FACTOR: u64 = 8;
+
+/// Transaction pool priority of misbehavior reports.
+const MISBEHAVIOR_REPORT_PRIORITY: TransactionPriority = 1 << 20;
+
+/// Transaction pool priority added to root calls changing a parachain.
+const PARACHAIN_CALL_PRIORITY: TransactionPriority = 1 << 16;
+
+/// Maximum size of the code of a registered parachain.
+pub const MAX_CODE_SIZE: usize = 1024 * 1024;
+
+/// Maximum size of the initial head data of a registered parachain.
+pub const MAX_HEAD_DATA_SIZE: usize = 16 * 1024;
+
+/// Reasons for calls to this module being rejected from the transaction pool,
+/// reported by `TransactionValidity::Invalid`.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[repr(i8)]
+pub enum InvalidCall {
+	/// The parachain code is larger than `MAX_CODE_SIZE`.
+	CodeTooLarge = 1,
+	/// The initial head data is larger than `MAX_HEAD_DATA_SIZE`.
+	HeadDataTooLarge = 2,
+	/// The call is for a parachain which is not registered.
+	UnknownParachain = 3,
+	/// The call registers a parachain which is already registered.
+	AlreadyRegistered = 4,
+	/// The call may not be dispatched with the origin of the transaction: it is
+	/// either an inherent or must be dispatched by root, through the sudo key.
+	BadOrigin = 5,
+}
 
 decl_storage! {
 	trait Store for Module<T: Trait> as Parachains {
@@ -48,14 +121,42 @@ decl_storage! {
 		pub Code get(parachain_code): map ParaId => Option<Vec<u8>>;
 		// The heads of the parachains registered at present.
 		pub Heads get(parachain_head): map ParaId => Option<Vec<u8>>;
-		// message routing roots (from, to).
-		pub Routing: map (ParaId, ParaId) => Option<Hash>;
+		// Recent heads of each parachain along with the relay block number at
+		// which they were included, in a ring buffer of `HEAD_HISTORY_LENGTH` slots.
+		HeadHistory: map (ParaId, u32) => Option<(T::BlockNumber, Vec<u8>)>;
+		// The slot of `HeadHistory` the next head of each parachain is written to.
+		// it holds the oldest head once the ring buffer is full.
+		HeadHistoryCursor: map ParaId => u32;
+		// queues of unprocessed message roots (from, to), along with the relay
+		// block number at which each was routed. oldest first.
+		pub RoutingQueue get(routing_queue): map (ParaId, ParaId) => Vec<(T::BlockNumber, Hash)>;
+		// The relay block number up to which each parachain has processed its ingress.
+		pub Watermarks get(watermark): map ParaId => Option<T::BlockNumber>;
+		// The balance each parachain holds on the relay chain.
+		pub RelayBalances get(relay_balance): map ParaId => u64;
+		// The accounts allowed to collate on permissioned parachains. Parachains
+		// without an entry may be collated on by anyone.
+		pub Collators get(collators): map ParaId => Option<Vec<AccountId>>;
 
 		// Did the parachain heads get updated in this block?
 		DidUpdate: bool;
+
+		// The index of the validator set and the duty roster in place for
+		// attestations on top of recent relay parents.
+		pub RecentRosters get(recent_roster): map Hash => Option<(u32, DutyRoster)>;
+		// The relay parents tracked in `RecentRosters`, oldest first.
+		RecentRosterParents: Vec<Hash>;
+		// The validators and their session keys referred to by `RecentRosters`.
+		// A new set is only recorded when the validators change.
+		pub ValidatorSets get(validator_set): map u32 => Option<(Vec<T::AccountId>, Vec<SessionKey>)>;
+		// The index the next recorded validator set will be stored under.
+		NextValidatorSet: u32;
+		// Validators already punished for misbehavior at a recent relay parent.
+		pub Punished get(punished): map Hash => Vec<SessionKey>;
 	}
 	add_extra_genesis {
 		config(parachains): Vec<(ParaId, Vec<u8>, Vec<u8>)>;
+		config(relay_balances): Vec<(ParaId, u64)>;
 		config(_phdata): PhantomData<T>;
 		build(|storage: &mut StorageOverlay, _: &mut ChildrenStorageOverlay, config: &GenesisConfig<T>| {
 			use codec::Encode;
@@ -71,10 +172,21 @@ decl_storage! {
 			for (id, code, genesis) in p {
 				let code_key = Self::hash(&<Code<T>>::key_for(&id)).to_vec();
 				let head_key = Self::hash(&<Heads<T>>::key_for(&id)).to_vec();
+				let history_key = Self::hash(&<HeadHistory<T>>::key_for(&(id, 0))).to_vec();
+				let cursor_key = Self::hash(&<HeadHistoryCursor<T>>::key_for(&id)).to_vec();
 				// no ingress -- a chain cannot be routed to until it is live.
 
+				let history: (T::BlockNumber, Vec<u8>) = (Zero::zero(), genesis.clone());
+
 				storage.insert(code_key, code.encode());
 				storage.insert(head_key, genesis.encode());
+				storage.insert(history_key, history.encode());
+				storage.insert(cursor_key, 1u32.encode());
+			}
+
+			for &(ref id, balance) in &config.relay_balances {
+				let balance_key = Self::hash(&<RelayBalances<T>>::key_for(id)).to_vec();
+				storage.insert(balance_key, balance.encode());
 			}
 		});
 	}
@@ -83,6 +195,8 @@ decl_storage! {
 decl_module! {
 	/// Parachains module.
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event<T>() = default;
+
 		/// Provide candidate receipts for parachains, in ascending order by id.
 		fn set_heads(origin, heads: Vec<AttestedCandidate>) -> Result {
 			ensure_inherent(origin)?;
@@ -114,16 +228,39 @@ decl_module! {
 				}
 			}
 
-			Self::check_attestations(&heads)?;
+			let duty_roster = Self::note_roster();
+			Self::check_attestations(&heads, &duty_roster)?;
 
+			// the block author leaves out candidates which cannot be included, but
+			// a single one slipping through is skipped rather than failing the block.
+			let heads: Vec<_> = heads.into_iter().filter(|head| Self::is_includable(head)).collect();
 
+			// candidates were built on the ingress available at the parent block,
+			// so everything routed up to then has now been processed.
+			let now = <system::Module<T>>::block_number();
+			let watermark = now.saturating_sub(One::one());
+			for head in &heads {
+				Self::advance_watermark(&active_parachains, head.parachain_index(), watermark);
+			}
+
+			let authorities = super::Consensus::authorities();
+			let validators = <session::Module<T>>::validators();
 			for head in heads {
 				let id = head.parachain_index();
+				Self::apply_balance_changes(&head, &authorities, &validators);
+				Self::note_head(id, now, head.candidate.head_data.0.clone());
+
+				let head_hash = BlakeTwo256::hash(&head.candidate.head_data.0[..]);
 				<Heads<T>>::insert(id, head.candidate.head_data.0);
+				let collator = T::ConvertAccountId::convert(head.candidate.collator);
+				Self::deposit_event(RawEvent::CandidateIncluded(id, head_hash, collator));
 
-				// update egress.
+				// update egress. messages to parachains which are not active are dropped.
 				for &(to, root) in &head.candidate.egress_queue_roots {
-					<Routing<T>>::insert((id, to), root);
+					if active_parachains.binary_search(&to).is_ok() {
+						<RoutingQueue<T>>::mutate((id, to), |queue| queue.push((now, root)));
+						Self::deposit_event(RawEvent::MessageRouted(id, to, root));
+					}
 				}
 			}
 
@@ -135,6 +272,9 @@ decl_module! {
 		/// Register a parachain with given code.
 		/// Fails if given ID is already used.
 		pub fn register_parachain(id: ParaId, code: Vec<u8>, initial_head_data: Vec<u8>) -> Result {
+			ensure!(code.len() <= MAX_CODE_SIZE, "Parachain code is too large");
+			ensure!(initial_head_data.len() <= MAX_HEAD_DATA_SIZE, "Parachain head data is too large");
+
 			let mut parachains = Self::active_parachains();
 			match parachains.binary_search(&id) {
 				Ok(_) => fail!("Parachain already exists"),
@@ -143,8 +283,11 @@ decl_module! {
 
 			<Code<T>>::insert(id, code);
 			<Parachains<T>>::put(parachains);
+			Self::note_head(id, <system::Module<T>>::block_number(), initial_head_data.clone());
 			<Heads<T>>::insert(id, initial_head_data);
 
+			Self::deposit_event(RawEvent::ParachainRegistered(id));
+
 			Ok(())
 		}
 
@@ -158,20 +301,86 @@ decl_module! {
 
 			<Code<T>>::remove(id);
 			<Heads<T>>::remove(id);
+			for slot in 0..HEAD_HISTORY_LENGTH {
+				<HeadHistory<T>>::remove((id, slot));
+			}
+			<HeadHistoryCursor<T>>::remove(id);
+			<Watermarks<T>>::remove(id);
+			<Collators<T>>::remove(id);
 
-			// clear all routing entries to and from other parachains.
+			// clear all routing queues to and from other parachains.
 			for other in parachains.iter().cloned() {
-				<Routing<T>>::remove((id, other));
-				<Routing<T>>::remove((other, id));
+				<RoutingQueue<T>>::remove((id, other));
+				<RoutingQueue<T>>::remove((other, id));
 			}
 
 			<Parachains<T>>::put(parachains);
 
+			Self::deposit_event(RawEvent::ParachainDeregistered(id));
+
+			Ok(())
+		}
+
+		/// Restrict the accounts allowed to collate on a parachain, or lift the
+		/// restriction with `None`.
+		pub fn set_collators(id: ParaId, collators: Option<Vec<AccountId>>) -> Result {
+			ensure!(
+				Self::active_parachains().binary_search(&id).is_ok(),
+				"Cannot set collators of an unregistered parachain"
+			);
+
+			match collators {
+				Some(mut collators) => {
+					collators.sort();
+					collators.dedup();
+					<Collators<T>>::insert(id, collators);
+				}
+				None => <Collators<T>>::remove(id),
+			}
+
+			Self::deposit_event(RawEvent::CollatorsChanged(id));
+
+			Ok(())
+		}
+
+		/// Move some of the sender's free balance into the relay chain balance of a
+		/// parachain, out of which its candidates' fees and balance uploads are paid.
+		fn fund_parachain(origin, id: ParaId, amount: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				Self::active_parachains().binary_search(&id).is_ok(),
+				"Cannot fund an unregistered parachain"
+			);
+
+			let relay_balance = Self::relay_balance(id).checked_add(amount)
+				.ok_or("Parachain relay chain balance overflow")?;
+
+			<balances::Module<T> as TransferAsset<T::AccountId>>::withdraw(&who, As::sa(amount))?;
+			<RelayBalances<T>>::insert(id, relay_balance);
+
+			Self::deposit_event(RawEvent::ParachainFunded(id, who, amount));
+
+			Ok(())
+		}
+
+		/// Report misbehavior of a validator during parachain attestation.
+		///
+		/// The proof is checked against the duty roster of the report's relay parent
+		/// and the offender's balance is slashed.
+		fn report_misbehavior(origin, report: MisbehaviorReport) -> Result {
+			ensure_inherent(origin)?;
+
+			let offender = Self::check_misbehavior_report(&report)?;
+			<Punished<T>>::mutate(&report.parent_hash, |punished| punished.push(report.offender));
+			Self::slash_misbehavior(&offender);
+
 			Ok(())
 		}
 
 		fn on_finalise(_n: T::BlockNumber) {
-			assert!(<Self as Store>::DidUpdate::take(), "Parachain heads must be updated once in the block");
+			// blocks lacking the parachain heads inherent are rejected when
+			// checking inherents, see `InherentError::MissingHeads`.
+			<Self as Store>::DidUpdate::kill();
 		}
 	}
 }
@@ -188,6 +397,27 @@ fn localized_payload(statement: Statement, parent_hash: ::primitives::Hash) -> V
 	encoded
 }
 
+// the total amount a candidate draws from its parachain's relay chain balance.
+fn candidate_debit(candidate: &CandidateReceipt) -> Option<u64> {
+	candidate.balance_uploads.iter()
+		.try_fold(candidate.fees, |total, &(_, amount)| total.checked_add(amount))
+}
+
+// whether two statements are conflicting votes on the validity of the same candidate.
+fn validity_votes_conflict(a: &Statement, b: &Statement) -> bool {
+	match (a, b) {
+		(Statement::Candidate(c), Statement::Valid(h))
+			| (Statement::Valid(h), Statement::Candidate(c))
+			| (Statement::Candidate(c), Statement::Invalid(h))
+			| (Statement::Invalid(h), Statement::Candidate(c))
+			=> &c.hash() == h,
+		(Statement::Valid(x), Statement::Invalid(y))
+			| (Statement::Invalid(y), Statement::Valid(x))
+			=> x == y,
+		_ => false,
+	}
+}
+
 impl<T: Trait> Module<T> {
 	/// Calculate the current block's duty roster using system's random seed.
 	pub fn calculate_duty_roster() -> DutyRoster {
@@ -233,24 +463,362 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// Get the head of a parachain as of the given relay chain block number.
+	///
+	/// Returns `None` if the parachain is not registered or the block is older
+	/// than the retained head history.
+	pub fn parachain_head_at(id: &ParaId, relay_number: T::BlockNumber) -> Option<Vec<u8>> {
+		let cursor = <HeadHistoryCursor<T>>::get(id);
+
+		// find the first head included after the block. the empty slots of a
+		// ring buffer which is not yet full come before all heads.
+		let (mut lo, mut hi) = (0, HEAD_HISTORY_LENGTH);
+		while lo < hi {
+			let mid = lo + (hi - lo) / 2;
+			match Self::history_entry(id, cursor, mid) {
+				Some((n, _)) if n > relay_number => hi = mid,
+				_ => lo = mid + 1,
+			}
+		}
+
+		lo.checked_sub(1)
+			.and_then(|idx| Self::history_entry(id, cursor, idx))
+			.map(|(_, head)| head)
+	}
+
+	/// Get up to `count` of the most recent heads of a parachain along with the
+	/// relay chain block number at which each was included, newest first.
+	pub fn recent_heads(id: &ParaId, count: usize) -> Vec<(T::BlockNumber, Vec<u8>)> {
+		let cursor = <HeadHistoryCursor<T>>::get(id);
+		(0..HEAD_HISTORY_LENGTH).rev()
+			.map(|idx| Self::history_entry(id, cursor, idx))
+			.take_while(Option::is_some)
+			.filter_map(|entry| entry)
+			.take(count)
+			.collect()
+	}
+
+	/// Get the retained heads of a parachain along with the relay chain block
+	/// number at which each was included, oldest first.
+	pub fn head_history(id: &ParaId) -> Vec<(T::BlockNumber, Vec<u8>)> {
+		let mut history = Self::recent_heads(id, HEAD_HISTORY_LENGTH as usize);
+		history.reverse();
+		history
+	}
+
+	// the entry of a parachain's head history `idx` places after the oldest slot,
+	// which is at the cursor.
+	fn history_entry(id: &ParaId, cursor: u32, idx: u32) -> Option<(T::BlockNumber, Vec<u8>)> {
+		<HeadHistory<T>>::get((*id, (cursor + idx) % HEAD_HISTORY_LENGTH))
+	}
+
+	/// The storage key of a parachain's head.
+	pub fn head_storage_key(id: &ParaId) -> Vec<u8> {
+		twox_128(&<Heads<T>>::key_for(id)).to_vec()
+	}
+
+	/// The storage key of the message roots queued from one parachain to another.
+	pub fn routing_storage_key(from: ParaId, to: ParaId) -> Vec<u8> {
+		twox_128(&<RoutingQueue<T>>::key_for(&(from, to))).to_vec()
+	}
+
 	/// Calculate the ingress to a specific parachain.
 	///
 	/// Yields a list of parachains being routed from, and the egress
-	/// queue roots to consider.
+	/// queue roots to consider, oldest first for each sender.
 	pub fn ingress(to: ParaId) -> Option<Vec<(ParaId, Hash)>> {
 		let active_parachains = Self::active_parachains();
 		if !active_parachains.contains(&to) { return None }
 
 		Some(active_parachains.into_iter().filter(|i| i != &to)
-			.filter_map(move |from| {
-				<Routing<T>>::get((from, to.clone())).map(move |h| (from, h))
+			.flat_map(move |from| {
+				<RoutingQueue<T>>::get((from, to.clone())).into_iter().map(move |(_, h)| (from, h))
 			})
 			.collect())
 	}
 
+	/// Slash a validator proven to have misbehaved by `MISBEHAVIOR_SLASH_FACTOR` times
+	/// the current offline slash, out of the balance it is staking with.
+	fn slash_misbehavior(offender: &T::AccountId) {
+		let slash = <staking::Module<T>>::current_offline_slash()
+			.checked_mul(&As::sa(MISBEHAVIOR_SLASH_FACTOR))
+			.unwrap_or_else(|| <T as staking::Trait>::Currency::total_balance(offender));
+
+		// whatever cannot be slashed is gone with the offender's stake already.
+		let _ = <T as staking::Trait>::Currency::slash(offender, slash);
+	}
+
+	/// Check a misbehavior report against the validators and duty roster recorded
+	/// for its relay parent. Returns the account of the offender on success.
+	pub fn check_misbehavior_report(report: &MisbehaviorReport)
+		-> rstd::result::Result<T::AccountId, &'static str>
+	{
+		use sr_primitives::traits::Verify;
+
+		let (set_index, duty_roster) = Self::recent_roster(&report.parent_hash)
+			.ok_or("Misbehavior reported for unknown or expired relay parent")?;
+		let (validators, authorities) = Self::validator_set(set_index)
+			.ok_or("Misbehavior reported for unknown or expired relay parent")?;
+
+		let offender_idx = authorities.iter().position(|a| a == &report.offender)
+			.ok_or("Reported offender was not a validator at the relay parent")?;
+
+		ensure!(
+			!Self::punished(&report.parent_hash).contains(&report.offender),
+			"Misbehavior already reported for this relay parent"
+		);
+
+		let signed_by_offender = |statement: &Statement, sig: &CandidateSignature| {
+			let payload = localized_payload(statement.clone(), report.parent_hash);
+			sig.verify(&payload[..], &report.offender.0.into())
+		};
+
+		let proven = match report.proof {
+			MisbehaviorProof::ValidityDoubleVote(ref a, ref sig_a, ref b, ref sig_b) =>
+				validity_votes_conflict(a, b)
+					&& signed_by_offender(a, sig_a)
+					&& signed_by_offender(b, sig_b),
+			MisbehaviorProof::MultipleCandidates(ref a, ref sig_a, ref b, ref sig_b) =>
+				a != b
+					&& signed_by_offender(&Statement::Candidate(a.clone()), sig_a)
+					&& signed_by_offender(&Statement::Candidate(b.clone()), sig_b),
+			MisbehaviorProof::UnauthorizedStatement(ref statement, ref sig) => match statement {
+				Statement::Candidate(c) =>
+					duty_roster.validator_duty.get(offender_idx) != Some(&Chain::Parachain(c.parachain_index))
+						&& signed_by_offender(statement, sig),
+				// the group of a bare candidate hash cannot be determined on-chain.
+				Statement::Valid(_) | Statement::Invalid(_) => false,
+			},
+			MisbehaviorProof::DoubleSign(ref statement, ref sig_a, ref sig_b) =>
+				sig_a != sig_b
+					&& signed_by_offender(statement, sig_a)
+					&& signed_by_offender(statement, sig_b),
+		};
+
+		ensure!(proven, "Invalid misbehavior proof");
+
+		validators.get(offender_idx).cloned()
+			.ok_or("Reported offender was not a validator at the relay parent")
+	}
+
+	/// Check a signed call to this module for inclusion in the transaction pool,
+	/// before the generic checks of the transaction carrying it. Returns `None`
+	/// if only the generic checks apply.
+	///
+	/// Funding of unregistered parachains is rejected, other funding is left to the
+	/// generic checks. All other calls are inherents or must be dispatched by root,
+	/// so they are rejected.
+	pub fn validate_call(call: &Call<T>) -> Option<TransactionValidity> {
+		let invalid = |reason: InvalidCall| Some(TransactionValidity::Invalid(reason as i8));
+
+		match *call {
+			Call::fund_parachain(ref id, _) => if Self::active_parachains().binary_search(id).is_ok() {
+				None
+			} else {
+				invalid(InvalidCall::UnknownParachain)
+			},
+			_ => invalid(InvalidCall::BadOrigin),
+		}
+	}
+
+	/// Check a call to this module proposed to be dispatched by root, for inclusion
+	/// in the transaction pool before the generic checks of the transaction carrying
+	/// it. The transaction must have been checked to be signed by the sudo key.
+	///
+	/// Registrations are capped in size, and calls are rejected early if they cannot
+	/// succeed. Each provides a tag of its parachain, so that only one root call
+	/// changing a parachain is in the pool at a time.
+	pub fn validate_root_call(call: &Call<T>) -> TransactionValidity {
+		use codec::Encode;
+
+		let invalid = |reason: InvalidCall| TransactionValidity::Invalid(reason as i8);
+		let is_active = |id: &ParaId| Self::active_parachains().binary_search(id).is_ok();
+
+		let id = match *call {
+			Call::register_parachain(ref id, ref code, ref initial_head_data) => {
+				if code.len() > MAX_CODE_SIZE { return invalid(InvalidCall::CodeTooLarge) }
+				if initial_head_data.len() > MAX_HEAD_DATA_SIZE { return invalid(InvalidCall::HeadDataTooLarge) }
+				if is_active(id) { return invalid(InvalidCall::AlreadyRegistered) }
+				*id
+			}
+			Call::deregister_parachain(ref id) | Call::set_collators(ref id, _) => {
+				if !is_active(id) { return invalid(InvalidCall::UnknownParachain) }
+				*id
+			}
+			_ => return invalid(InvalidCall::BadOrigin),
+		};
+
+		TransactionValidity::Valid {
+			priority: PARACHAIN_CALL_PRIORITY,
+			requires: Vec::new(),
+			provides: vec![(b"parachains", id).encode()],
+			longevity: TransactionLongevity::max_value(),
+		}
+	}
+
+	/// Check the validity of an unsigned misbehavior report for inclusion in the
+	/// transaction pool.
+	pub fn validate_misbehavior_report(report: &MisbehaviorReport) -> TransactionValidity {
+		use codec::Encode;
+
+		match Self::check_misbehavior_report(report) {
+			Ok(_) => TransactionValidity::Valid {
+				priority: MISBEHAVIOR_REPORT_PRIORITY,
+				requires: Vec::new(),
+				provides: vec![(report.parent_hash, report.offender).encode()],
+				longevity: MISBEHAVIOR_REPORT_WINDOW as TransactionLongevity,
+			},
+			Err(_) => TransactionValidity::Invalid(ApplyError::BadSignature as i8),
+		}
+	}
+
+	// whether a candidate can be included on top of the current state: its
+	// parachain must afford its debit and none of the queues it routes to may be full.
+	fn is_includable(head: &AttestedCandidate) -> bool {
+		let id = head.parachain_index();
+		let affordable = candidate_debit(&head.candidate)
+			.map_or(false, |debit| Self::relay_balance(id) >= debit);
+
+		affordable && head.candidate.egress_queue_roots.iter()
+			.all(|&(to, _)| <RoutingQueue<T>>::get((id, to)).len() < MAX_QUEUED_ROOTS)
+	}
+
+	// debit the parachain's relay chain balance for an attested candidate,
+	// crediting its balance uploads and paying its fees evenly to the validators
+	// who attested to it. any remainder of the fees goes to the first attester.
+	//
+	// the balance must have been checked to cover the candidate's debit.
+	fn apply_balance_changes(
+		head: &AttestedCandidate,
+		authorities: &[SessionKey],
+		validators: &[T::AccountId],
+	) {
+		let candidate = &head.candidate;
+		let debit = candidate_debit(candidate)
+			.expect("debit checked not to overflow before applying candidates; qed");
+		<RelayBalances<T>>::mutate(candidate.parachain_index, |balance| *balance -= debit);
+
+		for &(ref who, amount) in &candidate.balance_uploads {
+			let who = T::ConvertAccountId::convert(who.clone());
+			Self::pay_out(&who, amount);
+		}
+
+		let attesters: Vec<_> = head.validity_votes.iter()
+			.filter_map(|(key, _)| authorities.iter().position(|a| a == key))
+			.filter_map(|idx| validators.get(idx))
+			.collect();
+
+		if attesters.is_empty() || candidate.fees == 0 { return }
+
+		let share = candidate.fees / attesters.len() as u64;
+		let remainder = candidate.fees % attesters.len() as u64;
+		for (i, who) in attesters.into_iter().enumerate() {
+			let amount = if i == 0 { share + remainder } else { share };
+			if amount != 0 {
+				Self::pay_out(who, amount);
+			}
+		}
+	}
+
+	// credit an account with funds leaving a parachain's relay chain balance.
+	// they were withdrawn from the total issuance when the parachain was funded.
+	fn pay_out(who: &T::AccountId, amount: u64) {
+		// depositing only fails if the total issuance overflows, which funding
+		// a parachain with existing balance cannot lead to.
+		let _ = <balances::Module<T> as TransferAsset<T::AccountId>>::deposit(who, As::sa(amount));
+	}
+
+	// record a new head for the parachain in its bounded history, overwriting
+	// the oldest head once the history is full.
+	fn note_head(id: ParaId, now: T::BlockNumber, head: Vec<u8>) {
+		let cursor = <HeadHistoryCursor<T>>::get(id);
+
+		// a parachain's head is only updated once per block.
+		let last = (cursor + HEAD_HISTORY_LENGTH - 1) % HEAD_HISTORY_LENGTH;
+		if <HeadHistory<T>>::get((id, last)).map_or(false, |(n, _)| n == now) {
+			<HeadHistory<T>>::insert((id, last), (now, head));
+			return;
+		}
+
+		<HeadHistory<T>>::insert((id, cursor), (now, head));
+		<HeadHistoryCursor<T>>::insert(id, (cursor + 1) % HEAD_HISTORY_LENGTH);
+	}
+
+	// pop all roots routed to the given parachain up to and including the
+	// watermark, and record the new watermark.
+	fn advance_watermark(active_parachains: &[ParaId], to: ParaId, watermark: T::BlockNumber) {
+		for from in active_parachains.iter().cloned().filter(|from| from != &to) {
+			let mut queue = <RoutingQueue<T>>::get((from, to));
+			if queue.is_empty() { continue }
+
+			queue.retain(|&(routed_at, _)| routed_at > watermark);
+			if queue.is_empty() {
+				<RoutingQueue<T>>::remove((from, to));
+			} else {
+				<RoutingQueue<T>>::insert((from, to), queue);
+			}
+		}
+
+		<Watermarks<T>>::insert(to, watermark);
+	}
+
+	// record the validators and duty roster used for attestations on top of
+	// the current parent, so misbehavior reports can be checked in later blocks,
+	// and return the duty roster.
+	//
+	// validators query the duty roster of a relay parent in a block built on top
+	// of it, so it is computed from the seed of the current block, which is derived
+	// from the parent's hash. it must be noted before anything in the block changes
+	// the validators or parachains.
+	fn note_roster() -> DutyRoster {
+		let parent_hash = super::System::parent_hash();
+		if let Some((_, duty_roster)) = Self::recent_roster(&parent_hash) {
+			return duty_roster;
+		}
+
+		let duty_roster = Self::calculate_duty_roster();
+		let validators = <session::Module<T>>::validators();
+		let authorities = super::Consensus::authorities();
+
+		// only record the validator set again when it changes.
+		let next_set = <NextValidatorSet<T>>::get();
+		let last_set = next_set.checked_sub(1)
+			.and_then(|idx| Self::validator_set(idx).map(|set| (idx, set)));
+		let set_index = match last_set {
+			Some((idx, ref set)) if set.0 == validators && set.1 == authorities => idx,
+			_ => {
+				<ValidatorSets<T>>::insert(next_set, (validators, authorities));
+				<NextValidatorSet<T>>::put(next_set + 1);
+				next_set
+			}
+		};
+
+		<RecentRosters<T>>::insert(parent_hash, (set_index, duty_roster.clone()));
+
+		let mut parents = <RecentRosterParents<T>>::get();
+		parents.push(parent_hash);
+		if parents.len() > MISBEHAVIOR_REPORT_WINDOW {
+			let expired = parents.remove(0);
+			let expired_set = Self::recent_roster(&expired).map(|(idx, _)| idx);
+			let oldest_set = parents.first().and_then(|p| Self::recent_roster(p)).map(|(idx, _)| idx);
+			if let (Some(expired_set), Some(oldest_set)) = (expired_set, oldest_set) {
+				for idx in expired_set..oldest_set {
+					<ValidatorSets<T>>::remove(idx);
+				}
+			}
+
+			<RecentRosters<T>>::remove(expired);
+			<Punished<T>>::remove(expired);
+		}
+
+		<RecentRosterParents<T>>::put(parents);
+
+		duty_roster
+	}
+
 	// check the attestations on these candidates. The candidates should have been checked
 	// that each candidates' chain ID is valid.
-	fn check_attestations(attested_candidates: &[AttestedCandidate]) -> Result {
+	fn check_attestations(attested_candidates: &[AttestedCandidate], duty_roster: &DutyRoster) -> Result {
 		use primitives::parachain::ValidityAttestation;
 		use sr_primitives::traits::Verify;
 
@@ -295,7 +863,6 @@ impl<T: Trait> Module<T> {
 		}
 
 		let authorities = super::Consensus::authorities();
-		let duty_roster = Self::calculate_duty_roster();
 
 		// convert a duty roster, which is originally a Vec<Chain>, where each
 		// item corresponds to the same position in the session keys, into
@@ -405,35 +972,111 @@ pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"newheads";
 
 pub type InherentType = Vec<AttestedCandidate>;
 
+/// Errors that can occur while checking the parachain heads inherent.
+#[derive(Encode)]
+#[cfg_attr(feature = "std", derive(Debug, Decode))]
+pub enum InherentError {
+	/// The block includes a candidate which is not among the locally known
+	/// attested candidates.
+	UnknownCandidate(ParaId),
+	/// The locally known attested candidates could not be decoded.
+	InvalidInherentData,
+	/// The block does not include the parachain heads inherent.
+	MissingHeads,
+}
+
+impl IsFatalError for InherentError {
+	fn is_fatal_error(&self) -> bool {
+		match *self {
+			InherentError::UnknownCandidate(_) => true,
+			InherentError::InvalidInherentData => true,
+			InherentError::MissingHeads => true,
+		}
+	}
+}
+
+impl InherentError {
+	/// Try to create an instance out of the given identifier and data.
+	#[cfg(feature = "std")]
+	pub fn try_from(id: &InherentIdentifier, data: &[u8]) -> Option<Self> {
+		if id == &INHERENT_IDENTIFIER {
+			<InherentError as Decode>::decode(&mut &data[..])
+		} else {
+			None
+		}
+	}
+}
+
 impl<T: Trait> ProvideInherent for Module<T> {
 	type Call = Call<T>;
-	type Error = MakeFatalError<RuntimeString>;
+	type Error = InherentError;
 	const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
 
 	fn create_inherent(data: &InherentData) -> Option<Self::Call> {
-		let data = data.get_data::<InherentType>(&INHERENT_IDENTIFIER)
-			.expect("Parachain heads could not be decoded.")
-			.expect("No parachain heads found in inherent data.");
+		// without any attested candidates, include none rather than fail to
+		// author the block. candidates which cannot be included on top of the
+		// current state are left out for the same reason.
+		let heads = data.get_data::<InherentType>(&INHERENT_IDENTIFIER)
+			.ok()
+			.and_then(|heads| heads)
+			.unwrap_or_default()
+			.into_iter()
+			.filter(|head| Self::is_includable(head))
+			.collect();
+
+		Some(Call::set_heads(heads))
+	}
 
-		Some(Call::set_heads(data))
+	// the heads are only checked against attested candidates when the checking
+	// node supplies its own, as validators evaluating a proposal do. block import
+	// supplies none, so imported heads are only verified by `set_heads` itself and
+	// the runtime's `check_inherents` only ensures the inherent is present.
+	fn check_inherent(call: &Self::Call, data: &InherentData) -> rstd::result::Result<(), Self::Error> {
+		let heads = match call {
+			Call::set_heads(ref heads) => heads,
+			_ => return Ok(()),
+		};
+
+		let known = match data.get_data::<InherentType>(&INHERENT_IDENTIFIER) {
+			Ok(Some(known)) => known,
+			Ok(None) => return Ok(()),
+			Err(_) => return Err(InherentError::InvalidInherentData),
+		};
+
+		let known_hashes: Vec<_> = known.iter().map(|c| c.candidate.hash()).collect();
+		match heads.iter().find(|head| !known_hashes.contains(&head.candidate.hash())) {
+			Some(unknown) => Err(InherentError::UnknownCandidate(unknown.parachain_index())),
+			None => Ok(()),
+		}
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use codec::Encode;
 	use sr_io::{TestExternalities, with_externalities};
 	use substrate_primitives::{H256, Blake2Hasher};
-	use sr_primitives::{generic, BuildStorage};
-	use sr_primitives::traits::{BlakeTwo256, IdentityLookup};
+	use sr_primitives::{generic, BuildStorage, Perbill};
+	use sr_primitives::traits::{BlakeTwo256, IdentityLookup, OnFinalise};
 	use primitives::{parachain::{CandidateReceipt, HeadData, ValidityAttestation}, SessionKey};
 	use keyring::Keyring;
-	use {consensus, timestamp};
+	use {consensus, timestamp, balances};
 
 	impl_outer_origin! {
 		pub enum Origin for Test {}
 	}
 
+	mod parachains {
+		pub use super::super::*;
+	}
+
+	impl_outer_event! {
+		pub enum TestEvent for Test {
+			balances<T>, session<T>, staking<T>, parachains<T>,
+		}
+	}
+
 	#[derive(Clone, Eq, PartialEq)]
 	pub struct Test;
 	impl consensus::Trait for Test {
@@ -451,21 +1094,47 @@ mod tests {
 		type AccountId = ::AccountId;
 		type Lookup = IdentityLookup<::AccountId>;
 		type Header = ::Header;
-		type Event = ();
+		type Event = TestEvent;
 		type Log = ::Log;
 	}
 	impl session::Trait for Test {
 		type ConvertAccountIdToSessionKey = ::SessionKeyConversion;
 		type OnSessionChange = ();
-		type Event = ();
+		type Event = TestEvent;
 	}
 	impl timestamp::Trait for Test {
 		type Moment = u64;
 		type OnTimestampSet = ();
 	}
-	impl Trait for Test {}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type EnsureAccountLiquid = ();
+		type Event = TestEvent;
+	}
+	impl staking::Trait for Test {
+		type Currency = balances::Module<Test>;
+		type OnRewardMinted = ();
+		type Event = TestEvent;
+	}
+	impl Trait for Test {
+		type Event = TestEvent;
+		type ConvertAccountId = ::AccountIdConversion;
+	}
 
 	type Parachains = Module<Test>;
+	type Balances = balances::Module<Test>;
+
+	// the events of this module deposited so far.
+	fn parachain_events() -> Vec<RawEvent<AccountId>> {
+		system::Module::<Test>::events().into_iter()
+			.filter_map(|record| match record.event {
+				TestEvent::parachains(event) => Some(event),
+				_ => None,
+			})
+			.collect()
+	}
 
 	fn new_test_ext(parachains: Vec<(ParaId, Vec<u8>, Vec<u8>)>) -> TestExternalities<Blake2Hasher> {
 		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
@@ -488,8 +1157,23 @@ mod tests {
 			session_length: 1000,
 			validators: authority_keys.iter().map(|k| k.to_raw_public().into()).collect(),
 		}.build_storage().unwrap().0);
+		t.extend(staking::GenesisConfig::<Test>{
+			current_era: 0,
+			intentions: vec![],
+			minimum_validator_count: 0,
+			validator_count: 8,
+			sessions_per_era: 1000,
+			bonding_duration: 1000,
+			offline_slash: Perbill::zero(),
+			session_reward: Perbill::zero(),
+			current_offline_slash: 10,
+			current_session_reward: 0,
+			offline_slash_grace: 0,
+			invulnerables: vec![],
+		}.build_storage().unwrap().0);
 		t.extend(GenesisConfig::<Test>{
 			parachains: parachains,
+			relay_balances: vec![],
 			_phdata: Default::default(),
 		}.build_storage().unwrap().0);
 		t.into()
@@ -569,6 +1253,44 @@ mod tests {
 
 			assert_eq!(Parachains::active_parachains(), vec![99u32.into(), 100u32.into()]);
 			assert_eq!(Parachains::parachain_code(&5u32.into()), None);
+
+			assert_eq!(parachain_events(), vec![
+				RawEvent::ParachainRegistered(99u32.into()),
+				RawEvent::ParachainDeregistered(5u32.into()),
+			]);
+		});
+	}
+
+	#[test]
+	fn set_collators_works() {
+		let parachains = vec![
+			(5u32.into(), vec![1,2,3], vec![1]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			let id: ParaId = 5u32.into();
+			let (a, b): (AccountId, AccountId) = ([1; 32].into(), [2; 32].into());
+			assert_eq!(Parachains::collators(&id), None);
+
+			assert_ok!(Parachains::set_collators(id, Some(vec![b, a, b])));
+			assert_eq!(Parachains::collators(&id), Some(vec![a, b]));
+
+			assert_ok!(Parachains::set_collators(id, None));
+			assert_eq!(Parachains::collators(&id), None);
+
+			assert!(Parachains::set_collators(6u32.into(), Some(vec![a])).is_err());
+
+			// whitelists are cleared with the parachain.
+			assert_ok!(Parachains::set_collators(id, Some(vec![a])));
+			assert_ok!(Parachains::deregister_parachain(id));
+			assert_eq!(Parachains::collators(&id), None);
+
+			assert_eq!(parachain_events(), vec![
+				RawEvent::CollatorsChanged(id),
+				RawEvent::CollatorsChanged(id),
+				RawEvent::CollatorsChanged(id),
+				RawEvent::ParachainDeregistered(id),
+			]);
 		});
 	}
 
@@ -781,6 +1503,14 @@ mod tests {
 				Some(vec![(1.into(), [1; 32].into())]),
 			);
 
+			let head_hash = BlakeTwo256::hash(&[1, 2, 3]);
+			assert_eq!(parachain_events(), vec![
+				RawEvent::CandidateIncluded(0.into(), head_hash, Default::default()),
+				RawEvent::MessageRouted(0.into(), 1.into(), [1; 32].into()),
+				RawEvent::CandidateIncluded(1.into(), head_hash, Default::default()),
+				RawEvent::MessageRouted(1.into(), 99.into(), [1; 32].into()),
+			]);
+
 			assert_ok!(Parachains::deregister_parachain(1u32.into()));
 
 			// after deregistering, there is no ingress to 1 and we stop routing
@@ -789,4 +1519,559 @@ mod tests {
 			assert_eq!(Parachains::ingress(ParaId::from(99)), Some(Vec::new()));
 		});
 	}
+
+	fn make_candidate(id: u32, egress_queue_roots: Vec<(ParaId, Hash)>, fees: u64) -> AttestedCandidate {
+		let mut candidate = AttestedCandidate {
+			validity_votes: vec![],
+			candidate: CandidateReceipt {
+				parachain_index: id.into(),
+				collator: Default::default(),
+				signature: Default::default(),
+				head_data: HeadData(vec![1, 2, 3]),
+				balance_uploads: vec![],
+				egress_queue_roots,
+				fees,
+				block_data_hash: Default::default(),
+			}
+		};
+
+		make_attestations(&mut candidate);
+		candidate
+	}
+
+	#[test]
+	fn validate_root_call_caps_sizes() {
+		with_externalities(&mut new_test_ext(vec![]), || {
+			let id: ParaId = 5u32.into();
+			let reject = |call: Call<Test>, reason: InvalidCall| match Parachains::validate_root_call(&call) {
+				TransactionValidity::Invalid(e) => assert_eq!(e, reason as i8),
+				_ => panic!("call should be rejected"),
+			};
+
+			reject(Call::register_parachain(id, vec![0; MAX_CODE_SIZE + 1], vec![]), InvalidCall::CodeTooLarge);
+			reject(Call::register_parachain(id, vec![], vec![0; MAX_HEAD_DATA_SIZE + 1]), InvalidCall::HeadDataTooLarge);
+			assert!(Parachains::register_parachain(id, vec![0; MAX_CODE_SIZE + 1], vec![]).is_err());
+
+			match Parachains::validate_root_call(&Call::register_parachain(id, vec![0; MAX_CODE_SIZE], vec![])) {
+				TransactionValidity::Valid { priority, provides, .. } => {
+					assert_eq!(priority, PARACHAIN_CALL_PRIORITY);
+					assert_eq!(provides, vec![(b"parachains", id).encode()]);
+				}
+				_ => panic!("registration should be valid"),
+			}
+		});
+	}
+
+	#[test]
+	fn validate_root_call_rejects_unknown_and_registered_parachains() {
+		let parachains = vec![(5u32.into(), vec![], vec![])];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			let reject = |call: Call<Test>, reason: InvalidCall| match Parachains::validate_root_call(&call) {
+				TransactionValidity::Invalid(e) => assert_eq!(e, reason as i8),
+				_ => panic!("call should be rejected"),
+			};
+
+			reject(Call::register_parachain(5u32.into(), vec![], vec![]), InvalidCall::AlreadyRegistered);
+			reject(Call::deregister_parachain(6u32.into()), InvalidCall::UnknownParachain);
+			reject(Call::set_collators(6u32.into(), None), InvalidCall::UnknownParachain);
+			reject(Call::set_heads(vec![]), InvalidCall::BadOrigin);
+			reject(Call::fund_parachain(5u32.into(), 10), InvalidCall::BadOrigin);
+
+			for call in vec![Call::deregister_parachain(5u32.into()), Call::set_collators(5u32.into(), None)] {
+				match Parachains::validate_root_call(&call) {
+					TransactionValidity::Valid { provides, .. } =>
+						assert_eq!(provides, vec![(b"parachains", ParaId::from(5u32)).encode()]),
+					_ => panic!("root call should be valid"),
+				}
+			}
+		});
+	}
+
+	#[test]
+	fn validate_call_rejects_root_calls_and_inherents() {
+		let parachains = vec![(5u32.into(), vec![], vec![])];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			let reject = |call: Call<Test>, reason: InvalidCall| match Parachains::validate_call(&call) {
+				Some(TransactionValidity::Invalid(e)) => assert_eq!(e, reason as i8),
+				_ => panic!("call should be rejected"),
+			};
+
+			// root calls signed directly cannot claim the parachain's tag.
+			reject(Call::register_parachain(6u32.into(), vec![], vec![]), InvalidCall::BadOrigin);
+			reject(Call::deregister_parachain(5u32.into()), InvalidCall::BadOrigin);
+			reject(Call::set_collators(5u32.into(), None), InvalidCall::BadOrigin);
+			reject(Call::set_heads(vec![]), InvalidCall::BadOrigin);
+			reject(Call::fund_parachain(6u32.into(), 10), InvalidCall::UnknownParachain);
+
+			// funding a registered parachain is an ordinary signed transaction.
+			assert!(Parachains::validate_call(&Call::fund_parachain(5u32.into(), 10)).is_none());
+		});
+	}
+
+	#[test]
+	fn storage_keys_match_proof_verifier() {
+		let (a, b): (ParaId, ParaId) = (5u32.into(), 6u32.into());
+		assert_eq!(Parachains::head_storage_key(&a), ::primitives::proof::head_storage_key(a));
+		assert_eq!(Parachains::routing_storage_key(a, b), ::primitives::proof::routing_storage_key(a, b));
+
+		let parachains = vec![(a, vec![], vec![1, 2, 3])];
+		with_externalities(&mut new_test_ext(parachains), || {
+			let head = sr_io::storage(&Parachains::head_storage_key(&a)).unwrap();
+			assert_eq!(head, vec![1u8, 2, 3].encode());
+		});
+	}
+
+	#[test]
+	fn ingress_queues_until_processed() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![]),
+			(1u32.into(), vec![], vec![]),
+			(2u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let root_a: Hash = [1; 32].into();
+			let root_b: Hash = [2; 32].into();
+
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(0, vec![(2.into(), root_a)], 0)]),
+				Origin::INHERENT,
+			));
+			<DidUpdate<Test>>::kill();
+
+			system::Module::<Test>::set_block_number(2);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(0, vec![(2.into(), root_b)], 0)]),
+				Origin::INHERENT,
+			));
+			<DidUpdate<Test>>::kill();
+
+			// the second root does not overwrite the first.
+			assert_eq!(
+				Parachains::ingress(ParaId::from(2)),
+				Some(vec![(0.into(), root_a), (0.into(), root_b)]),
+			);
+
+			// inclusion of the receiver pops everything routed before its relay parent.
+			system::Module::<Test>::set_block_number(3);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(2, vec![], 0)]),
+				Origin::INHERENT,
+			));
+
+			assert_eq!(Parachains::ingress(ParaId::from(2)), Some(Vec::new()));
+			assert_eq!(Parachains::watermark(ParaId::from(2)), Some(2));
+		});
+	}
+
+	#[test]
+	fn routing_to_full_queue_is_skipped() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![]),
+			(1u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let queued = (0..MAX_QUEUED_ROOTS).map(|i| (1, [i as u8; 32].into())).collect::<Vec<_>>();
+			<RoutingQueue<Test>>::insert((ParaId::from(0), ParaId::from(1)), queued);
+
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(0, vec![(1.into(), [0xff; 32].into())], 0)]),
+				Origin::INHERENT,
+			));
+
+			assert_eq!(Parachains::routing_queue((ParaId::from(0), ParaId::from(1))).len(), MAX_QUEUED_ROOTS);
+			assert_eq!(Parachains::parachain_head(&ParaId::from(0)), Some(vec![]));
+		});
+	}
+
+	#[test]
+	fn fees_are_charged_from_relay_balance() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![]),
+			(1u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+
+			// an underfunded candidate is skipped.
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(0, vec![], 10)]),
+				Origin::INHERENT,
+			));
+			assert_eq!(Parachains::parachain_head(&ParaId::from(0)), Some(vec![]));
+			<DidUpdate<Test>>::kill();
+
+			<RelayBalances<Test>>::insert(ParaId::from(0), 15);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(0, vec![], 10)]),
+				Origin::INHERENT,
+			));
+
+			assert_eq!(Parachains::relay_balance(ParaId::from(0)), 5);
+			assert_eq!(Parachains::parachain_head(&ParaId::from(0)), Some(vec![1, 2, 3]));
+		});
+	}
+
+	#[test]
+	fn parachains_are_funded_from_accounts() {
+		let parachains = vec![(0u32.into(), vec![], vec![])];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			let funder: AccountId = [7; 32].into();
+			Balances::increase_free_balance_creating(&funder, 100);
+			Balances::increase_total_stake_by(100);
+
+			assert!(Parachains::dispatch(Call::fund_parachain(1.into(), 10), Origin::signed(funder)).is_err());
+			assert!(Parachains::dispatch(Call::fund_parachain(0.into(), 101), Origin::signed(funder)).is_err());
+			assert!(Parachains::dispatch(Call::fund_parachain(0.into(), 10), Origin::INHERENT).is_err());
+
+			assert_ok!(Parachains::dispatch(Call::fund_parachain(0.into(), 60), Origin::signed(funder)));
+			assert_eq!(Parachains::relay_balance(ParaId::from(0)), 60);
+			assert_eq!(Balances::free_balance(&funder), 40);
+			assert_eq!(parachain_events(), vec![RawEvent::ParachainFunded(0.into(), funder, 60)]);
+
+			// the funds left circulation with the funder's balance.
+			assert_eq!(Balances::total_issuance(), 40);
+		});
+	}
+
+	#[test]
+	fn relay_balances_set_at_genesis() {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(GenesisConfig::<Test>{
+			parachains: vec![(0u32.into(), vec![], vec![])],
+			relay_balances: vec![(0u32.into(), 500)],
+			_phdata: Default::default(),
+		}.build_storage().unwrap().0);
+
+		with_externalities(&mut TestExternalities::<Blake2Hasher>::from(t), || {
+			assert_eq!(Parachains::relay_balance(ParaId::from(0)), 500);
+			assert_eq!(Parachains::relay_balance(ParaId::from(1)), 0);
+		});
+	}
+
+	#[test]
+	fn balance_uploads_are_credited_and_fees_paid_to_attesters() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![]),
+			(1u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let recipient: AccountId = [42; 32].into();
+
+			let mut candidate = make_candidate(0, vec![], 10);
+			candidate.candidate.balance_uploads = vec![(recipient, 20)];
+			candidate.validity_votes.clear();
+			make_attestations(&mut candidate);
+
+			// not enough to cover both the fees and the upload.
+			<RelayBalances<Test>>::insert(ParaId::from(0), 25);
+			assert!(Parachains::dispatch(
+				Call::set_heads(vec![candidate.clone()]),
+				Origin::INHERENT,
+			).is_err());
+			assert_eq!(Parachains::relay_balance(ParaId::from(0)), 25);
+			assert_eq!(Balances::free_balance(&recipient), 0);
+
+			<RelayBalances<Test>>::insert(ParaId::from(0), 100);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![candidate.clone()]),
+				Origin::INHERENT,
+			));
+
+			assert_eq!(Parachains::relay_balance(ParaId::from(0)), 70);
+			assert_eq!(Balances::free_balance(&recipient), 20);
+
+			let paid: u64 = candidate.validity_votes.iter()
+				.map(|(key, _)| Balances::free_balance(&AccountId::from(key.0)))
+				.sum();
+			assert_eq!(paid, 10);
+
+			// funds paid out of the relay chain balance enter circulation.
+			assert_eq!(Balances::total_issuance(), 30);
+		});
+	}
+
+	#[test]
+	fn head_history_is_kept() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![0]),
+			(1u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let id = ParaId::from(0);
+
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![make_candidate(0, vec![], 0)]),
+				Origin::INHERENT,
+			));
+			<DidUpdate<Test>>::kill();
+
+			let mut candidate = make_candidate(0, vec![], 0);
+			candidate.candidate.head_data = HeadData(vec![4]);
+			candidate.validity_votes.clear();
+			make_attestations(&mut candidate);
+
+			system::Module::<Test>::set_block_number(5);
+			assert_ok!(Parachains::dispatch(
+				Call::set_heads(vec![candidate]),
+				Origin::INHERENT,
+			));
+
+			assert_eq!(Parachains::parachain_head_at(&id, 0), Some(vec![0]));
+			assert_eq!(Parachains::parachain_head_at(&id, 1), Some(vec![1, 2, 3]));
+			assert_eq!(Parachains::parachain_head_at(&id, 4), Some(vec![1, 2, 3]));
+			assert_eq!(Parachains::parachain_head_at(&id, 5), Some(vec![4]));
+			assert_eq!(Parachains::parachain_head_at(&id, 100), Some(vec![4]));
+			assert_eq!(
+				Parachains::recent_heads(&id, 2),
+				vec![(5, vec![4]), (1, vec![1, 2, 3])],
+			);
+
+			// the history is bounded, forgetting the oldest heads.
+			for n in 6..(6 + HEAD_HISTORY_LENGTH as u64) {
+				Parachains::note_head(id, n, vec![n as u8]);
+			}
+
+			assert_eq!(Parachains::head_history(&id).len(), HEAD_HISTORY_LENGTH as usize);
+			assert_eq!(Parachains::recent_heads(&id, 1), vec![(261, vec![261u64 as u8])]);
+			assert_eq!(Parachains::parachain_head_at(&id, 5), None);
+			assert_eq!(Parachains::parachain_head_at(&id, 6), Some(vec![6]));
+
+			assert_ok!(Parachains::deregister_parachain(id));
+			assert!(Parachains::head_history(&id).is_empty());
+		});
+	}
+
+	#[test]
+	fn missing_inherent_data_yields_empty_heads() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			let call = <Parachains as ProvideInherent>::create_inherent(&InherentData::new());
+			assert_eq!(call, Some(Call::set_heads(Vec::new())));
+
+			// finalising a block without the inherent does not panic.
+			<Parachains as OnFinalise<u64>>::on_finalise(1);
+			assert!(!<DidUpdate<Test>>::exists());
+		});
+	}
+
+	#[test]
+	fn unincludable_candidates_left_out_of_inherent() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![]),
+			(1u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			<RelayBalances<Test>>::insert(ParaId::from(0), 15);
+
+			let funded = make_candidate(0, vec![], 10);
+			let underfunded = make_candidate(1, vec![], 10);
+
+			let mut data = InherentData::new();
+			data.put_data(INHERENT_IDENTIFIER, &vec![funded.clone(), underfunded]).unwrap();
+
+			let call = <Parachains as ProvideInherent>::create_inherent(&data).unwrap();
+			assert_eq!(call, Call::set_heads(vec![funded]));
+
+			// the block is authored with the remaining candidate.
+			assert_ok!(Parachains::dispatch(call, Origin::INHERENT));
+			assert_eq!(Parachains::relay_balance(ParaId::from(0)), 5);
+		});
+	}
+
+	#[test]
+	fn check_inherent_rejects_unknown_candidates() {
+		let parachains = vec![
+			(0u32.into(), vec![], vec![]),
+			(1u32.into(), vec![], vec![]),
+		];
+
+		with_externalities(&mut new_test_ext(parachains), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let known = make_candidate(0, vec![], 0);
+			let unknown = make_candidate(1, vec![], 0);
+
+			let mut data = InherentData::new();
+			data.put_data(INHERENT_IDENTIFIER, &vec![known.clone()]).unwrap();
+
+			assert!(<Parachains as ProvideInherent>::check_inherent(
+				&Call::set_heads(vec![known.clone()]),
+				&data,
+			).is_ok());
+
+			match <Parachains as ProvideInherent>::check_inherent(
+				&Call::set_heads(vec![known.clone(), unknown.clone()]),
+				&data,
+			) {
+				Err(InherentError::UnknownCandidate(id)) => assert_eq!(id, 1.into()),
+				other => panic!("unexpected check result: {:?}", other),
+			}
+			assert!(InherentError::UnknownCandidate(1.into()).is_fatal_error());
+			assert!(InherentError::MissingHeads.is_fatal_error());
+
+			// nothing to check against without local candidates.
+			assert!(<Parachains as ProvideInherent>::check_inherent(
+				&Call::set_heads(vec![known, unknown]),
+				&InherentData::new(),
+			).is_ok());
+		});
+	}
+
+	fn sign_statement(key: Keyring, statement: Statement) -> CandidateSignature {
+		key.sign(&localized_payload(statement, ::System::parent_hash())[..]).into()
+	}
+
+	#[test]
+	fn double_vote_report_is_accepted_once() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			Parachains::note_roster();
+
+			let candidate_hash: Hash = [1; 32].into();
+			let valid = Statement::Valid(candidate_hash);
+			let invalid = Statement::Invalid(candidate_hash);
+			let report = MisbehaviorReport {
+				parent_hash: ::System::parent_hash(),
+				offender: Keyring::Alice.to_raw_public().into(),
+				proof: MisbehaviorProof::ValidityDoubleVote(
+					valid.clone(),
+					sign_statement(Keyring::Alice, valid),
+					invalid.clone(),
+					sign_statement(Keyring::Alice, invalid),
+				),
+			};
+
+			let offender: ::AccountId = Keyring::Alice.to_raw_public().into();
+			Balances::set_free_balance(&offender, 1000);
+
+			assert!(Parachains::check_misbehavior_report(&report).is_ok());
+			assert_ok!(Parachains::dispatch(
+				Call::report_misbehavior(report.clone()),
+				Origin::INHERENT,
+			));
+
+			assert_eq!(Parachains::punished(&report.parent_hash), vec![report.offender]);
+			assert_eq!(Balances::free_balance(&offender), 1000 - 10 * MISBEHAVIOR_SLASH_FACTOR);
+			assert!(Parachains::dispatch(
+				Call::report_misbehavior(report),
+				Origin::INHERENT,
+			).is_err());
+		});
+	}
+
+	#[test]
+	fn report_with_bad_signature_is_rejected() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			Parachains::note_roster();
+
+			let candidate_hash: Hash = [1; 32].into();
+			let valid = Statement::Valid(candidate_hash);
+			let invalid = Statement::Invalid(candidate_hash);
+
+			// Bob signed one of the statements, not Alice.
+			let report = MisbehaviorReport {
+				parent_hash: ::System::parent_hash(),
+				offender: Keyring::Alice.to_raw_public().into(),
+				proof: MisbehaviorProof::ValidityDoubleVote(
+					valid.clone(),
+					sign_statement(Keyring::Alice, valid),
+					invalid.clone(),
+					sign_statement(Keyring::Bob, invalid),
+				),
+			};
+
+			assert!(Parachains::dispatch(
+				Call::report_misbehavior(report),
+				Origin::INHERENT,
+			).is_err());
+		});
+	}
+
+	#[test]
+	fn unauthorized_candidate_report_is_accepted() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let duty_roster = Parachains::note_roster();
+
+			// a validator not assigned to parachain 0 signing one of its candidates.
+			let authorities = ::Consensus::authorities();
+			let offender_idx = duty_roster.validator_duty.iter()
+				.position(|duty| duty != &Chain::Parachain(0.into()))
+				.unwrap();
+			let offender = Keyring::from_raw_public(authorities[offender_idx].0).unwrap();
+
+			let statement = Statement::Candidate(make_candidate(0, vec![], 0).candidate);
+			let report = MisbehaviorReport {
+				parent_hash: ::System::parent_hash(),
+				offender: authorities[offender_idx],
+				proof: MisbehaviorProof::UnauthorizedStatement(
+					statement.clone(),
+					sign_statement(offender, statement),
+				),
+			};
+
+			assert!(Parachains::check_misbehavior_report(&report).is_ok());
+		});
+	}
+
+	#[test]
+	fn rosters_share_validator_sets_until_expired() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			for i in 0..(MISBEHAVIOR_REPORT_WINDOW + 1) {
+				system::Module::<Test>::set_parent_hash([i as u8 + 1; 32].into());
+				Parachains::note_roster();
+			}
+
+			// the validators did not change, so all rosters refer to the first set.
+			assert!(Parachains::recent_roster(&H256::from([1; 32])).is_none());
+			assert_eq!(Parachains::recent_roster(&H256::from([2; 32])).map(|(idx, _)| idx), Some(0));
+			assert!(Parachains::validator_set(0).is_some());
+			assert!(Parachains::validator_set(1).is_none());
+		});
+	}
+
+	#[test]
+	fn roster_noted_when_heads_set() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			system::Module::<Test>::set_random_seed([0u8; 32].into());
+			let duty_roster = Parachains::calculate_duty_roster();
+
+			assert_ok!(Parachains::dispatch(Call::set_heads(vec![]), Origin::INHERENT));
+			assert_eq!(Parachains::recent_roster(&::System::parent_hash()), Some((0, duty_roster)));
+		});
+	}
+
+	#[test]
+	fn report_for_unknown_parent_is_rejected() {
+		with_externalities(&mut new_test_ext(vec![(0u32.into(), vec![], vec![])]), || {
+			let statement = Statement::Valid([1; 32].into());
+			let report = MisbehaviorReport {
+				parent_hash: [2; 32].into(),
+				offender: Keyring::Alice.to_raw_public().into(),
+				proof: MisbehaviorProof::DoubleSign(
+					statement.clone(),
+					sign_statement(Keyring::Alice, statement.clone()),
+					sign_statement(Keyring::Alice, statement),
+				),
+			};
+
+			assert!(Parachains::check_misbehavior_report(&report).is_err());
+		});
+	}
 }
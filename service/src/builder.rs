@@ -0,0 +1,215 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Typed configuration of a node, for embedding it without command line arguments.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use network::NonReservedPeerMode;
+use polkadot_primitives::parachain;
+use primitives::ed25519;
+use service::Roles;
+
+use chain_spec::ChainSpec;
+use {Configuration, CustomConfiguration};
+
+/// Default p2p port of a node.
+pub const DEFAULT_PORT: u16 = 30333;
+
+/// Builder of the configuration of a node.
+///
+/// The node's data is kept under `chains/<chain id>` of its base path, as when
+/// it is configured from the command line.
+pub struct NodeBuilder {
+	chain_spec: ChainSpec,
+	base_path: PathBuf,
+	name: Option<String>,
+	port: u16,
+	isolated: bool,
+	rpc_http: Option<SocketAddr>,
+	rpc_ws: Option<SocketAddr>,
+	bootnodes: Vec<String>,
+	keys: Vec<String>,
+	roles: Roles,
+	custom: CustomConfiguration,
+}
+
+impl NodeBuilder {
+	/// Start building the configuration of a full node of the given chain,
+	/// keeping its data under `base_path`.
+	pub fn new<P: Into<PathBuf>>(chain_spec: ChainSpec, base_path: P) -> Self {
+		NodeBuilder {
+			chain_spec,
+			base_path: base_path.into(),
+			name: None,
+			port: DEFAULT_PORT,
+			isolated: false,
+			rpc_http: None,
+			rpc_ws: None,
+			bootnodes: Vec::new(),
+			keys: Vec::new(),
+			roles: Roles::FULL,
+			custom: CustomConfiguration::default(),
+		}
+	}
+
+	/// Set the human-readable name of the node.
+	pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	/// Set the port to listen for p2p connections on.
+	pub fn port(mut self, port: u16) -> Self {
+		self.port = port;
+		self
+	}
+
+	/// Only listen on the loopback interface and only connect to reserved peers,
+	/// so that the node is reachable by nodes of the same process or machine which
+	/// reserve it, and by no others.
+	pub fn isolated(mut self) -> Self {
+		self.isolated = true;
+		self
+	}
+
+	/// Serve the HTTP RPC API on the given address. The Polkadot-specific RPC API
+	/// is served on the next port, unless configured otherwise.
+	pub fn rpc_http(mut self, addr: SocketAddr) -> Self {
+		self.rpc_http = Some(addr);
+		self
+	}
+
+	/// Serve the WebSockets RPC API on the given address.
+	pub fn rpc_ws(mut self, addr: SocketAddr) -> Self {
+		self.rpc_ws = Some(addr);
+		self
+	}
+
+	/// Add a node to connect to on startup, as a multiaddress ending with its peer id.
+	pub fn bootnode<S: Into<String>>(mut self, bootnode: S) -> Self {
+		self.bootnodes.push(bootnode.into());
+		self
+	}
+
+	/// Add a key seed to the keystore, e.g. `Alice`. The first is used to author blocks
+	/// when the node is an authority.
+	pub fn key<S: Into<String>>(mut self, seed: S) -> Self {
+		self.keys.push(seed.into());
+		self
+	}
+
+	/// Set the roles of the node.
+	pub fn roles(mut self, roles: Roles) -> Self {
+		self.roles = roles;
+		self
+	}
+
+	/// Set the Polkadot-specific configuration.
+	pub fn custom(mut self, custom: CustomConfiguration) -> Self {
+		self.custom = custom;
+		self
+	}
+
+	/// Run the network protocol in collator mode for the given parachain, with the
+	/// given collator key.
+	pub fn collating_for(mut self, collator: Arc<ed25519::Pair>, para_id: parachain::Id) -> Self {
+		self.custom.collating_for = Some((collator, para_id));
+		self
+	}
+
+	/// Build the configuration of the node.
+	pub fn build(self) -> Result<Configuration, String> {
+		let chain_path = self.base_path.join("chains").join(self.chain_spec.id());
+		let listen_ip = if self.isolated { "127.0.0.1" } else { "0.0.0.0" };
+		let listen_address = format!("/ip4/{}/tcp/{}", listen_ip, self.port).parse()
+			.map_err(|e| format!("Invalid p2p port {}: {:?}", self.port, e))?;
+
+		let mut config = Configuration::default_with_spec(self.chain_spec);
+		if let Some(name) = self.name {
+			config.name = name;
+		}
+		config.roles = self.roles;
+		config.keys = self.keys;
+		config.keystore_path = chain_path.join("keystore").to_string_lossy().into();
+		config.database_path = chain_path.join("db").to_string_lossy().into();
+		config.network.config_path = Some(chain_path.join("network").to_string_lossy().into());
+		config.network.listen_addresses = vec![listen_address];
+		config.network.boot_nodes = self.bootnodes;
+		if self.isolated {
+			config.network.non_reserved_mode = NonReservedPeerMode::Deny;
+		}
+		config.rpc_http = self.rpc_http;
+		config.rpc_ws = self.rpc_ws;
+
+		config.custom = self.custom;
+		if config.custom.parachain_rpc_http.is_none() {
+			config.custom.parachain_rpc_http = default_parachain_rpc_http(config.rpc_http);
+		}
+
+		Ok(config)
+	}
+}
+
+/// The default address of the Polkadot-specific RPC API: the port after the
+/// substrate HTTP RPC port.
+pub fn default_parachain_rpc_http(rpc_http: Option<SocketAddr>) -> Option<SocketAddr> {
+	rpc_http.and_then(|mut addr| {
+		addr.set_port(addr.port().checked_add(1)?);
+		Some(addr)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chain_spec;
+
+	#[test]
+	fn builds_configuration() {
+		let config = NodeBuilder::new(chain_spec::development_config(), "/tmp/polkadot")
+			.name("collator")
+			.port(30334)
+			.rpc_http("127.0.0.1:9933".parse().unwrap())
+			.bootnode("/ip4/127.0.0.1/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV")
+			.key("Alice")
+			.roles(Roles::AUTHORITY)
+			.build()
+			.unwrap();
+
+		assert_eq!(config.name, "collator");
+		assert_eq!(config.roles, Roles::AUTHORITY);
+		assert_eq!(config.keys, vec!["Alice".to_string()]);
+		assert_eq!(config.database_path, "/tmp/polkadot/chains/development/db");
+		assert_eq!(config.network.boot_nodes.len(), 1);
+		assert_eq!(config.custom.parachain_rpc_http, Some("127.0.0.1:9934".parse().unwrap()));
+		assert_eq!(config.network.non_reserved_mode, NonReservedPeerMode::Accept);
+	}
+
+	#[test]
+	fn isolated_nodes_listen_locally_to_reserved_peers() {
+		let config = NodeBuilder::new(chain_spec::development_config(), "/tmp/polkadot")
+			.port(0)
+			.isolated()
+			.build()
+			.unwrap();
+
+		assert_eq!(config.network.listen_addresses, vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()]);
+		assert_eq!(config.network.non_reserved_mode, NonReservedPeerMode::Deny);
+	}
+}
@@ -16,12 +16,17 @@
 
 //! Polkadot chain configurations.
 
-use primitives::{H256, Ed25519AuthorityId as AuthorityId, ed25519};
+use std::{env, fs, process};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use primitives::{H256, Bytes, Ed25519AuthorityId as AuthorityId, ed25519};
+use polkadot_primitives::parachain::Id as ParaId;
 use polkadot_runtime::{
 	GenesisConfig, ConsensusConfig, CouncilSeatsConfig, DemocracyConfig, TreasuryConfig,
 	SessionConfig, StakingConfig, TimestampConfig, BalancesConfig, Perbill,
 	CouncilVotingConfig, GrandpaConfig, UpgradeKeyConfig, SudoConfig, IndicesConfig,
-	ClaimsConfig, FeesConfig, Permill
+	ClaimsConfig, FeesConfig, Permill, ParachainsConfig,
 };
 
 const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
@@ -131,6 +136,8 @@ fn staging_testnet_config_genesis() -> GenesisConfig {
 		}),
 		claims: Some(ClaimsConfig {
 			claims: vec![],
+			vesting: vec![],
+			signing: vec![],
 		}),
 		fees: Some(FeesConfig {
 			transaction_base_fee: 1 * CENTS,
@@ -154,7 +161,11 @@ pub fn staging_testnet_config() -> ChainSpec {
 	)
 }
 
-fn testnet_genesis(initial_authorities: Vec<AuthorityId>, upgrade_key: H256) -> GenesisConfig {
+fn testnet_genesis(
+	initial_authorities: Vec<AuthorityId>,
+	upgrade_key: H256,
+	parachains: Vec<(ParaId, Vec<u8>, Vec<u8>)>,
+) -> GenesisConfig {
 	let endowed_accounts = vec![
 		ed25519::Pair::from_seed(b"Alice                           ").public().0.into(),
 		ed25519::Pair::from_seed(b"Bob                             ").public().0.into(),
@@ -224,7 +235,11 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, upgrade_key: H256) ->
 			voting_period: 20,
 			enact_delay_period: 0,
 		}),
-		parachains: Some(Default::default()),
+		parachains: Some(ParachainsConfig {
+			parachains,
+			relay_balances: vec![],
+			_phdata: Default::default(),
+		}),
 		timestamp: Some(TimestampConfig {
 			period: 2,					// 2*2=4 second block time.
 		}),
@@ -237,6 +252,8 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, upgrade_key: H256) ->
 		}),
 		claims: Some(ClaimsConfig {
 			claims: vec![],
+			vesting: vec![],
+			signing: vec![],
 		}),
 		fees: Some(FeesConfig {
 			transaction_base_fee: 1,
@@ -250,7 +267,8 @@ fn development_config_genesis() -> GenesisConfig {
 		vec![
 			ed25519::Pair::from_seed(b"Alice                           ").public().into(),
 		],
-		ed25519::Pair::from_seed(b"Alice                           ").public().0.into()
+		ed25519::Pair::from_seed(b"Alice                           ").public().0.into(),
+		vec![],
 	)
 }
 
@@ -274,7 +292,8 @@ fn local_testnet_genesis() -> GenesisConfig {
 			ed25519::Pair::from_seed(b"Alice                           ").public().into(),
 			ed25519::Pair::from_seed(b"Bob                             ").public().into(),
 		],
-		ed25519::Pair::from_seed(b"Alice                           ").public().0.into()
+		ed25519::Pair::from_seed(b"Alice                           ").public().0.into(),
+		vec![],
 	)
 }
 
@@ -291,3 +310,186 @@ pub fn local_testnet_config() -> ChainSpec {
 		None,
 	)
 }
+
+/// A parachain registered at the genesis of a testnet described in a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisParachain {
+	/// The parachain's id.
+	pub id: u32,
+	/// Path to the parachain's Wasm code, relative to the testnet file.
+	pub wasm: PathBuf,
+	/// The parachain's genesis head data, hex-encoded.
+	pub genesis_head: Bytes,
+}
+
+/// A local testnet described in a JSON or TOML file, so that testnets with
+/// several parachains can be started without recompiling.
+///
+/// Its chain spec is built like the `local` one, but with the given name,
+/// authorities and parachains. It can be exported with `build-spec`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestnetSpec {
+	/// Human-readable name of the chain.
+	pub name: String,
+	/// Identifier of the chain.
+	pub id: String,
+	/// Names of the well-known keys of the initial authorities, e.g. `Alice`.
+	/// The first also holds the upgrade and sudo keys.
+	pub authorities: Vec<String>,
+	/// Parachains registered at genesis.
+	pub parachains: Vec<GenesisParachain>,
+}
+
+impl TestnetSpec {
+	/// Read a testnet description from a JSON file, or a TOML file if it has
+	/// the `toml` extension.
+	pub fn from_file(path: &Path) -> Result<Self, String> {
+		let contents = fs::read_to_string(path)
+			.map_err(|e| format!("Error reading testnet spec {}: {}", path.display(), e))?;
+
+		match path.extension().and_then(|e| e.to_str()) {
+			Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string()),
+			_ => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+		}.map_err(|e| format!("Error parsing testnet spec {}: {}", path.display(), e))
+	}
+
+	/// Build the chain spec of the testnet, loading the code of its parachains
+	/// from paths relative to `base`.
+	pub fn into_chain_spec(self, base: &Path) -> Result<ChainSpec, String> {
+		if self.authorities.is_empty() {
+			return Err("Testnet spec must have at least one authority".into());
+		}
+
+		let authorities = self.authorities.iter()
+			.map(|name| authority_seed(name).map(|seed| ed25519::Pair::from_seed(&seed).public()))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let mut parachains = Vec::with_capacity(self.parachains.len());
+		for para in self.parachains {
+			if parachains.iter().any(|&(id, _, _)| id == ParaId::from(para.id)) {
+				return Err(format!("Parachain {} is listed more than once", para.id));
+			}
+
+			let path = base.join(&para.wasm);
+			let code = fs::read(&path)
+				.map_err(|e| format!("Error reading code of parachain {} from {}: {}", para.id, path.display(), e))?;
+			parachains.push((para.id.into(), code, para.genesis_head.0));
+		}
+
+		let upgrade_key: H256 = authorities[0].0.into();
+		let authorities: Vec<AuthorityId> = authorities.into_iter().map(Into::into).collect();
+
+		// `ChainSpec::from_genesis` takes no state, so the genesis is built here
+		// and loaded from a chain spec file instead.
+		let spec = json!({
+			"name": self.name,
+			"id": self.id,
+			"bootNodes": [],
+			"protocolId": DEFAULT_PROTOCOL_ID,
+			"genesis": {
+				"runtime": testnet_genesis(authorities, upgrade_key, parachains),
+			},
+		});
+		let spec = serde_json::to_vec(&spec)
+			.map_err(|e| format!("Error serializing testnet chain spec: {}", e))?;
+
+		ChainSpec::from_json_file(write_generated_spec(&spec)?)
+	}
+}
+
+// Write a generated chain spec to the temporary directory, named after its
+// contents so that nodes loading the same testnet share the file.
+fn write_generated_spec(spec: &[u8]) -> Result<PathBuf, String> {
+	use std::collections::hash_map::DefaultHasher;
+	use std::hash::Hasher;
+
+	static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+
+	let mut hasher = DefaultHasher::new();
+	hasher.write(spec);
+	let path = env::temp_dir().join(format!("polkadot-chain-spec-{:016x}.json", hasher.finish()));
+	if path.exists() {
+		return Ok(path);
+	}
+
+	// write to a file of our own first, so the spec is never read half written.
+	let tmp = path.with_extension(format!("{}.{}.tmp", process::id(), NEXT_TMP.fetch_add(1, Ordering::Relaxed)));
+	fs::write(&tmp, spec)
+		.and_then(|_| fs::rename(&tmp, &path))
+		.map_err(|e| format!("Error writing testnet chain spec to {}: {}", path.display(), e))?;
+
+	Ok(path)
+}
+
+/// Load the chain spec of a testnet described in a JSON or TOML file.
+pub fn testnet_config_from_file(path: &Path) -> Result<ChainSpec, String> {
+	let base = path.parent().unwrap_or_else(|| Path::new("."));
+	TestnetSpec::from_file(path)?.into_chain_spec(base)
+}
+
+// The seed of a well-known key: its name padded with spaces to 32 bytes.
+fn authority_seed(name: &str) -> Result<[u8; 32], String> {
+	if name.len() > 32 {
+		return Err(format!("Authority name {} is longer than 32 bytes", name));
+	}
+
+	let mut seed = [b' '; 32];
+	seed[..name.len()].copy_from_slice(name.as_bytes());
+	Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn testnet_spec_from_toml() {
+		let spec: TestnetSpec = toml::from_str(r#"
+			name = "Adder Testnet"
+			id = "adder_testnet"
+			authorities = ["Alice", "Bob"]
+
+			[[parachains]]
+			id = 100
+			wasm = "adder.wasm"
+			genesis_head = "0x0102"
+		"#).unwrap();
+
+		assert_eq!(spec.authorities, vec!["Alice".to_string(), "Bob".to_string()]);
+		assert_eq!(spec.parachains.len(), 1);
+		assert_eq!(spec.parachains[0].id, 100);
+		assert_eq!(spec.parachains[0].genesis_head.0, vec![1, 2]);
+	}
+
+	#[test]
+	fn full_chain_spec_is_not_a_testnet_spec() {
+		assert!(serde_json::from_str::<TestnetSpec>(r#"{
+			"name": "Local Testnet",
+			"id": "local_testnet",
+			"authorities": ["Alice"],
+			"parachains": [],
+			"genesis": {}
+		}"#).is_err());
+	}
+
+	#[test]
+	fn testnet_spec_into_chain_spec() {
+		let spec = TestnetSpec {
+			name: "Adder Testnet".into(),
+			id: "adder_testnet".into(),
+			authorities: vec!["Alice".into(), "Bob".into()],
+			parachains: vec![],
+		}.into_chain_spec(Path::new(".")).unwrap();
+
+		assert_eq!(spec.name(), "Adder Testnet");
+		assert_eq!(spec.id(), "adder_testnet");
+	}
+
+	#[test]
+	fn authority_seeds_are_padded() {
+		assert_eq!(&authority_seed("Alice").unwrap(), b"Alice                           ");
+		assert!(authority_seed(&"A".repeat(33)).is_err());
+	}
+}
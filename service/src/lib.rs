@@ -24,27 +24,38 @@ extern crate polkadot_primitives;
 extern crate polkadot_runtime;
 extern crate polkadot_executor;
 extern crate polkadot_network;
+extern crate polkadot_rpc;
 extern crate sr_primitives;
 extern crate substrate_primitives as primitives;
 extern crate substrate_client as client;
 #[macro_use]
 extern crate substrate_service as service;
+extern crate substrate_network as network;
 extern crate substrate_consensus_aura as aura;
 extern crate substrate_finality_grandpa as grandpa;
 extern crate substrate_transaction_pool as transaction_pool;
 extern crate tokio;
 extern crate substrate_inherents as inherents;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate toml;
 
 #[macro_use]
 extern crate log;
 #[macro_use]
 extern crate hex_literal;
+#[macro_use]
+extern crate serde_derive;
 
 pub mod chain_spec;
+mod builder;
 
+use std::io;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
-use polkadot_primitives::{parachain, AccountId, Block};
+use polkadot_primitives::{parachain, Block};
 use polkadot_runtime::{GenesisConfig, RuntimeApi};
 use primitives::ed25519;
 use tokio::runtime::TaskExecutor;
@@ -62,18 +73,24 @@ pub use service::config::full_version_from_strs;
 pub use client::{backend::Backend, runtime_api::Core as CoreApi, ExecutionStrategy};
 pub use polkadot_network::{PolkadotProtocol, NetworkService};
 pub use polkadot_primitives::parachain::ParachainHost;
-pub use primitives::{Blake2Hasher};
+pub use primitives::{Blake2Hasher, Bytes};
 pub use sr_primitives::traits::ProvideRuntimeApi;
 pub use chain_spec::ChainSpec;
+pub use builder::{NodeBuilder, default_parachain_rpc_http};
 
 /// All configuration for the polkadot node.
 pub type Configuration = FactoryFullConfiguration<Factory>;
 
 /// Polkadot-specific configuration.
 pub struct CustomConfiguration {
-	/// Set to `Some` with a collator `AccountId` and desired parachain
+	/// Set to `Some` with a collator key and desired parachain
 	/// if the network protocol should be started in collator mode.
-	pub collating_for: Option<(AccountId, parachain::Id)>,
+	pub collating_for: Option<(Arc<ed25519::Pair>, parachain::Id)>,
+
+	/// Address to serve the Polkadot-specific RPC API on over HTTP.
+	/// If `None`, the port after the substrate HTTP RPC port is used.
+	/// Only full nodes serve the API.
+	pub parachain_rpc_http: Option<SocketAddr>,
 
 	/// Intermediate state during setup. Will be removed in future. Set to `None`.
 	// FIXME: rather than putting this on the config, let's have an actual intermediate setup state
@@ -90,6 +107,7 @@ impl Default for CustomConfiguration {
 	fn default() -> Self {
 		Self {
 			collating_for: None,
+			parachain_rpc_http: None,
 			grandpa_import_setup: None,
 			inherent_data_providers: InherentDataProviders::new(),
 		}
@@ -152,11 +170,18 @@ impl PolkadotService for Service<LightComponents<Factory>> {
 	}
 }
 
+/// Start serving the Polkadot-specific RPC API for the service over HTTP.
+pub fn start_polkadot_rpc<S: PolkadotService>(addr: &SocketAddr, service: &S)
+	-> io::Result<polkadot_rpc::HttpServer>
+{
+	polkadot_rpc::start_http(addr, polkadot_rpc::rpc_handler(service.client()))
+}
+
 construct_service_factory! {
 	struct Factory {
 		Block = Block,
 		RuntimeApi = RuntimeApi,
-		NetworkProtocol = PolkadotProtocol { |config: &Configuration| Ok(PolkadotProtocol::new(config.custom.collating_for)) },
+		NetworkProtocol = PolkadotProtocol { |config: &Configuration| Ok(PolkadotProtocol::new(config.custom.collating_for.clone())) },
 		RuntimeDispatch = polkadot_executor::Executor,
 		FullTransactionPoolApi = TxChainApi<FullBackend<Self>, FullExecutor<Self>>
 			{ |config, client| Ok(TransactionPool::new(config, TxChainApi::new(client))) },
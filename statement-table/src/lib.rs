@@ -27,6 +27,7 @@ pub use generic::Table;
 
 use primitives::parachain::{
 	Id, CandidateReceipt, CandidateSignature as Signature, Statement as PrimitiveStatement,
+	MisbehaviorProof,
 };
 use primitives::{SessionKey, Hash};
 
@@ -85,3 +86,37 @@ impl From<Statement> for PrimitiveStatement {
 		}
 	}
 }
+
+impl From<Misbehavior> for MisbehaviorProof {
+	fn from(m: Misbehavior) -> MisbehaviorProof {
+		use generic::{ValidityDoubleVote, DoubleSign};
+
+		match m {
+			generic::Misbehavior::ValidityDoubleVote(vote) => {
+				let (a, sig_a, b, sig_b) = match vote {
+					ValidityDoubleVote::IssuedAndValidity((c, c_sig), (d, d_sig)) =>
+						(PrimitiveStatement::Candidate(c), c_sig, PrimitiveStatement::Valid(d), d_sig),
+					ValidityDoubleVote::IssuedAndInvalidity((c, c_sig), (d, d_sig)) =>
+						(PrimitiveStatement::Candidate(c), c_sig, PrimitiveStatement::Invalid(d), d_sig),
+					ValidityDoubleVote::ValidityAndInvalidity(d, valid_sig, invalid_sig) =>
+						(PrimitiveStatement::Valid(d.clone()), valid_sig, PrimitiveStatement::Invalid(d), invalid_sig),
+				};
+
+				MisbehaviorProof::ValidityDoubleVote(a, sig_a, b, sig_b)
+			}
+			generic::Misbehavior::MultipleCandidates(m) =>
+				MisbehaviorProof::MultipleCandidates(m.first.0, m.first.1, m.second.0, m.second.1),
+			generic::Misbehavior::UnauthorizedStatement(u) =>
+				MisbehaviorProof::UnauthorizedStatement(u.statement.statement.into(), u.statement.signature),
+			generic::Misbehavior::DoubleSign(double_sign) => {
+				let (statement, sig_a, sig_b) = match double_sign {
+					DoubleSign::Candidate(c, a, b) => (PrimitiveStatement::Candidate(c), a, b),
+					DoubleSign::Validity(d, a, b) => (PrimitiveStatement::Valid(d), a, b),
+					DoubleSign::Invalidity(d, a, b) => (PrimitiveStatement::Invalid(d), a, b),
+				};
+
+				MisbehaviorProof::DoubleSign(statement, sig_a, sig_b)
+			}
+		}
+	}
+}
@@ -0,0 +1,51 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The adder test parachain, registered at genesis and collated in-process.
+
+use std::path::Path;
+
+use adder_parachain::HeadData as AdderHead;
+use parachain::codec::{Encode, Decode};
+use service::chain_spec::{ChainSpec, TestnetSpec, GenesisParachain};
+
+pub use adder_collator::{AdderContext, GENESIS};
+
+/// The id the adder parachain is registered with.
+pub const ADDER_ID: u32 = 100;
+
+/// A chain spec with the given authorities and the adder parachain registered
+/// at genesis.
+pub fn chain_spec(authorities: &[&str]) -> Result<ChainSpec, String> {
+	let res = Path::new(env!("CARGO_MANIFEST_DIR")).join("../parachain/tests/res");
+	let spec = TestnetSpec {
+		name: "Adder Harness".into(),
+		id: "adder_harness".into(),
+		authorities: authorities.iter().map(|name| name.to_string()).collect(),
+		parachains: vec![GenesisParachain {
+			id: ADDER_ID,
+			wasm: "adder.wasm".into(),
+			genesis_head: GENESIS.encode().into(),
+		}],
+	};
+
+	spec.into_chain_spec(&res)
+}
+
+/// Decode a head of the adder parachain.
+pub fn decode_head(head: &[u8]) -> Option<AdderHead> {
+	AdderHead::decode(&mut &head[..])
+}
@@ -0,0 +1,234 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Harness running polkadot validators and collators in a single process.
+//!
+//! Each node is a full service with its own database. The network is closed to
+//! the outside: nodes listen on the loopback interface only, on ports chosen by
+//! the OS, and only connect to the other nodes of the network, which they reserve.
+//! This exercises the proposer, the shared table, collation fetching and the
+//! runtime together, as the unit tests of each crate cannot.
+
+#![warn(missing_docs)]
+
+extern crate adder as adder_parachain;
+extern crate adder_collator;
+extern crate exit_future;
+extern crate futures;
+extern crate polkadot_cli as cli;
+extern crate polkadot_collator as collator;
+extern crate polkadot_parachain as parachain;
+extern crate polkadot_primitives;
+extern crate polkadot_service as service;
+extern crate sr_primitives;
+extern crate substrate_client as client;
+extern crate substrate_primitives as primitives;
+extern crate tokio;
+
+#[macro_use]
+extern crate log;
+
+pub mod adder;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use client::ChainHead;
+use cli::Worker;
+use collator::{CollationNode, ParachainContext};
+use futures::Future;
+use polkadot_primitives::BlockId;
+use polkadot_primitives::parachain::Id as ParaId;
+use primitives::ed25519;
+use service::{
+	chain_spec::ChainSpec, Configuration, Factory, FullComponents, NodeBuilder, ParachainHost,
+	ProvideRuntimeApi, Roles, Service, ServiceFactory,
+};
+use sr_primitives::traits::Header as HeaderT;
+use tokio::runtime::Runtime;
+
+/// A full polkadot service.
+pub type FullService = Service<FullComponents<Factory>>;
+
+/// How often conditions are polled while waiting on the network.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long to wait for a node to bind its listening port.
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Distinguishes the directories of test networks within one process.
+static NEXT_NETWORK: AtomicUsize = AtomicUsize::new(0);
+
+/// A node of the test network.
+pub struct Node {
+	/// The name of the node.
+	pub name: String,
+	/// The node's service.
+	pub service: Arc<FullService>,
+}
+
+/// A network of validators and collators running in this process.
+///
+/// The nodes are stopped and their databases removed when it is dropped.
+pub struct TestNet {
+	spec: ChainSpec,
+	runtime: Option<Runtime>,
+	nodes: Vec<Node>,
+	exit: Option<exit_future::Signal>,
+	exit_future: exit_future::Exit,
+	root: PathBuf,
+}
+
+impl TestNet {
+	/// Start a validator for each of the given well-known keys, e.g. `Alice`.
+	pub fn new(spec: ChainSpec, validators: &[&str]) -> Result<Self, String> {
+		let root = ::std::env::temp_dir().join(format!(
+			"polkadot-test-harness-{}-{}",
+			::std::process::id(),
+			NEXT_NETWORK.fetch_add(1, Ordering::SeqCst),
+		));
+		let (exit, exit_future) = exit_future::signal();
+
+		let mut net = TestNet {
+			spec,
+			runtime: Some(Runtime::new().map_err(|e| e.to_string())?),
+			nodes: Vec::new(),
+			exit: Some(exit),
+			exit_future,
+			root,
+		};
+
+		for name in validators {
+			let config = net.node_builder(&name.to_lowercase())
+				.key(*name)
+				.roles(Roles::AUTHORITY)
+				.build()?;
+			net.start(config)?;
+		}
+
+		Ok(net)
+	}
+
+	/// Start a collator of the given parachain, collating with the given context.
+	pub fn add_collator<P>(&mut self, para_id: ParaId, context: P) -> Result<(), String> where
+		P: ParachainContext + Send + 'static,
+	{
+		let key = Arc::new(ed25519::Pair::from_seed(&[self.nodes.len() as u8 + 1; 32]));
+		let worker = CollationNode::new(context, para_id, self.exit_future.clone(), key);
+
+		let name = format!("collator-{}-{}", para_id.into_inner(), self.nodes.len());
+		let config = self.node_builder(&name).custom(worker.configuration()).build()?;
+
+		let service = self.start(config)?;
+		let work = worker.work(&*service);
+		self.runtime.as_mut().expect("runtime only taken on drop; qed").spawn(work);
+
+		Ok(())
+	}
+
+	/// The nodes of the network, validators first in the order they were given.
+	pub fn nodes(&self) -> &[Node] {
+		&self.nodes
+	}
+
+	/// The head of a parachain at the best block of the given node.
+	pub fn parachain_head(&self, node: usize, id: ParaId) -> Option<Vec<u8>> {
+		let client = self.nodes[node].service.client();
+		let best = client.best_block_header().ok()?.hash();
+		client.runtime_api().parachain_head(&BlockId::hash(best), id).ok()?
+	}
+
+	/// Wait until the condition holds, polling it until the timeout passes.
+	/// Returns whether the condition was met.
+	pub fn wait_for<F: Fn(&TestNet) -> bool>(&self, timeout: Duration, condition: F) -> bool {
+		let deadline = Instant::now() + timeout;
+		while Instant::now() < deadline {
+			if condition(self) {
+				return true;
+			}
+
+			thread::sleep(POLL_INTERVAL);
+		}
+
+		condition(self)
+	}
+
+	// the builder of the next node, listening locally on any free port.
+	fn node_builder(&self, name: &str) -> NodeBuilder {
+		NodeBuilder::new(self.spec.clone(), self.root.join(name))
+			.name(name)
+			.port(0)
+			.isolated()
+	}
+
+	// start a node and connect it to the nodes already running. nodes only
+	// accept reserved peers, so they reserve each other.
+	fn start(&mut self, config: Configuration) -> Result<Arc<FullService>, String> {
+		let name = config.name.clone();
+		let executor = self.runtime.as_ref().expect("runtime only taken on drop; qed").executor();
+		let service = Arc::new(Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?);
+		let node = Node { name, service: service.clone() };
+
+		let address = listen_address(&node)
+			.ok_or_else(|| format!("Test node {} is not listening", node.name))?;
+		for other in &self.nodes {
+			let other_address = listen_address(other)
+				.ok_or_else(|| format!("Test node {} is not listening", other.name))?;
+			service.network().add_reserved_peer(other_address)?;
+			other.service.network().add_reserved_peer(address.clone())?;
+		}
+
+		info!("Started test node {}", node.name);
+		self.nodes.push(node);
+		Ok(service)
+	}
+}
+
+// the address a node listens on, once it has bound its port.
+fn listen_address(node: &Node) -> Option<String> {
+	let deadline = Instant::now() + LISTEN_TIMEOUT;
+	loop {
+		if let Some(address) = node.service.network().node_id() {
+			return Some(address);
+		}
+
+		if Instant::now() >= deadline {
+			return None;
+		}
+
+		thread::sleep(POLL_INTERVAL);
+	}
+}
+
+impl Drop for TestNet {
+	fn drop(&mut self) {
+		if let Some(exit) = self.exit.take() {
+			exit.fire();
+		}
+
+		// services must be dropped before the runtime driving them is shut down.
+		self.nodes.clear();
+		if let Some(runtime) = self.runtime.take() {
+			let _ = runtime.shutdown_now().wait();
+		}
+
+		let _ = fs::remove_dir_all(&self.root);
+	}
+}
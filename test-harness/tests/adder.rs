@@ -0,0 +1,45 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The adder parachain advancing on a network of in-process validators.
+
+extern crate polkadot_test_harness as harness;
+
+use std::time::Duration;
+
+use harness::TestNet;
+use harness::adder::{self, AdderContext, ADDER_ID};
+
+const VALIDATORS: &[&str] = &["Alice", "Bob"];
+
+#[test]
+fn adder_heads_advance() {
+	let spec = adder::chain_spec(VALIDATORS).unwrap();
+	let mut net = TestNet::new(spec, VALIDATORS).unwrap();
+	net.add_collator(ADDER_ID.into(), AdderContext::default()).unwrap();
+
+	let head_number = |net: &TestNet, node| net.parachain_head(node, ADDER_ID.into())
+		.and_then(|head| adder::decode_head(&head))
+		.map(|head| head.number);
+
+	assert_eq!(head_number(&net, 0), Some(0));
+
+	let advanced = net.wait_for(Duration::from_secs(120), |net| {
+		(0..VALIDATORS.len()).all(|node| head_number(net, node).map_or(false, |n| n >= 2))
+	});
+	assert!(advanced, "adder heads did not advance on all validators: {:?}",
+		(0..VALIDATORS.len()).map(|node| head_number(&net, node)).collect::<Vec<_>>());
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Collation logic of the adder parachain, shared by its collator and the test harness.
+
+extern crate adder;
+extern crate polkadot_parachain as parachain;
+extern crate polkadot_primitives as primitives;
+extern crate polkadot_collator as collator;
+extern crate parking_lot;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use adder::{HeadData as AdderHead, BlockData as AdderBody};
+use parachain::codec::{Encode, Decode};
+use primitives::parachain::{HeadData, BlockData, Id as ParaId, Message};
+use collator::{InvalidHead, ParachainContext};
+use parking_lot::Mutex;
+
+/// The genesis head of the adder parachain.
+pub const GENESIS: AdderHead = AdderHead {
+	number: 0,
+	parent_hash: [0; 32],
+	post_state: [1, 27, 77, 3, 221, 140, 1, 241, 4, 145, 67, 207, 156, 76, 129, 126, 75, 22, 127, 29, 27, 131, 229, 198, 240, 241, 13, 137, 186, 30, 123, 206],
+};
+
+const GENESIS_BODY: AdderBody = AdderBody {
+	state: 0,
+	add: 0,
+};
+
+/// The parachain context, adding a fixed amount to the state in each block.
+#[derive(Clone, Default)]
+pub struct AdderContext {
+	db: Arc<Mutex<HashMap<AdderHead, AdderBody>>>,
+}
+
+impl ParachainContext for AdderContext {
+	fn produce_candidate<I: IntoIterator<Item=(ParaId, Message)>>(
+		&self,
+		last_head: HeadData,
+		ingress: I,
+	) -> Result<(BlockData, HeadData), InvalidHead>
+	{
+		let adder_head = AdderHead::decode(&mut &last_head.0[..])
+			.ok_or(InvalidHead)?;
+
+		let mut db = self.db.lock();
+
+		// bodies are only known for heads this context produced.
+		let last_body = if adder_head == GENESIS {
+			GENESIS_BODY
+		} else {
+			db.get(&adder_head).cloned().ok_or(InvalidHead)?
+		};
+
+		let next_body = AdderBody {
+			state: last_body.state.overflowing_add(last_body.add).0,
+			add: adder_head.number % 100,
+		};
+
+		let from_messages = ::adder::process_messages(
+			ingress.into_iter().map(|(_, msg)| msg.0)
+		);
+
+		let next_head = ::adder::execute(adder_head.hash(), adder_head, &next_body, from_messages)
+			.map_err(|_| InvalidHead)?;
+
+		let encoded_head = HeadData(next_head.encode());
+		let encoded_body = BlockData(next_body.encode());
+
+		println!("Created collation for #{}, post-state={}",
+			next_head.number, next_body.state.overflowing_add(next_body.add).0);
+
+		db.insert(next_head.clone(), next_body);
+		Ok((encoded_body, encoded_head))
+	}
+}
@@ -16,86 +16,23 @@
 
 //! Collator for polkadot
 
-extern crate adder;
+extern crate adder_collator;
 extern crate substrate_primitives;
 extern crate polkadot_parachain as parachain;
 extern crate polkadot_primitives as primitives;
 extern crate polkadot_collator as collator;
-extern crate parking_lot;
 extern crate ctrlc;
 extern crate futures;
 extern crate exit_future;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::sync::Arc;
 
-use adder::{HeadData as AdderHead, BlockData as AdderBody};
+use adder_collator::{AdderContext, GENESIS};
 use substrate_primitives::ed25519::Pair;
-use parachain::codec::{Encode, Decode};
-use primitives::parachain::{HeadData, BlockData, Id as ParaId, Message};
-use collator::{InvalidHead, ParachainContext, VersionInfo};
-use parking_lot::Mutex;
-
-const GENESIS: AdderHead = AdderHead {
-	number: 0,
-	parent_hash: [0; 32],
-	post_state: [1, 27, 77, 3, 221, 140, 1, 241, 4, 145, 67, 207, 156, 76, 129, 126, 75, 22, 127, 29, 27, 131, 229, 198, 240, 241, 13, 137, 186, 30, 123, 206],
-};
-
-const GENESIS_BODY: AdderBody = AdderBody {
-	state: 0,
-	add: 0,
-};
-
-#[derive(Clone)]
-struct AdderContext {
-	db: Arc<Mutex<HashMap<AdderHead, AdderBody>>>,
-}
-
-/// The parachain context.
-impl ParachainContext for AdderContext {
-	fn produce_candidate<I: IntoIterator<Item=(ParaId, Message)>>(
-		&self,
-		last_head: HeadData,
-		ingress: I,
-	) -> Result<(BlockData, HeadData), InvalidHead>
-	{
-		let adder_head = AdderHead::decode(&mut &last_head.0[..])
-			.ok_or(InvalidHead)?;
-
-		let mut db = self.db.lock();
-
-		let last_body = if adder_head == GENESIS {
-			GENESIS_BODY
-		} else {
-			db.get(&adder_head)
-				.expect("All past bodies stored since this is the only collator")
-				.clone()
-		};
-
-		let next_body = AdderBody {
-			state: last_body.state.overflowing_add(last_body.add).0,
-			add: adder_head.number % 100,
-		};
-
-		let from_messages = ::adder::process_messages(
-			ingress.into_iter().map(|(_, msg)| msg.0)
-		);
-
-		let next_head = ::adder::execute(adder_head.hash(), adder_head, &next_body, from_messages)
-			.expect("good execution params; qed");
-
-		let encoded_head = HeadData(next_head.encode());
-		let encoded_body = BlockData(next_body.encode());
-
-		println!("Created collation for #{}, post-state={}",
-			next_head.number, next_body.state.overflowing_add(next_body.add).0);
-
-		db.insert(next_head.clone(), next_body);
-		Ok((encoded_body, encoded_head))
-	}
-}
+use parachain::codec::Encode;
+use primitives::parachain::Id as ParaId;
+use collator::VersionInfo;
 
 fn main() {
 	let key = Arc::new(Pair::from_seed(&[1; 32]));
@@ -124,11 +61,9 @@ fn main() {
 		}
 	}).expect("Errror setting up ctrl-c handler");
 
-	let context = AdderContext {
-		db: Arc::new(Mutex::new(HashMap::new())),
-	};
+	let context = AdderContext::default();
 
-	let res = ::collator::run_collator(
+	let res = ::collator::run_collator_with_args(
 		context,
 		id,
 		exit,
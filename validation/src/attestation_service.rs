@@ -34,9 +34,10 @@ use client::runtime_api::Core;
 use primitives::ed25519;
 use futures::prelude::*;
 use polkadot_primitives::{Block, BlockId};
-use polkadot_primitives::parachain::{CandidateReceipt, ParachainHost};
+use polkadot_primitives::parachain::{CandidateReceipt, ParachainHost, MisbehaviorReport};
 use extrinsic_store::Store as ExtrinsicStore;
 use runtime_primitives::traits::{ProvideRuntimeApi, Header as HeaderT};
+use transaction_pool::txpool::{Pool, ChainApi as PoolChainApi};
 
 use tokio::runtime::TaskExecutor;
 use tokio::runtime::current_thread::Runtime as LocalRuntime;
@@ -63,6 +64,29 @@ pub(crate) fn fetch_candidates<P: BlockBody<Block>>(client: &P, block: &BlockId)
 		.next())
 }
 
+// submit a misbehavior report to the transaction pool as an unsigned extrinsic,
+// to be validated against the state at the given block.
+fn submit_misbehavior_report<A>(pool: &Pool<A>, at: &BlockId, report: MisbehaviorReport)
+	where A: PoolChainApi<Block=Block>
+{
+	use codec::{Encode, Decode};
+	use polkadot_primitives::UncheckedExtrinsic;
+	use polkadot_runtime::{Call, ParachainsCall, UncheckedExtrinsic as RuntimeExtrinsic};
+
+	let offender = report.offender;
+	let extrinsic = RuntimeExtrinsic::new_unsigned(
+		Call::Parachains(ParachainsCall::report_misbehavior(report))
+	);
+
+	let extrinsic = UncheckedExtrinsic::decode(&mut extrinsic.encode().as_slice())
+		.expect("runtime extrinsics are encoded as opaque extrinsics; qed");
+
+	match pool.submit_one(at, extrinsic) {
+		Ok(_) => info!(target: "validation", "Submitted misbehavior report against {:?}", offender),
+		Err(e) => debug!(target: "validation", "Misbehavior report against {:?} not submitted: {:?}", offender, e),
+	}
+}
+
 // creates a task to prune redundant entries in availability store upon block finalization
 //
 // NOTE: this will need to be changed to finality notification rather than
@@ -102,9 +126,10 @@ pub(crate) struct ServiceHandle {
 }
 
 /// Create and start a new instance of the attestation service.
-pub(crate) fn start<C, N, P>(
+pub(crate) fn start<C, N, P, TxApi>(
 	client: Arc<P>,
 	parachain_validation: Arc<::ParachainValidation<C, N, P>>,
+	transaction_pool: Arc<Pool<TxApi>>,
 	thread_pool: TaskExecutor,
 	key: Arc<ed25519::Pair>,
 	extrinsic_store: ExtrinsicStore,
@@ -118,6 +143,7 @@ pub(crate) fn start<C, N, P>(
 		N: Network + Send + Sync + 'static,
 		N::TableRouter: Send + 'static,
 		<<N::TableRouter as TableRouter>::FetchIncoming as IntoFuture>::Future: Send + 'static,
+		TxApi: PoolChainApi<Block=Block> + Send + Sync + 'static,
 {
 	const TIMER_DELAY: Duration = Duration::from_secs(5);
 	const TIMER_INTERVAL: Duration = Duration::from_secs(30);
@@ -151,6 +177,14 @@ pub(crate) fn start<C, N, P>(
 							warn!("Unable to start parachain validation on top of {:?}: {}",
 								parent_hash, e);
 						}
+
+						// the session on top of the imported block's parent is now over,
+						// so report any misbehavior witnessed during it.
+						let at = BlockId::hash(parent_hash);
+						let reports = validation.misbehavior_reports(notification.header.parent_hash());
+						for report in reports {
+							submit_misbehavior_report(&*transaction_pool, &at, report);
+						}
 					}
 					Ok(())
 				})
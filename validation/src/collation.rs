@@ -23,6 +23,7 @@ use std::sync::Arc;
 
 use polkadot_primitives::{Block, Hash, AccountId, BlockId};
 use polkadot_primitives::parachain::{Id as ParaId, Collation, Extrinsic, OutgoingMessage};
+use polkadot_primitives::parachain::{MAX_MESSAGE_SIZE, MAX_MESSAGES_PER_ROUTE};
 use polkadot_primitives::parachain::{CandidateReceipt, ParachainHost};
 use runtime_primitives::traits::ProvideRuntimeApi;
 use parachain::{wasm_executor::{self, ExternalitiesError}, MessageRef};
@@ -50,6 +51,9 @@ pub trait Collators: Clone {
 
 	/// Note a bad collator. TODO: take proof
 	fn note_bad_collator(&self, collator: AccountId);
+
+	/// Note a collator whose collation was found valid.
+	fn note_good_collator(&self, collator: AccountId);
 }
 
 /// A future which resolves when a collation is available.
@@ -116,6 +120,7 @@ impl<C: Collators, P: ProvideRuntimeApi> Future for CollationFetch<C, P>
 
 			match validate_collation(&*self.client, &self.relay_parent, &x, &self.incoming) {
 				Ok(e) => {
+					self.collators.note_good_collator(x.receipt.collator);
 					return Ok(Async::Ready((x, e)))
 				}
 				Err(e) => {
@@ -155,6 +160,10 @@ error_chain! {
 			description("Missing or extra egress route."),
 			display("Missing or extra egress route. (expected: {:?}, got {:?})", expected, got),
 		}
+		FeesNotCovered(expected: u64, got: u64) {
+			description("Candidate fees do not cover the cost of posted messages."),
+			display("Candidate fees do not cover the cost of posted messages. (expected at least: {}, got {})", expected, got),
+		}
 		WrongHeadData(expected: Vec<u8>, got: Vec<u8>) {
 			description("Parachain validation produced wrong head data."),
 			display("Parachain validation produced wrong head data (expected: {:?}, got {:?}", expected, got),
@@ -244,17 +253,27 @@ fn check_extrinsic(
 struct Externalities {
 	parachain_index: ParaId,
 	outgoing: Vec<OutgoingMessage>,
+	message_fees: u64,
 }
 
 impl wasm_executor::Externalities for Externalities {
 	fn post_message(&mut self, message: MessageRef) -> Result<(), ExternalitiesError> {
-		// TODO: https://github.com/paritytech/polkadot/issues/92
-		// check per-message and per-byte fees for the parachain.
 		let target: ParaId = message.target.into();
 		if target == self.parachain_index {
 			return Err(ExternalitiesError::CannotPostMessage("posted message to self"));
 		}
 
+		if message.data.len() > MAX_MESSAGE_SIZE {
+			return Err(ExternalitiesError::CannotPostMessage("message exceeds maximum size"));
+		}
+
+		if self.outgoing.iter().filter(|o| o.target == target).count() >= MAX_MESSAGES_PER_ROUTE {
+			return Err(ExternalitiesError::CannotPostMessage("too many messages posted to one parachain"));
+		}
+
+		self.message_fees = self.message_fees
+			.saturating_add(::polkadot_primitives::parachain::message_fees(Some(message.data)));
+
 		self.outgoing.push(OutgoingMessage {
 			target,
 			data: message.data.to_vec(),
@@ -270,6 +289,12 @@ impl Externalities {
 		self,
 		candidate: &CandidateReceipt,
 	) -> Result<Extrinsic, Error> {
+		// the fees are charged from the parachain's relay chain balance
+		// when the candidate is included.
+		if candidate.fees < self.message_fees {
+			return Err(ErrorKind::FeesNotCovered(self.message_fees, candidate.fees).into());
+		}
+
 		check_extrinsic(
 			self.outgoing,
 			&candidate.egress_queue_roots[..],
@@ -317,6 +342,7 @@ pub fn validate_collation<P>(
 	let mut ext = Externalities {
 		parachain_index: collation.receipt.parachain_index.clone(),
 		outgoing: Vec::new(),
+		message_fees: 0,
 	};
 
 	match wasm_executor::validate_candidate(&validation_code, params, &mut ext) {
@@ -388,9 +414,34 @@ mod tests {
 		let mut ext = Externalities {
 			parachain_index: 5.into(),
 			outgoing: Vec::new(),
+			message_fees: 0,
 		};
 
 		assert!(ext.post_message(MessageRef { target: 1.into(), data: &[] }).is_ok());
 		assert!(ext.post_message(MessageRef { target: 5.into(), data: &[] }).is_err());
 	}
+
+	#[test]
+	fn ext_enforces_message_limits() {
+		let mut ext = Externalities {
+			parachain_index: 5.into(),
+			outgoing: Vec::new(),
+			message_fees: 0,
+		};
+
+		let oversized = vec![0; MAX_MESSAGE_SIZE + 1];
+		assert!(ext.post_message(MessageRef { target: 1.into(), data: &oversized[..] }).is_err());
+
+		for _ in 0..MAX_MESSAGES_PER_ROUTE {
+			assert!(ext.post_message(MessageRef { target: 1.into(), data: &[1, 2, 3] }).is_ok());
+		}
+
+		assert!(ext.post_message(MessageRef { target: 1.into(), data: &[1, 2, 3] }).is_err());
+		assert!(ext.post_message(MessageRef { target: 2.into(), data: &[1, 2, 3] }).is_ok());
+
+		let expected_fees = ::polkadot_primitives::parachain::message_fees(
+			::std::iter::repeat(&[1u8, 2, 3][..]).take(MAX_MESSAGES_PER_ROUTE + 1)
+		);
+		assert_eq!(ext.message_fees, expected_fees);
+	}
 }
@@ -78,7 +78,7 @@ use polkadot_primitives::{Hash, Block, BlockId, BlockNumber, Header, SessionKey}
 use polkadot_primitives::parachain::{
 	Id as ParaId, Chain, DutyRoster, BlockData, Extrinsic as ParachainExtrinsic, CandidateReceipt,
 	CandidateSignature, ParachainHost, AttestedCandidate, Statement as PrimitiveStatement, Message,
-	OutgoingMessage,
+	OutgoingMessage, MisbehaviorReport,
 };
 use primitives::{Ed25519AuthorityId as AuthorityId, ed25519};
 use runtime_primitives::{traits::{ProvideRuntimeApi, Header as HeaderT}, ApplyError};
@@ -172,10 +172,14 @@ pub trait Network {
 
 	/// Instantiate a table router using the given shared table.
 	/// Also pass through any outgoing messages to be broadcast to peers.
+	///
+	/// `upcoming_authors` are the authorities expected to author the next few blocks.
+	/// They need to learn about candidates and their validity in order to include them.
 	fn communication_for(
 		&self,
 		table: Arc<SharedTable>,
 		outgoing: Outgoing,
+		upcoming_authors: Vec<SessionKey>,
 	) -> Self::TableRouter;
 }
 
@@ -272,6 +276,8 @@ struct ParachainValidation<C, N, P> {
 	/// Live agreements. Maps relay chain parent hashes to attestation
 	/// instances.
 	live_instances: Mutex<HashMap<Hash, Arc<AttestationTracker>>>,
+	/// Duration of an aura slot, for determining block authors.
+	aura_slot_duration: SlotDuration,
 }
 
 impl<C, N, P> ParachainValidation<C, N, P> where
@@ -343,10 +349,17 @@ impl<C, N, P> ParachainValidation<C, N, P> where
 
 		debug!(target: "validation", "Active parachains: {:?}", active_parachains);
 
+		let upcoming_authors = upcoming_slot_authors(
+			authorities,
+			self.aura_slot_duration.get(),
+			current_timestamp(),
+		);
+
 		let table = Arc::new(SharedTable::new(group_info, sign_with.clone(), parent_hash, self.extrinsic_store.clone()));
 		let router = self.network.communication_for(
 			table.clone(),
 			outgoing,
+			upcoming_authors,
 		);
 
 		let drop_signal = match local_duty.validation {
@@ -369,6 +382,14 @@ impl<C, N, P> ParachainValidation<C, N, P> where
 		Ok(tracker)
 	}
 
+	/// Get reports of misbehavior witnessed in the validation session on top of
+	/// the given parent hash, if any.
+	fn misbehavior_reports(&self, parent_hash: &Hash) -> Vec<MisbehaviorReport> {
+		self.live_instances.lock().get(parent_hash)
+			.map(|tracker| tracker.table.misbehavior_reports())
+			.unwrap_or_default()
+	}
+
 	/// Retain validation sessions matching predicate.
 	fn retain<F: FnMut(&Hash) -> bool>(&self, mut pred: F) {
 		self.live_instances.lock().retain(|k, _| pred(k))
@@ -469,7 +490,7 @@ impl<C, N, P, TxApi> ProposerFactory<C, N, P, TxApi> where
 	N: Network + Send + Sync + 'static,
 	N::TableRouter: Send + 'static,
 	<<N::TableRouter as TableRouter>::FetchIncoming as IntoFuture>::Future: Send + 'static,
-	TxApi: PoolChainApi,
+	TxApi: PoolChainApi<Block=Block> + Send + Sync + 'static,
 {
 	/// Create a new proposer factory.
 	pub fn new(
@@ -489,11 +510,13 @@ impl<C, N, P, TxApi> ProposerFactory<C, N, P, TxApi> where
 			handle: thread_pool.clone(),
 			extrinsic_store: extrinsic_store.clone(),
 			live_instances: Mutex::new(HashMap::new()),
+			aura_slot_duration,
 		});
 
 		let service_handle = ::attestation_service::start(
 			client,
 			parachain_validation.clone(),
+			transaction_pool.clone(),
 			thread_pool,
 			key.clone(),
 			extrinsic_store,
@@ -632,6 +655,30 @@ impl<C, TxApi> consensus::Proposer<Block> for Proposer<C, TxApi> where
 	}
 }
 
+// number of slots, starting with the one at the local time, whose authors learn about
+// candidates. covering several slots tolerates clock drift between nodes and authors
+// missing their slot.
+const UPCOMING_AUTHOR_SLOTS: u64 = 3;
+
+// the distinct authorities of the `UPCOMING_AUTHOR_SLOTS` slots starting with the one
+// at `timestamp`, following aura's round-robin assignment of slots.
+fn upcoming_slot_authors(authorities: &[AuthorityId], slot_duration: u64, timestamp: u64) -> Vec<AuthorityId> {
+	let mut authors = Vec::new();
+	if authorities.is_empty() || slot_duration == 0 {
+		return authors
+	}
+
+	let current_slot = timestamp / slot_duration;
+	for slot in current_slot..current_slot + UPCOMING_AUTHOR_SLOTS {
+		let author = &authorities[(slot % authorities.len() as u64) as usize];
+		if !authors.contains(author) {
+			authors.push(author.clone());
+		}
+	}
+
+	authors
+}
+
 fn current_timestamp() -> u64 {
 	time::SystemTime::now().duration_since(time::UNIX_EPOCH)
 		.expect("now always later than unix epoch; qed")
@@ -810,4 +857,26 @@ mod tests {
 		assert!(!check_statement(&statement, &sig, Keyring::Alice.to_raw_public().into(), &[0xff; 32].into()));
 		assert!(!check_statement(&statement, &sig, Keyring::Bob.to_raw_public().into(), &parent_hash));
 	}
+
+	#[test]
+	fn upcoming_slot_authors_are_round_robin() {
+		let authorities: Vec<AuthorityId> = vec![
+			Keyring::Alice.to_raw_public().into(),
+			Keyring::Bob.to_raw_public().into(),
+			Keyring::Charlie.to_raw_public().into(),
+			Keyring::Dave.to_raw_public().into(),
+		];
+
+		// slot 3 at timestamp 18 with 6 second slots.
+		let expected = vec![authorities[3].clone(), authorities[0].clone(), authorities[1].clone()];
+		assert_eq!(upcoming_slot_authors(&authorities, 6, 18), expected);
+		assert_eq!(upcoming_slot_authors(&authorities, 6, 23), expected);
+		assert_eq!(upcoming_slot_authors(&authorities, 6, 24)[0], authorities[0]);
+
+		// authors of several upcoming slots are only listed once.
+		assert_eq!(upcoming_slot_authors(&authorities[..2], 6, 18), vec![authorities[1].clone(), authorities[0].clone()]);
+
+		assert!(upcoming_slot_authors(&[], 6, 24).is_empty());
+		assert!(upcoming_slot_authors(&authorities, 0, 24).is_empty());
+	}
 }
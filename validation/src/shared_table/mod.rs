@@ -25,7 +25,7 @@ use table::{self, Table, Context as TableContextTrait};
 use polkadot_primitives::{Block, BlockId, Hash, SessionKey};
 use polkadot_primitives::parachain::{
 	Id as ParaId, BlockData, Collation, Extrinsic, CandidateReceipt,
-	AttestedCandidate, ParachainHost
+	AttestedCandidate, ParachainHost, MisbehaviorReport,
 };
 
 use parking_lot::Mutex;
@@ -404,6 +404,11 @@ impl SharedTable {
 		self.context.local_id()
 	}
 
+	/// Get the key pair statements in this table are signed with.
+	pub fn session_key_pair(&self) -> Arc<ed25519::Pair> {
+		self.context.key.clone()
+	}
+
 	/// Get group info.
 	pub fn group_info(&self) -> &HashMap<ParaId, GroupInfo> {
 		&self.context.groups
@@ -522,6 +527,16 @@ impl SharedTable {
 		self.inner.lock().table.get_misbehavior().clone()
 	}
 
+	/// Get reports of all witnessed misbehavior, suitable for submission to the
+	/// relay chain.
+	pub fn misbehavior_reports(&self) -> Vec<MisbehaviorReport> {
+		self.get_misbehavior().into_iter().map(|(offender, misbehavior)| MisbehaviorReport {
+			parent_hash: self.context.parent_hash.clone(),
+			offender,
+			proof: misbehavior.into(),
+		}).collect()
+	}
+
 	/// Track includability  of a given set of candidate hashes.
 	pub fn track_includability<I>(&self, iterable: I) -> Includable
 		where I: IntoIterator<Item=Hash>